@@ -5,9 +5,10 @@ mod remote;
 
 use crate::error::Error;
 use crate::local::collect_from_file;
-use crate::raw::{PrizePage, PrizeRecord};
+use crate::raw::{PrizeGradeType, PrizePage, PrizeRecord};
 use crate::remote::collect_from_network;
-use ssq_tool_domain::PrBusinessObj;
+use itertools::Itertools;
+use ssq_tool_domain::{PrBusinessObj, PrizeGrade, PrizeGradeDetail};
 use tracing::info;
 
 pub enum Collector {
@@ -49,6 +50,7 @@ fn collect_business_obj(
             blue,
             week,
             sales,
+            pool_money,
             prize_grades,
             ..
         } = prize_record;
@@ -62,6 +64,30 @@ fn collect_business_obj(
             red[5].try_into()?,
         ];
         let blue_ball = blue.try_into()?;
+        let total_prized_tickets = prize_grades
+            .values()
+            .map(|grade_obj| grade_obj.prize_type_number as u64)
+            .sum();
+        let prize_grades = prize_grades
+            .values()
+            .filter_map(|grade_obj| {
+                let grade = match grade_obj.prize_type {
+                    PrizeGradeType::Level1 => PrizeGrade::First,
+                    PrizeGradeType::Level2 => PrizeGrade::Second,
+                    PrizeGradeType::Level3 => PrizeGrade::Third,
+                    PrizeGradeType::Level4 => PrizeGrade::Fourth,
+                    PrizeGradeType::Level5 => PrizeGrade::Fifth,
+                    PrizeGradeType::Level6 => PrizeGrade::Sixth,
+                    PrizeGradeType::Level7 => return None,
+                };
+                Some(PrizeGradeDetail {
+                    grade,
+                    winning_tickets: grade_obj.prize_type_number as u64,
+                    money: parse_leading_number(&grade_obj.prize_type_money),
+                })
+            })
+            .sorted_by_key(|detail| detail.grade)
+            .collect();
         let business_obj = PrBusinessObj {
             code,
             seq,
@@ -70,10 +96,9 @@ fn collect_business_obj(
             red_balls,
             day,
             total_tickets: sales / 2,
-            total_prized_tickets: prize_grades
-                .values()
-                .map(|grade_obj| grade_obj.prize_type_number as u64)
-                .sum(),
+            total_prized_tickets,
+            prize_grades,
+            pool_money: parse_leading_number(&pool_money),
         };
         business_objs.push(business_obj);
         seq += 1;
@@ -84,3 +109,13 @@ fn collect_business_obj(
     info!("共收集{}条中奖记录...", business_objs.len());
     Ok(business_objs)
 }
+
+/// 解析金额字符串开头的数字，例如 `5250000（含加奖250000）` 解析为 `5250000`
+fn parse_leading_number(value: &str) -> u64 {
+    let digits = value
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>();
+    digits.parse::<u64>().unwrap_or(0)
+}
@@ -17,7 +17,7 @@ where
     for prize_grade in data.values() {
         prize_grades.push(prize_grade);
     }
-    prize_grades.sort_by(|v1, v2| v1.prize_type.cmp(&v2.prize_type));
+    prize_grades.sort_by_key(|v| v.prize_type);
 
     let prize_grades_string =
         serde_json::to_string(&prize_grades).map_err(serde::ser::Error::custom)?;
@@ -1,12 +1,28 @@
 use chrono::NaiveDate;
 use derive_more::TryFrom;
 use derive_more::{Display, From};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Formatter};
 use strum::{EnumCount, EnumIter};
 
+mod ticket;
+
+pub use ticket::{Ticket, TicketCheck, TicketParseError};
+
 #[derive(
-    Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Display, From, Deserialize, Serialize,
+    Hash,
+    Eq,
+    PartialEq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Copy,
+    Display,
+    From,
+    Deserialize,
+    Serialize,
+    JsonSchema,
 )]
 #[serde(rename = "双色球")]
 pub enum Ball {
@@ -38,6 +54,7 @@ impl Debug for Ball {
     EnumCount,
     Serialize,
     Deserialize,
+    JsonSchema,
 )]
 #[try_from(repr)]
 #[repr(usize)]
@@ -113,6 +130,7 @@ impl Debug for BlueBall {
     EnumCount,
     Serialize,
     Deserialize,
+    JsonSchema,
 )]
 #[try_from(repr)]
 #[repr(usize)]
@@ -225,7 +243,82 @@ impl Debug for RedBall {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(
+    Hash,
+    Eq,
+    PartialEq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Copy,
+    Debug,
+    Display,
+    EnumIter,
+    EnumCount,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(rename = "奖级")]
+pub enum PrizeGrade {
+    #[display("一等奖")]
+    #[serde(rename = "一等奖")]
+    First,
+    #[display("二等奖")]
+    #[serde(rename = "二等奖")]
+    Second,
+    #[display("三等奖")]
+    #[serde(rename = "三等奖")]
+    Third,
+    #[display("四等奖")]
+    #[serde(rename = "四等奖")]
+    Fourth,
+    #[display("五等奖")]
+    #[serde(rename = "五等奖")]
+    Fifth,
+    #[display("六等奖")]
+    #[serde(rename = "六等奖")]
+    Sixth,
+}
+
+impl PrizeGrade {
+    /// 按照命中的红球个数与是否命中蓝球确定奖级
+    pub fn from_hits(red_hits: usize, blue_hit: bool) -> Option<Self> {
+        match (red_hits, blue_hit) {
+            (6, true) => Some(PrizeGrade::First),
+            (6, false) => Some(PrizeGrade::Second),
+            (5, true) => Some(PrizeGrade::Third),
+            (5, false) | (4, true) => Some(PrizeGrade::Fourth),
+            (4, false) | (3, true) => Some(PrizeGrade::Fifth),
+            (_, true) => Some(PrizeGrade::Sixth),
+            _ => None,
+        }
+    }
+
+    /// 固定奖级的单注奖金，一等奖与二等奖为浮动奖金
+    pub fn fixed_money(&self) -> Option<u64> {
+        match self {
+            PrizeGrade::First | PrizeGrade::Second => None,
+            PrizeGrade::Third => Some(3000),
+            PrizeGrade::Fourth => Some(200),
+            PrizeGrade::Fifth => Some(10),
+            PrizeGrade::Sixth => Some(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "奖级详情")]
+pub struct PrizeGradeDetail {
+    #[serde(rename = "奖级")]
+    pub grade: PrizeGrade,
+    #[serde(rename = "中奖注数")]
+    pub winning_tickets: u64,
+    #[serde(rename = "单注奖金")]
+    pub money: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename = "中奖记录")]
 pub struct PrBusinessObj {
     #[serde(rename = "中奖期号")]
@@ -244,4 +337,30 @@ pub struct PrBusinessObj {
     pub total_tickets: u64,
     #[serde(rename = "中奖人数")]
     pub total_prized_tickets: u64,
+    #[serde(rename = "奖级详情", default)]
+    pub prize_grades: Vec<PrizeGradeDetail>,
+    /// 本期开奖后滚入下一期的奖池金额
+    #[serde(rename = "奖池金额", default)]
+    pub pool_money: u64,
+}
+
+impl PrBusinessObj {
+    /// 计算一注单式号码在本期的中奖奖级
+    pub fn check(&self, red_balls: &[RedBall], blue_ball: BlueBall) -> Option<PrizeGrade> {
+        let red_hits = red_balls
+            .iter()
+            .filter(|ball| self.red_balls.contains(ball))
+            .count();
+        PrizeGrade::from_hits(red_hits, blue_ball == self.blue_ball)
+    }
+
+    /// 本期指定奖级的单注奖金，优先使用官方公布的奖金，没有公布时使用固定奖金
+    pub fn prize_money(&self, grade: PrizeGrade) -> u64 {
+        self.prize_grades
+            .iter()
+            .find(|detail| detail.grade == grade && detail.money > 0)
+            .map(|detail| detail.money)
+            .or(grade.fixed_money())
+            .unwrap_or(0)
+    }
 }
@@ -0,0 +1,514 @@
+use crate::{BlueBall, PrBusinessObj, PrizeGrade, RedBall};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use strum::EnumCount;
+use thiserror::Error;
+
+/// 每注红球个数
+const RED_BALLS_PER_BET: usize = 6;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TicketParseError {
+    #[error("号码缺少蓝球，格式应为 `红球+蓝球`：{0}")]
+    MissingBlueBall(String),
+    #[error("无效的号码：{0}")]
+    InvalidBall(String),
+    #[error("号码重复：{0}")]
+    DuplicateBall(String),
+    #[error("红球个数不符合投注规则：{0}")]
+    InvalidRedBallCount(String),
+    #[error("蓝球个数不符合投注规则：{0}")]
+    InvalidBlueBallCount(String),
+}
+
+/// 一张彩票，单式是红球为 6 个、蓝球为 1 个的复式。反序列化时与解析字符串一样校验投注规则
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "彩票", try_from = "UncheckedTicket")]
+pub enum Ticket {
+    /// 复式：6 到 20 个红球，1 到 16 个蓝球
+    #[serde(rename = "复式")]
+    Compound {
+        #[serde(rename = "红球")]
+        red_balls: Vec<RedBall>,
+        #[serde(rename = "蓝球")]
+        blue_balls: Vec<BlueBall>,
+    },
+    /// 胆拖：1 到 5 个胆码，胆码与拖码合计至少 7 个红球
+    #[serde(rename = "胆拖")]
+    Banker {
+        #[serde(rename = "胆码")]
+        bankers: Vec<RedBall>,
+        #[serde(rename = "拖码")]
+        draggers: Vec<RedBall>,
+        #[serde(rename = "蓝球")]
+        blue_balls: Vec<BlueBall>,
+    },
+}
+
+/// 没有经过校验的彩票，只用于反序列化
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename = "彩票")]
+enum UncheckedTicket {
+    /// 复式：6 到 20 个红球，1 到 16 个蓝球
+    #[serde(rename = "复式")]
+    Compound {
+        #[serde(rename = "红球")]
+        red_balls: Vec<RedBall>,
+        #[serde(rename = "蓝球")]
+        blue_balls: Vec<BlueBall>,
+    },
+    /// 胆拖：1 到 5 个胆码，胆码与拖码合计至少 7 个红球
+    #[serde(rename = "胆拖")]
+    Banker {
+        #[serde(rename = "胆码")]
+        bankers: Vec<RedBall>,
+        #[serde(rename = "拖码")]
+        draggers: Vec<RedBall>,
+        #[serde(rename = "蓝球")]
+        blue_balls: Vec<BlueBall>,
+    },
+}
+
+impl TryFrom<UncheckedTicket> for Ticket {
+    type Error = TicketParseError;
+
+    fn try_from(ticket: UncheckedTicket) -> Result<Self, Self::Error> {
+        let ticket = match ticket {
+            UncheckedTicket::Compound {
+                red_balls,
+                blue_balls,
+            } => Ticket::Compound {
+                red_balls,
+                blue_balls,
+            },
+            UncheckedTicket::Banker {
+                bankers,
+                draggers,
+                blue_balls,
+            } => Ticket::Banker {
+                bankers,
+                draggers,
+                blue_balls,
+            },
+        };
+        let description = ticket.to_string();
+        ticket.validate(&description)
+    }
+}
+
+/// 对号码排序，有重复的号码时返回 `false`
+fn sort_unique<T: Ord>(balls: &mut [T]) -> bool {
+    balls.sort();
+    balls.windows(2).all(|pair| pair[0] != pair[1])
+}
+
+fn combinations(n: usize, k: usize) -> u64 {
+    if k > n {
+        return 0;
+    }
+    (0..k).fold(1, |acc, i| acc * (n - i) as u64 / (i + 1) as u64)
+}
+
+impl Ticket {
+    pub fn single(red_balls: Vec<RedBall>, blue_ball: BlueBall) -> Self {
+        Ticket::Compound {
+            red_balls,
+            blue_balls: vec![blue_ball],
+        }
+    }
+
+    pub fn blue_balls(&self) -> &[BlueBall] {
+        match self {
+            Ticket::Compound { blue_balls, .. } | Ticket::Banker { blue_balls, .. } => blue_balls,
+        }
+    }
+
+    /// 按照投注规则校验号码个数与重复的号码，并把号码按照从小到大排列，`ticket` 用于错误信息
+    fn validate(mut self, ticket: &str) -> Result<Self, TicketParseError> {
+        let unique = match &mut self {
+            Ticket::Compound {
+                red_balls,
+                blue_balls,
+            } => sort_unique(red_balls) && sort_unique(blue_balls),
+            Ticket::Banker {
+                bankers,
+                draggers,
+                blue_balls,
+            } => {
+                sort_unique(bankers)
+                    && sort_unique(draggers)
+                    && sort_unique(blue_balls)
+                    && !bankers.iter().any(|ball| draggers.contains(ball))
+            }
+        };
+        if !unique {
+            return Err(TicketParseError::DuplicateBall(ticket.to_string()));
+        }
+        if self.blue_balls().is_empty() {
+            return Err(TicketParseError::InvalidBlueBallCount(ticket.to_string()));
+        }
+        let valid_red_ball_count = match &self {
+            Ticket::Compound { red_balls, .. } => {
+                (RED_BALLS_PER_BET..=20).contains(&red_balls.len())
+            }
+            Ticket::Banker {
+                bankers, draggers, ..
+            } => {
+                (1..RED_BALLS_PER_BET).contains(&bankers.len())
+                    && (RED_BALLS_PER_BET + 1..=RedBall::COUNT)
+                        .contains(&(bankers.len() + draggers.len()))
+            }
+        };
+        if !valid_red_ball_count {
+            return Err(TicketParseError::InvalidRedBallCount(ticket.to_string()));
+        }
+        Ok(self)
+    }
+
+    /// 彩票包含的单式注数
+    pub fn bet_count(&self) -> u64 {
+        let red_combinations = match self {
+            Ticket::Compound { red_balls, .. } => combinations(red_balls.len(), RED_BALLS_PER_BET),
+            Ticket::Banker {
+                bankers, draggers, ..
+            } => RED_BALLS_PER_BET
+                .checked_sub(bankers.len())
+                .map_or(0, |dragger_picks| {
+                    combinations(draggers.len(), dragger_picks)
+                }),
+        };
+        red_combinations * self.blue_balls().len() as u64
+    }
+
+    /// 彩票金额，每注 2 元
+    pub fn cost(&self) -> u64 {
+        self.bet_count() * 2
+    }
+
+    /// 与给定开奖结果比较，按照命中的胆码、拖码个数直接计算各奖级注数，不需要展开所有单式
+    pub fn check(&self, draw: &PrBusinessObj) -> TicketCheck {
+        let hits = |balls: &[RedBall]| {
+            balls
+                .iter()
+                .filter(|ball| draw.red_balls.contains(ball))
+                .copied()
+                .collect::<Vec<RedBall>>()
+        };
+        let (bankers, draggers) = match self {
+            Ticket::Compound { red_balls, .. } => (&[][..], &red_balls[..]),
+            Ticket::Banker {
+                bankers, draggers, ..
+            } => (&bankers[..], &draggers[..]),
+        };
+        let banker_hits = hits(bankers);
+        let dragger_hits = hits(draggers);
+        let blue_hit = self.blue_balls().contains(&draw.blue_ball);
+        let blue_miss_count = self.blue_balls().len() as u64 - blue_hit as u64;
+        let mut grade_counts = BTreeMap::<PrizeGrade, u64>::new();
+        // 胆码多于一注的红球个数时不构成任何一注
+        if let Some(dragger_picks) = RED_BALLS_PER_BET.checked_sub(bankers.len()) {
+            for dragger_hit_count in 0..=dragger_hits.len().min(dragger_picks) {
+                let red_combinations = combinations(dragger_hits.len(), dragger_hit_count)
+                    * combinations(
+                        draggers.len() - dragger_hits.len(),
+                        dragger_picks - dragger_hit_count,
+                    );
+                if red_combinations == 0 {
+                    continue;
+                }
+                let red_hits = banker_hits.len() + dragger_hit_count;
+                for (hit, count) in [(true, blue_hit as u64), (false, blue_miss_count)] {
+                    if let Some(grade) = PrizeGrade::from_hits(red_hits, hit)
+                        && count > 0
+                    {
+                        *grade_counts.entry(grade).or_default() += red_combinations * count;
+                    }
+                }
+            }
+        }
+        let payout = grade_counts
+            .iter()
+            .map(|(grade, count)| draw.prize_money(*grade) * count)
+            .sum();
+        TicketCheck {
+            code: draw.code.clone(),
+            matched_red_balls: banker_hits.into_iter().chain(dragger_hits).collect(),
+            matched_blue_ball: blue_hit.then_some(draw.blue_ball),
+            grade_counts,
+            payout,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "兑奖结果")]
+pub struct TicketCheck {
+    #[serde(rename = "中奖期号")]
+    pub code: String,
+    #[serde(rename = "命中红球")]
+    pub matched_red_balls: Vec<RedBall>,
+    #[serde(rename = "命中蓝球")]
+    pub matched_blue_ball: Option<BlueBall>,
+    #[serde(rename = "各奖级注数")]
+    pub grade_counts: BTreeMap<PrizeGrade, u64>,
+    #[serde(rename = "奖金")]
+    pub payout: u64,
+}
+
+impl TicketCheck {
+    /// 中奖的最高奖级
+    pub fn best_grade(&self) -> Option<PrizeGrade> {
+        self.grade_counts.keys().next().copied()
+    }
+}
+
+fn parse_balls<T: TryFrom<usize>>(value: &str, ticket: &str) -> Result<Vec<T>, TicketParseError> {
+    value
+        .split(|c: char| c == ',' || c == '，' || c.is_whitespace())
+        .filter(|ball| !ball.is_empty())
+        .map(|ball| {
+            ball.parse::<usize>()
+                .ok()
+                .and_then(|ball| T::try_from(ball).ok())
+                .ok_or(TicketParseError::InvalidBall(ticket.to_string()))
+        })
+        .collect()
+}
+
+/// 支持以下格式，号码之间可以使用逗号或空格分隔：
+/// - 单式或复式：`01,02,03,04,05,06+07`、`01,02,03,04,05,06,07+08,09`
+/// - 胆拖：`01,02#03,04,05,06,07+08`，`#` 之前为胆码
+impl FromStr for Ticket {
+    type Err = TicketParseError;
+
+    fn from_str(ticket: &str) -> Result<Self, Self::Err> {
+        let (red_part, blue_part) = ticket
+            .trim()
+            .split_once('+')
+            .ok_or(TicketParseError::MissingBlueBall(ticket.to_string()))?;
+        let blue_balls = parse_balls::<BlueBall>(blue_part, ticket)?;
+        match red_part.split_once('#') {
+            Some((banker_part, dragger_part)) => Ticket::Banker {
+                bankers: parse_balls::<RedBall>(banker_part, ticket)?,
+                draggers: parse_balls::<RedBall>(dragger_part, ticket)?,
+                blue_balls,
+            },
+            None => Ticket::Compound {
+                red_balls: parse_balls::<RedBall>(red_part, ticket)?,
+                blue_balls,
+            },
+        }
+        .validate(ticket)
+    }
+}
+
+impl Display for Ticket {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fn join(balls: &[impl Display]) -> String {
+            balls
+                .iter()
+                .map(|ball| format!("{:0>2}", ball.to_string()))
+                .collect::<Vec<String>>()
+                .join(",")
+        }
+        match self {
+            Ticket::Compound {
+                red_balls,
+                blue_balls,
+            } => write!(f, "{}+{}", join(red_balls), join(blue_balls)),
+            Ticket::Banker {
+                bankers,
+                draggers,
+                blue_balls,
+            } => write!(
+                f,
+                "{}#{}+{}",
+                join(bankers),
+                join(draggers),
+                join(blue_balls)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn red_balls(values: &[usize]) -> Vec<RedBall> {
+        values
+            .iter()
+            .map(|value| RedBall::try_from(*value).unwrap())
+            .collect()
+    }
+
+    fn blue_ball(value: usize) -> BlueBall {
+        BlueBall::try_from(value).unwrap()
+    }
+
+    /// 开奖号码为 01,02,03,04,05,06+07，没有奖级详情时一、二等奖奖金为 0
+    fn draw() -> PrBusinessObj {
+        PrBusinessObj {
+            code: "2025001".to_string(),
+            seq: 0,
+            date: NaiveDate::from_ymd_opt(2025, 1, 2).unwrap(),
+            day: "四".to_string(),
+            blue_ball: blue_ball(7),
+            red_balls: red_balls(&[1, 2, 3, 4, 5, 6]).try_into().unwrap(),
+            total_tickets: 0,
+            total_prized_tickets: 0,
+            prize_grades: vec![],
+            pool_money: 0,
+        }
+    }
+
+    #[test]
+    fn parses_single_compound_and_banker_tickets() {
+        assert_eq!(
+            "06 05,04，03,02,01+07".parse::<Ticket>().unwrap(),
+            Ticket::single(red_balls(&[1, 2, 3, 4, 5, 6]), blue_ball(7))
+        );
+        assert_eq!(
+            "01,02,03,04,05,06,07+08,09".parse::<Ticket>().unwrap(),
+            Ticket::Compound {
+                red_balls: red_balls(&[1, 2, 3, 4, 5, 6, 7]),
+                blue_balls: vec![blue_ball(8), blue_ball(9)],
+            }
+        );
+        assert_eq!(
+            "02,01#03,04,05,06,07+08".parse::<Ticket>().unwrap(),
+            Ticket::Banker {
+                bankers: red_balls(&[1, 2]),
+                draggers: red_balls(&[3, 4, 5, 6, 7]),
+                blue_balls: vec![blue_ball(8)],
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_tickets_breaking_betting_rules() {
+        let error = |ticket: &str| ticket.parse::<Ticket>().unwrap_err();
+        assert!(matches!(
+            error("01,02,03,04,05,06"),
+            TicketParseError::MissingBlueBall(_)
+        ));
+        assert!(matches!(
+            error("01,02,03,04,05,34+07"),
+            TicketParseError::InvalidBall(_)
+        ));
+        assert!(matches!(
+            error("01,02,03,04,05,05+07"),
+            TicketParseError::DuplicateBall(_)
+        ));
+        assert!(matches!(
+            error("01,02#02,03,04,05,06+07"),
+            TicketParseError::DuplicateBall(_)
+        ));
+        assert!(matches!(
+            error("01,02,03,04,05+07"),
+            TicketParseError::InvalidRedBallCount(_)
+        ));
+        assert!(matches!(
+            error("01,02,03,04,05,06,07,08,09,10,11,12,13,14,15,16,17,18,19,20,21+07"),
+            TicketParseError::InvalidRedBallCount(_)
+        ));
+        assert!(matches!(
+            error("01,02,03,04,05,06#07,08+07"),
+            TicketParseError::InvalidRedBallCount(_)
+        ));
+        assert!(matches!(
+            error("01,02#03,04,05,06+07"),
+            TicketParseError::InvalidRedBallCount(_)
+        ));
+        assert!(matches!(
+            error("01,02,03,04,05,06+"),
+            TicketParseError::InvalidBlueBallCount(_)
+        ));
+    }
+
+    #[test]
+    fn deserialization_applies_betting_rules() {
+        let ticket = "01,02#03,04,05,06,07+08".parse::<Ticket>().unwrap();
+        let json = serde_json::to_string(&ticket).unwrap();
+        assert_eq!(serde_json::from_str::<Ticket>(&json).unwrap(), ticket);
+
+        let too_many_bankers = serde_json::json!({
+            "胆拖": {"胆码": ["1", "2", "3", "4", "5", "6", "7"], "拖码": ["8"], "蓝球": ["1"]}
+        });
+        let error = serde_json::from_value::<Ticket>(too_many_bankers).unwrap_err();
+        assert!(error.to_string().contains("红球个数不符合投注规则"));
+    }
+
+    #[test]
+    fn checks_single_ticket() {
+        let first = "01,02,03,04,05,06+07"
+            .parse::<Ticket>()
+            .unwrap()
+            .check(&draw());
+        assert_eq!(first.grade_counts, BTreeMap::from([(PrizeGrade::First, 1)]));
+        assert_eq!(first.matched_blue_ball, Some(blue_ball(7)));
+
+        let fourth = "01,02,03,04,05,10+08"
+            .parse::<Ticket>()
+            .unwrap()
+            .check(&draw());
+        assert_eq!(
+            fourth.grade_counts,
+            BTreeMap::from([(PrizeGrade::Fourth, 1)])
+        );
+        assert_eq!(fourth.matched_red_balls, red_balls(&[1, 2, 3, 4, 5]));
+        assert_eq!(fourth.payout, 200);
+
+        let missed = "10,11,12,13,14,15+08"
+            .parse::<Ticket>()
+            .unwrap()
+            .check(&draw());
+        assert!(missed.grade_counts.is_empty());
+        assert_eq!(missed.best_grade(), None);
+    }
+
+    #[test]
+    fn checks_compound_ticket() {
+        let ticket = "01,02,03,04,05,06,07+07,08".parse::<Ticket>().unwrap();
+        assert_eq!(ticket.bet_count(), 14);
+        let check = ticket.check(&draw());
+        assert_eq!(
+            check.grade_counts,
+            BTreeMap::from([
+                (PrizeGrade::First, 1),
+                (PrizeGrade::Second, 1),
+                (PrizeGrade::Third, 6),
+                (PrizeGrade::Fourth, 6),
+            ])
+        );
+        assert_eq!(check.grade_counts.values().sum::<u64>(), ticket.bet_count());
+        assert_eq!(check.payout, 6 * 3000 + 6 * 200);
+    }
+
+    #[test]
+    fn checks_banker_ticket() {
+        let ticket = "01,02#03,04,05,10,11+07".parse::<Ticket>().unwrap();
+        assert_eq!(ticket.bet_count(), 5);
+        let check = ticket.check(&draw());
+        assert_eq!(
+            check.grade_counts,
+            BTreeMap::from([(PrizeGrade::Third, 2), (PrizeGrade::Fourth, 3)])
+        );
+        assert_eq!(check.payout, 2 * 3000 + 3 * 200);
+    }
+
+    #[test]
+    fn unchecked_banker_ticket_does_not_overflow() {
+        let ticket = Ticket::Banker {
+            bankers: red_balls(&[1, 2, 3, 4, 5, 6, 7]),
+            draggers: red_balls(&[8]),
+            blue_balls: vec![blue_ball(7)],
+        };
+        assert_eq!(ticket.bet_count(), 0);
+        assert!(ticket.check(&draw()).grade_counts.is_empty());
+    }
+}
@@ -0,0 +1,311 @@
+use crate::error::Error;
+use crate::pipeline::PipelineConfig;
+use rmcp::schemars::{self, Schema, SchemaGenerator};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use ssq_tool_domain::{BlueBall, PrBusinessObj, RedBall};
+use ssq_tool_processor::association_rule::AssociationRule;
+use ssq_tool_processor::bayesian_ball_probability::BallPosteriors;
+use ssq_tool_processor::blue_ball_markov::BlueBallMarkovModel;
+use ssq_tool_processor::context::ProcessorContext;
+use ssq_tool_processor::expected_value::{DrawExpectedValue, ExpectedValue};
+use ssq_tool_processor::hot_cold_ball::BallTemperatureWindow;
+use ssq_tool_processor::portfolio::Portfolio;
+use ssq_tool_processor::progress::ProgressReporter;
+use ssq_tool_processor::randomness_test::RandomnessTestResult;
+use ssq_tool_processor::red_ball_shape::{DrawRedBallShape, RedBallShapeDistribution};
+use ssq_tool_processor::red_ball_structure::{DrawRedBallStructure, RedBallStructureDistribution};
+use ssq_tool_processor::strategy::ScoredTicket;
+use ssq_tool_processor::ticket_filter::{FilterReport, HistoricalFilterResult};
+use ssq_tool_processor::view::{BallOccurrenceView, BallRelationshipView, BlueBallFollowingView};
+use ssq_tool_processor::{
+    BALL_ASSOCIATION_RULES, BALL_OCCURRENCE, BALL_TEMPERATURE, BAYESIAN_BALL_PROBABILITIES,
+    BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP, BLUE_BALL_FOLLOWING_OCCURRENCES,
+    BLUE_BALL_MARKOV_MODEL, EXPECTED_VALUES, FINAL_PROCESSOR_CHAIN_RESULTS,
+    FinalProcessorChainResult, NEXT_DRAW_EXPECTED_VALUE, PORTFOLIO, RANDOMNESS_TEST_RESULTS,
+    RED_BALL_AND_RED_BALL_RELATIONSHIP_FP, RED_BALL_SHAPE_DISTRIBUTION, RED_BALL_SHAPES,
+    RED_BALL_STRUCTURE_DISTRIBUTION, RED_BALL_STRUCTURES, SCORED_TICKETS, TICKET_FILTER_HISTORY,
+    TICKET_FILTER_REPORT,
+};
+use tokio_util::sync::CancellationToken;
+
+/// 可以导出的分析链属性
+struct Attribute {
+    name: &'static str,
+    /// 把上下文中的属性转换为 JSON，上下文中不存在该属性时返回 `None`
+    serialize: fn(&ProcessorContext) -> Option<Result<Value, serde_json::Error>>,
+    /// 属性 JSON 表示的 JSON Schema
+    schema: fn(&mut SchemaGenerator) -> Schema,
+}
+
+fn json<T: Serialize>(value: T) -> Result<Value, serde_json::Error> {
+    serde_json::to_value(value)
+}
+
+const ATTRIBUTES: [Attribute; 20] = [
+    Attribute {
+        name: "BALL_OCCURRENCE",
+        serialize: |context| {
+            context
+                .get_attribute(&BALL_OCCURRENCE)
+                .map(|value| json(BallOccurrenceView::from_map(value)))
+        },
+        schema: |generator| generator.subschema_for::<Vec<BallOccurrenceView>>(),
+    },
+    Attribute {
+        name: "BALL_TEMPERATURE",
+        serialize: |context| context.get_attribute(&BALL_TEMPERATURE).map(json),
+        schema: |generator| generator.subschema_for::<Vec<BallTemperatureWindow>>(),
+    },
+    Attribute {
+        name: "RED_BALL_SHAPES",
+        serialize: |context| context.get_attribute(&RED_BALL_SHAPES).map(json),
+        schema: |generator| generator.subschema_for::<Vec<DrawRedBallShape>>(),
+    },
+    Attribute {
+        name: "RED_BALL_SHAPE_DISTRIBUTION",
+        serialize: |context| {
+            context
+                .get_attribute(&RED_BALL_SHAPE_DISTRIBUTION)
+                .map(json)
+        },
+        schema: |generator| generator.subschema_for::<RedBallShapeDistribution>(),
+    },
+    Attribute {
+        name: "RED_BALL_STRUCTURES",
+        serialize: |context| context.get_attribute(&RED_BALL_STRUCTURES).map(json),
+        schema: |generator| generator.subschema_for::<Vec<DrawRedBallStructure>>(),
+    },
+    Attribute {
+        name: "RED_BALL_STRUCTURE_DISTRIBUTION",
+        serialize: |context| {
+            context
+                .get_attribute(&RED_BALL_STRUCTURE_DISTRIBUTION)
+                .map(json)
+        },
+        schema: |generator| generator.subschema_for::<RedBallStructureDistribution>(),
+    },
+    Attribute {
+        name: "RANDOMNESS_TEST_RESULTS",
+        serialize: |context| context.get_attribute(&RANDOMNESS_TEST_RESULTS).map(json),
+        schema: |generator| generator.subschema_for::<Vec<RandomnessTestResult>>(),
+    },
+    Attribute {
+        name: "BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP",
+        serialize: |context| {
+            context
+                .get_attribute(&BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP)
+                .map(|value| json(BallRelationshipView::from_map(value)))
+        },
+        schema: |generator| generator.subschema_for::<Vec<BallRelationshipView<BlueBall>>>(),
+    },
+    Attribute {
+        name: "RED_BALL_AND_RED_BALL_RELATIONSHIP_FP",
+        serialize: |context| {
+            context
+                .get_attribute(&RED_BALL_AND_RED_BALL_RELATIONSHIP_FP)
+                .map(|value| json(BallRelationshipView::from_map(value)))
+        },
+        schema: |generator| generator.subschema_for::<Vec<BallRelationshipView<RedBall>>>(),
+    },
+    Attribute {
+        name: "BALL_ASSOCIATION_RULES",
+        serialize: |context| context.get_attribute(&BALL_ASSOCIATION_RULES).map(json),
+        schema: |generator| generator.subschema_for::<Vec<AssociationRule>>(),
+    },
+    Attribute {
+        name: "BLUE_BALL_FOLLOWING_OCCURRENCES",
+        serialize: |context| {
+            context
+                .get_attribute(&BLUE_BALL_FOLLOWING_OCCURRENCES)
+                .map(|value| json(BlueBallFollowingView::from_map(value)))
+        },
+        schema: |generator| generator.subschema_for::<Vec<BlueBallFollowingView>>(),
+    },
+    Attribute {
+        name: "BLUE_BALL_MARKOV_MODEL",
+        serialize: |context| context.get_attribute(&BLUE_BALL_MARKOV_MODEL).map(json),
+        schema: |generator| generator.subschema_for::<BlueBallMarkovModel>(),
+    },
+    Attribute {
+        name: "EXPECTED_VALUES",
+        serialize: |context| context.get_attribute(&EXPECTED_VALUES).map(json),
+        schema: |generator| generator.subschema_for::<Vec<DrawExpectedValue>>(),
+    },
+    Attribute {
+        name: "NEXT_DRAW_EXPECTED_VALUE",
+        serialize: |context| context.get_attribute(&NEXT_DRAW_EXPECTED_VALUE).map(json),
+        schema: |generator| generator.subschema_for::<ExpectedValue>(),
+    },
+    Attribute {
+        name: "BAYESIAN_BALL_PROBABILITIES",
+        serialize: |context| {
+            context
+                .get_attribute(&BAYESIAN_BALL_PROBABILITIES)
+                .map(json)
+        },
+        schema: |generator| generator.subschema_for::<BallPosteriors>(),
+    },
+    Attribute {
+        name: "SCORED_TICKETS",
+        serialize: |context| context.get_attribute(&SCORED_TICKETS).map(json),
+        schema: |generator| generator.subschema_for::<Vec<ScoredTicket>>(),
+    },
+    Attribute {
+        name: "FINAL_PROCESSOR_CHAIN_RESULTS",
+        serialize: |context| {
+            context
+                .get_attribute(&FINAL_PROCESSOR_CHAIN_RESULTS)
+                .map(json)
+        },
+        schema: |generator| generator.subschema_for::<Vec<FinalProcessorChainResult>>(),
+    },
+    Attribute {
+        name: "TICKET_FILTER_REPORT",
+        serialize: |context| context.get_attribute(&TICKET_FILTER_REPORT).map(json),
+        schema: |generator| generator.subschema_for::<FilterReport>(),
+    },
+    Attribute {
+        name: "TICKET_FILTER_HISTORY",
+        serialize: |context| context.get_attribute(&TICKET_FILTER_HISTORY).map(json),
+        schema: |generator| generator.subschema_for::<Vec<HistoricalFilterResult>>(),
+    },
+    Attribute {
+        name: "PORTFOLIO",
+        serialize: |context| context.get_attribute(&PORTFOLIO).map(json),
+        schema: |generator| generator.subschema_for::<Portfolio>(),
+    },
+];
+
+/// 每期一条记录的属性数据量较大，只有明确要求时才导出
+const PER_DRAW_ATTRIBUTE_NAMES: [&str; 4] = [
+    "RED_BALL_SHAPES",
+    "RED_BALL_STRUCTURES",
+    "EXPECTED_VALUES",
+    "TICKET_FILTER_HISTORY",
+];
+
+/// 预测相关的属性名称
+pub const PREDICTION_ATTRIBUTE_NAMES: [&str; 4] = [
+    "FINAL_PROCESSOR_CHAIN_RESULTS",
+    "SCORED_TICKETS",
+    "TICKET_FILTER_REPORT",
+    "PORTFOLIO",
+];
+
+#[derive(Debug, Default, serde::Deserialize, schemars::JsonSchema)]
+pub struct RunChainRequest {
+    #[schemars(
+        description = "分析链配置，格式与命令行 --config 指定的 JSON 文件相同，但不能包含写入文件的执行器，不指定时使用默认分析链"
+    )]
+    pub config: Option<PipelineConfig>,
+    #[schemars(description = "只使用最近若干期的中奖数据，不指定时使用全部中奖数据")]
+    pub recent_size: Option<usize>,
+    #[schemars(
+        description = "需要返回的属性名称，例如 BALL_OCCURRENCE、BLUE_BALL_FOLLOWING_OCCURRENCES、RED_BALL_AND_RED_BALL_RELATIONSHIP_FP、FINAL_PROCESSOR_CHAIN_RESULTS"
+    )]
+    pub attributes: Option<Vec<String>>,
+}
+
+fn check_attribute_names(names: Option<&[String]>) -> Result<(), Error> {
+    match names.into_iter().flatten().find(|name| {
+        !ATTRIBUTES
+            .iter()
+            .any(|attribute| attribute.name == name.as_str())
+    }) {
+        Some(unknown) => Err(Error::InvalidArgument(format!(
+            "未知的属性名称 {unknown}，可用的属性名称：{}",
+            ATTRIBUTES.map(|attribute| attribute.name).join("，")
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// 在最近若干期中奖数据上执行请求的分析链或预测链，返回请求的属性。
+/// 预测链没有指定属性时只返回预测相关的属性。执行过程中通过 `progress_reporter` 报告进度，
+/// 被 `cancellation_token` 取消或者超时时返回已经执行完的执行器的结果
+pub async fn run_chain(
+    records: &[PrBusinessObj],
+    request: RunChainRequest,
+    prediction: bool,
+    progress_reporter: Option<ProgressReporter>,
+    cancellation_token: CancellationToken,
+) -> Result<Map<String, Value>, Error> {
+    let RunChainRequest {
+        config,
+        recent_size,
+        attributes,
+    } = request;
+    let config = config.unwrap_or_default();
+    config.check_service_request()?;
+    let records = match recent_size {
+        Some(0) => {
+            return Err(Error::InvalidArgument("中奖数据期数必须为正数".to_string()));
+        }
+        Some(recent_size) => &records[..recent_size.min(records.len())],
+        None => records,
+    };
+    let attributes = attributes.or_else(|| {
+        prediction.then(|| {
+            PREDICTION_ATTRIBUTE_NAMES
+                .iter()
+                .map(|name| name.to_string())
+                .collect()
+        })
+    });
+    check_attribute_names(attributes.as_deref())?;
+    let mut processor_chain = if prediction {
+        config.prediction_chain()
+    } else {
+        config.analysis_chain()
+    };
+    let mut context = ProcessorContext::new(records, config.result_size)
+        .with_cancellation_token(cancellation_token);
+    if let Some(progress_reporter) = progress_reporter {
+        context = context.with_progress_reporter(progress_reporter);
+    }
+    match processor_chain.execute(&mut context).await {
+        Ok(()) => context_attributes(&context, attributes.as_deref()),
+        Err(reason) if reason.is_interrupted() => Err(Error::Interrupted {
+            reason,
+            partial_results: context_attributes(&context, attributes.as_deref())?,
+        }),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 分析链结果的 JSON Schema：以属性名称为键、属性的 JSON 表示为值的对象
+pub fn attributes_schema(generator: &mut SchemaGenerator) -> Value {
+    let properties = ATTRIBUTES
+        .iter()
+        .map(|attribute| {
+            let schema = (attribute.schema)(generator);
+            (attribute.name.to_string(), schema.to_value())
+        })
+        .collect::<Map<String, Value>>();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    })
+}
+
+/// 把分析链上下文中的属性转换为 JSON，键为属性名称，上下文中不存在的属性不会出现在结果中。
+/// `names` 为空时导出除每期记录以外的所有属性
+pub fn context_attributes(
+    context: &ProcessorContext,
+    names: Option<&[String]>,
+) -> Result<Map<String, Value>, Error> {
+    check_attribute_names(names)?;
+    let wanted = |name: &str| match names {
+        Some(names) => names.iter().any(|wanted| wanted == name),
+        None => !PER_DRAW_ATTRIBUTE_NAMES.contains(&name),
+    };
+    let mut attributes = Map::new();
+    for attribute in ATTRIBUTES.iter().filter(|attribute| wanted(attribute.name)) {
+        if let Some(value) = (attribute.serialize)(context) {
+            attributes.insert(attribute.name.to_string(), value?);
+        }
+    }
+    Ok(attributes)
+}
@@ -0,0 +1,204 @@
+use crate::service::rest::DEFAULT_API_PATH;
+use crate::service::transport::{DEFAULT_HTTP_BIND_ADDRESS, DEFAULT_HTTP_PATH};
+use crate::wallet::{DEFAULT_WALLET_FILE, WalletGroup};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(name = "ssq-tool", version, about = "双色球数据收集、分析与预测工具")]
+pub struct Cli {
+    #[command(flatten)]
+    pub options: GlobalOptions,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Args)]
+pub struct GlobalOptions {
+    /// 往期中奖数据来源
+    #[arg(long, value_enum, default_value_t = Source::Local, global = true)]
+    pub source: Source,
+    /// 只使用最近若干期的中奖数据
+    #[arg(long, global = true)]
+    pub records: Option<usize>,
+    /// 分析链配置文件（JSON），不指定时使用默认的分析链
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// 输出格式
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, global = true)]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Source {
+    /// 本地的 official_data.json
+    Local,
+    /// 中国福利彩票官网，同时更新本地数据
+    Remote,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// 从官网同步往期中奖数据到本地
+    Sync,
+    /// 执行分析链并输出统计结果
+    Analyze,
+    /// 执行分析链并输出预测号码
+    Predict,
+    /// 检查彩票的中奖情况，支持单式 `01,02,03,04,05,06+07`、
+    /// 复式 `01,02,03,04,05,06,07+08,09` 与胆拖 `01,02#03,04,05,06,07+08`
+    Check {
+        tickets: Vec<String>,
+        /// 彩票文件，每行一张彩票
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// 开奖期号，不指定时使用最近一期
+        #[arg(long, conflicts_with = "all")]
+        code: Option<String>,
+        /// 检查所有往期
+        #[arg(long)]
+        all: bool,
+    },
+    /// 逐期前推回测分析链的预测结果
+    Backtest(BacktestArgs),
+    /// 启动 MCP 服务
+    ServeMcp(ServeMcpArgs),
+    /// 导出数据到文件或标准输出
+    Export {
+        /// 输出文件，不指定时输出到标准输出
+        #[arg(long, short, global = true)]
+        output: Option<PathBuf>,
+        #[command(subcommand)]
+        target: ExportTarget,
+    },
+    /// 输出最近一期、随机性检验、期望收益、后验概率与预测号码的综合报告
+    Report,
+    /// 管理本地购彩钱包，查看前会自动兑奖
+    Wallet {
+        /// 钱包文件
+        #[arg(long, default_value = DEFAULT_WALLET_FILE, global = true)]
+        wallet: PathBuf,
+        #[command(subcommand)]
+        action: WalletAction,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WalletAction {
+    /// 记录一张已购买的彩票
+    Add {
+        /// 彩票号码，格式与 `check` 命令相同
+        ticket: String,
+        /// 开奖期号
+        #[arg(long)]
+        code: String,
+        /// 购买人
+        #[arg(long)]
+        owner: String,
+        /// 号码来源策略
+        #[arg(long)]
+        strategy: Option<String>,
+        /// 备注
+        #[arg(long)]
+        notes: Option<String>,
+    },
+    /// 列出购买记录
+    List {
+        /// 只列出指定购买人的记录
+        #[arg(long)]
+        owner: Option<String>,
+        /// 只列出还没有开奖的记录
+        #[arg(long)]
+        pending: bool,
+    },
+    /// 删除一条购买记录
+    Remove { id: u64 },
+    /// 对已开奖的购买记录兑奖
+    Settle,
+    /// 统计投入、奖金与盈亏
+    Report {
+        #[arg(long, value_enum, default_value_t = WalletGroup::Owner)]
+        by: WalletGroup,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum McpTransport {
+    /// 通过标准输入输出与启动服务的 MCP 客户端通信
+    Stdio,
+    /// 可流式传输的 HTTP
+    Http,
+}
+
+#[derive(Debug, Args)]
+pub struct ServeMcpArgs {
+    /// MCP 服务的传输方式
+    #[arg(long, value_enum, default_value_t = McpTransport::Http)]
+    pub transport: McpTransport,
+    /// HTTP 模式监听的地址
+    #[arg(long, default_value = DEFAULT_HTTP_BIND_ADDRESS)]
+    pub bind: String,
+    /// HTTP 模式挂载 MCP 服务的路径
+    #[arg(long, default_value = DEFAULT_HTTP_PATH)]
+    pub path: String,
+    /// HTTP 模式挂载 REST 接口的路径，OpenAPI 文档位于该路径下的 openapi.json
+    #[arg(long, default_value = DEFAULT_API_PATH)]
+    pub api_path: String,
+    /// 后台检查开奖日是否需要刷新数据的间隔（分钟）
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    pub refresh_interval: u64,
+    /// 购彩钱包文件，钱包工具读写该文件，刷新到新的开奖结果后对其兑奖
+    #[arg(long, default_value = DEFAULT_WALLET_FILE)]
+    pub wallet: PathBuf,
+    /// 不启动后台刷新任务，只能通过 MCP 工具手动刷新
+    #[arg(long)]
+    pub no_auto_refresh: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct BacktestArgs {
+    /// 只回测最近的若干期
+    #[arg(long, default_value_t = 50)]
+    pub evaluation_size: usize,
+    /// 每期只使用之前最近的若干期作为历史
+    #[arg(long)]
+    pub history_window: Option<usize>,
+    /// 历史期数少于该值的开奖期不参与回测
+    #[arg(long, default_value_t = 100)]
+    pub minimum_history: usize,
+    /// 随机号码基准的模拟次数，为 0 时不比较
+    #[arg(long, default_value_t = 0)]
+    pub baseline_trials: usize,
+    /// 随机号码基准的随机数种子
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ExportTarget {
+    /// 往期中奖数据
+    Draws,
+    /// 预测号码
+    Predictions,
+    /// 旋转矩阵生成的单式号码
+    Wheel {
+        /// 选号红球，例如 `1,5,8,12,16,20,23,27,30,33`
+        #[arg(long, value_delimiter = ',', required = true)]
+        pool: Vec<usize>,
+        /// 保证命中的红球个数
+        #[arg(long, default_value_t = 5)]
+        guarantee: usize,
+        /// 选号中开出的红球个数
+        #[arg(long, default_value_t = 6)]
+        drawn: usize,
+        /// 与每组红球搭配的蓝球
+        #[arg(long, value_delimiter = ',', required = true)]
+        blue: Vec<usize>,
+    },
+}
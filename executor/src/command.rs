@@ -0,0 +1,867 @@
+use crate::cli::{BacktestArgs, ExportTarget, OutputFormat, WalletAction};
+use crate::error::Error;
+use crate::pipeline::PipelineConfig;
+use crate::ticket_check::{CheckScope, check_tickets, parse_ticket, read_tickets_file};
+use crate::wallet::{ProfitAndLoss, Purchase, Wallet};
+use itertools::Itertools;
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::{BlueBall, PrBusinessObj, RedBall, Ticket};
+use ssq_tool_processor::association_rule::AssociationRule;
+use ssq_tool_processor::backtest::{Backtest, BacktestReport};
+use ssq_tool_processor::bayesian_ball_probability::BallPosteriors;
+use ssq_tool_processor::blue_ball_markov::BlueBallMarkovModel;
+use ssq_tool_processor::context::ProcessorContext;
+use ssq_tool_processor::expected_value::ExpectedValue;
+use ssq_tool_processor::portfolio::Portfolio;
+use ssq_tool_processor::progress::ProgressReporter;
+use ssq_tool_processor::random_baseline::{MAX_TRIALS, RandomBaseline, RandomBaselineReport};
+use ssq_tool_processor::randomness_test::RandomnessTestResult;
+use ssq_tool_processor::red_ball_shape::RedBallShapeDistribution;
+use ssq_tool_processor::red_ball_structure::RedBallStructureDistribution;
+use ssq_tool_processor::strategy::ScoredTicket;
+use ssq_tool_processor::ticket_filter::FilterReport;
+use ssq_tool_processor::wheel::{Wheel, WheelGenerator, WheelVerification};
+use ssq_tool_processor::{
+    BALL_ASSOCIATION_RULES, BAYESIAN_BALL_PROBABILITIES, BLUE_BALL_MARKOV_MODEL,
+    FINAL_PROCESSOR_CHAIN_RESULTS, FinalProcessorChainResult, NEXT_DRAW_EXPECTED_VALUE, PORTFOLIO,
+    RANDOMNESS_TEST_RESULTS, RED_BALL_SHAPE_DISTRIBUTION, RED_BALL_STRUCTURE_DISTRIBUTION,
+    SCORED_TICKETS, TICKET_FILTER_REPORT,
+};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+/// 文本输出中展示的关联规则条数
+const DISPLAYED_ASSOCIATION_RULES: usize = 10;
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), Error> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn format_ball(ball: impl ToString) -> String {
+    format!("{:0>2}", ball.to_string())
+}
+
+pub(crate) fn format_draw(record: &PrBusinessObj) -> String {
+    format!(
+        "第 {} 期（{} {}）红球：{} 蓝球：{}",
+        record.code,
+        record.date,
+        record.day,
+        record.red_balls.iter().map(format_ball).join(" "),
+        format_ball(record.blue_ball)
+    )
+}
+
+/// 与 `check` 命令的输入格式一致，例如 `01,02,03,04,05,06+07`
+fn format_ticket(ticket: &FinalProcessorChainResult) -> String {
+    format!(
+        "{}+{}",
+        ticket.red_ball().iter().map(format_ball).join(","),
+        format_ball(ticket.blue_ball())
+    )
+}
+
+pub(crate) fn latest_draw(records: &[PrBusinessObj]) -> Result<&PrBusinessObj, Error> {
+    records
+        .iter()
+        .max_by_key(|record| record.date)
+        .ok_or(Error::Other("没有往期中奖数据".to_string()))
+}
+
+fn output_writer(output: Option<&Path>) -> Result<Box<dyn Write>, Error> {
+    Ok(match output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(std::io::stdout()),
+    })
+}
+
+#[derive(Serialize)]
+struct SyncSummary<'a> {
+    #[serde(rename = "期数")]
+    record_count: usize,
+    #[serde(rename = "最近一期")]
+    latest: &'a PrBusinessObj,
+}
+
+pub fn sync(records: &[PrBusinessObj], format: OutputFormat) -> Result<(), Error> {
+    let latest = latest_draw(records)?;
+    match format {
+        OutputFormat::Text => {
+            println!("已同步 {} 期中奖数据", records.len());
+            println!("最近一期：{}", format_draw(latest));
+            Ok(())
+        }
+        OutputFormat::Json => print_json(&SyncSummary {
+            record_count: records.len(),
+            latest,
+        }),
+    }
+}
+
+#[derive(Serialize)]
+struct AnalysisReport<'a> {
+    #[serde(rename = "期数")]
+    record_count: usize,
+    #[serde(rename = "随机性检验")]
+    randomness_tests: Option<&'a Vec<RandomnessTestResult>>,
+    #[serde(rename = "红球形态分布")]
+    red_ball_shape_distribution: Option<&'a RedBallShapeDistribution>,
+    #[serde(rename = "红球结构分布")]
+    red_ball_structure_distribution: Option<&'a RedBallStructureDistribution>,
+    #[serde(rename = "关联规则")]
+    association_rules: Option<&'a Vec<AssociationRule>>,
+    #[serde(rename = "蓝球马尔可夫模型")]
+    blue_ball_markov_model: Option<&'a BlueBallMarkovModel>,
+    #[serde(rename = "下期期望收益")]
+    next_draw_expected_value: Option<&'a ExpectedValue>,
+    #[serde(rename = "后验概率")]
+    ball_posteriors: Option<&'a BallPosteriors>,
+}
+
+impl<'a> AnalysisReport<'a> {
+    fn new(context: &'a ProcessorContext) -> Self {
+        Self {
+            record_count: context.get_prize_records().len(),
+            randomness_tests: context.get_attribute(&RANDOMNESS_TEST_RESULTS),
+            red_ball_shape_distribution: context.get_attribute(&RED_BALL_SHAPE_DISTRIBUTION),
+            red_ball_structure_distribution: context
+                .get_attribute(&RED_BALL_STRUCTURE_DISTRIBUTION),
+            association_rules: context.get_attribute(&BALL_ASSOCIATION_RULES),
+            blue_ball_markov_model: context.get_attribute(&BLUE_BALL_MARKOV_MODEL),
+            next_draw_expected_value: context.get_attribute(&NEXT_DRAW_EXPECTED_VALUE),
+            ball_posteriors: context.get_attribute(&BAYESIAN_BALL_PROBABILITIES),
+        }
+    }
+
+    fn print_randomness_tests(&self) {
+        if let Some(randomness_tests) = self.randomness_tests {
+            println!("随机性检验：");
+            randomness_tests.iter().for_each(|result| {
+                println!(
+                    "  {}：统计量 {:.3}，P值 {:.4}，{}",
+                    result.name(),
+                    result.statistic(),
+                    result.p_value(),
+                    if result.reject_randomness() {
+                        "拒绝随机假设"
+                    } else {
+                        "不能拒绝随机假设"
+                    }
+                );
+            });
+        }
+    }
+
+    fn print_distributions(&self) {
+        fn most_common<K: std::fmt::Display>(
+            distribution: impl IntoIterator<Item = (K, usize)>,
+        ) -> String {
+            distribution
+                .into_iter()
+                .sorted_by(|a, b| b.1.cmp(&a.1))
+                .take(3)
+                .map(|(value, count)| format!("{value}（{count} 期）"))
+                .join("，")
+        }
+        if let Some(distribution) = self.red_ball_shape_distribution {
+            println!("红球形态：");
+            println!(
+                "  最常见奇偶比：{}",
+                most_common(distribution.odd_even_ratio().iter().map(|(k, v)| (k, *v)))
+            );
+            println!(
+                "  最常见三区比：{}",
+                most_common(distribution.zone_ratio().iter().map(|(k, v)| (k, *v)))
+            );
+        }
+        if let Some(distribution) = self.red_ball_structure_distribution {
+            println!("红球结构：");
+            println!(
+                "  最常见 AC 值：{}",
+                most_common(distribution.ac_value().iter().map(|(k, v)| (k, *v)))
+            );
+            println!(
+                "  最常见最长连号：{}",
+                most_common(distribution.max_consecutive().iter().map(|(k, v)| (k, *v)))
+            );
+        }
+    }
+
+    fn print_association_rules(&self) {
+        if let Some(association_rules) = self.association_rules {
+            println!("提升度最高的关联规则：");
+            association_rules
+                .iter()
+                .take(DISPLAYED_ASSOCIATION_RULES)
+                .for_each(|rule| {
+                    println!(
+                        "  {} => {}：支持度 {:.4}，置信度 {:.4}，提升度 {:.3}",
+                        rule.antecedent().iter().join(" "),
+                        rule.consequent().iter().join(" "),
+                        rule.support(),
+                        rule.confidence(),
+                        rule.lift()
+                    );
+                });
+        }
+    }
+
+    fn print_blue_ball_markov_model(&self) {
+        if let Some(model) = self.blue_ball_markov_model {
+            println!(
+                "马尔可夫链给出的下期蓝球概率：{}",
+                model
+                    .next_draw_probabilities()
+                    .iter()
+                    .sorted_by(|a, b| b.1.total_cmp(a.1))
+                    .take(5)
+                    .map(|(ball, probability)| format!(
+                        "{}（{:.4}）",
+                        format_ball(ball),
+                        probability
+                    ))
+                    .join("，")
+            );
+        }
+    }
+
+    fn print_expected_value(&self) {
+        if let Some(expected_value) = self.next_draw_expected_value {
+            println!(
+                "下期单注期望收益：{:.3} 元（返奖率 {:.1}%，奖池 {} 元，一等奖预计 {:.0} 元）",
+                expected_value.expected_value(),
+                expected_value.return_rate() * 100.0,
+                expected_value.jackpot_pool(),
+                expected_value.first_prize_estimate()
+            );
+        }
+    }
+
+    fn print_ball_posteriors(&self) {
+        if let Some(posteriors) = self.ball_posteriors {
+            println!(
+                "后验概率（有效期数 {:.1}，95% 可信区间）：",
+                posteriors.effective_draws()
+            );
+            let red = posteriors
+                .red()
+                .iter()
+                .sorted_by(|a, b| b.1.mean().total_cmp(&a.1.mean()))
+                .take(6)
+                .map(|(ball, posterior)| {
+                    format!(
+                        "{} {:.4} [{:.4}, {:.4}]",
+                        format_ball(ball),
+                        posterior.mean(),
+                        posterior.lower(),
+                        posterior.upper()
+                    )
+                })
+                .join("；");
+            let blue = posteriors
+                .blue()
+                .iter()
+                .sorted_by(|a, b| b.1.mean().total_cmp(&a.1.mean()))
+                .take(3)
+                .map(|(ball, posterior)| {
+                    format!(
+                        "{} {:.4} [{:.4}, {:.4}]",
+                        format_ball(ball),
+                        posterior.mean(),
+                        posterior.lower(),
+                        posterior.upper()
+                    )
+                })
+                .join("；");
+            println!("  红球：{red}");
+            println!("  蓝球：{blue}");
+        }
+    }
+}
+
+pub async fn analyze(
+    records: &[PrBusinessObj],
+    config: &PipelineConfig,
+    format: OutputFormat,
+) -> Result<(), Error> {
+    let mut context = ProcessorContext::new(records, config.result_size);
+    config.analysis_chain().execute(&mut context).await?;
+    let report = AnalysisReport::new(&context);
+    match format {
+        OutputFormat::Text => {
+            println!("共分析 {} 期中奖数据", report.record_count);
+            report.print_randomness_tests();
+            report.print_distributions();
+            report.print_association_rules();
+            report.print_blue_ball_markov_model();
+            report.print_expected_value();
+            report.print_ball_posteriors();
+            Ok(())
+        }
+        OutputFormat::Json => print_json(&report),
+    }
+}
+
+#[derive(Serialize)]
+struct PredictionReport<'a> {
+    #[serde(rename = "预测结果")]
+    final_results: &'a Vec<FinalProcessorChainResult>,
+    #[serde(rename = "候选号码")]
+    scored_tickets: Option<&'a Vec<ScoredTicket>>,
+    #[serde(rename = "缩水结果")]
+    filter_report: Option<&'a FilterReport>,
+    #[serde(rename = "投注组合")]
+    portfolio: Option<&'a Portfolio>,
+}
+
+impl<'a> PredictionReport<'a> {
+    fn new(context: &'a ProcessorContext) -> Result<Self, Error> {
+        Ok(Self {
+            final_results: context
+                .get_attribute(&FINAL_PROCESSOR_CHAIN_RESULTS)
+                .ok_or(Error::NoFinalProcessorChainResults)?,
+            scored_tickets: context.get_attribute(&SCORED_TICKETS),
+            filter_report: context.get_attribute(&TICKET_FILTER_REPORT),
+            portfolio: context.get_attribute(&PORTFOLIO),
+        })
+    }
+
+    fn print(&self) {
+        if let Some(filter_report) = self.filter_report {
+            println!(
+                "缩水：共 {} 注，保留 {} 注",
+                filter_report.total_count(),
+                filter_report.passed_count()
+            );
+        }
+        if let Some(portfolio) = self.portfolio {
+            println!(
+                "投注组合：预算 {} 元，花费 {} 元，覆盖 {} 个红球",
+                portfolio.budget(),
+                portfolio.cost(),
+                portfolio.covered_red_balls().len()
+            );
+        }
+        self.final_results.iter().for_each(|record| {
+            println!("{record}");
+        });
+    }
+}
+
+pub async fn predict(
+    records: &[PrBusinessObj],
+    config: &PipelineConfig,
+    format: OutputFormat,
+) -> Result<(), Error> {
+    let mut context = ProcessorContext::new(records, config.result_size);
+    info!("开始分析双色球数据...");
+    config.prediction_chain().execute(&mut context).await?;
+    let report = PredictionReport::new(&context)?;
+    match format {
+        OutputFormat::Text => {
+            report.print();
+            Ok(())
+        }
+        OutputFormat::Json => print_json(&report),
+    }
+}
+
+pub fn check(
+    records: &[PrBusinessObj],
+    tickets: &[String],
+    file: Option<&Path>,
+    scope: CheckScope,
+    format: OutputFormat,
+) -> Result<(), Error> {
+    let mut parsed_tickets = tickets
+        .iter()
+        .map(|ticket| parse_ticket(ticket))
+        .collect::<Result<Vec<Ticket>, Error>>()?;
+    if let Some(file) = file {
+        parsed_tickets.extend(read_tickets_file(file)?);
+    }
+    if parsed_tickets.is_empty() {
+        return Err(Error::InvalidArgument(
+            "需要给出至少一张彩票或彩票文件".to_string(),
+        ));
+    }
+    let reports = check_tickets(records, &parsed_tickets, scope)?;
+    let draws_by_code = records
+        .iter()
+        .map(|record| (record.code.as_str(), record))
+        .collect::<HashMap<&str, &PrBusinessObj>>();
+    match format {
+        OutputFormat::Text => {
+            reports.iter().for_each(|report| {
+                println!(
+                    "{}（{} 注）：检查 {} 期，投注 {} 元，奖金 {} 元",
+                    report.ticket,
+                    report.bet_count,
+                    report.checked_draws,
+                    report.total_cost,
+                    report.total_payout
+                );
+                report.checks.iter().for_each(|check| {
+                    let draw = draws_by_code[check.code.as_str()];
+                    let grades = if check.grade_counts.is_empty() {
+                        "未中奖".to_string()
+                    } else {
+                        check
+                            .grade_counts
+                            .iter()
+                            .map(|(grade, count)| format!("{grade} {count} 注"))
+                            .join("，")
+                    };
+                    println!("  {}", format_draw(draw));
+                    println!(
+                        "    命中红球：{}，命中蓝球：{}，{grades}，奖金 {} 元",
+                        check.matched_red_balls.iter().map(format_ball).join(" "),
+                        check
+                            .matched_blue_ball
+                            .map_or("无".to_string(), format_ball),
+                        check.payout
+                    );
+                });
+            });
+            Ok(())
+        }
+        OutputFormat::Json => print_json(&reports),
+    }
+}
+
+#[derive(Debug, Serialize, schemars::JsonSchema)]
+pub struct BacktestSummary {
+    #[serde(rename = "回测报告")]
+    pub report: BacktestReport,
+    #[serde(rename = "随机基准")]
+    pub baseline: Option<RandomBaselineReport>,
+}
+
+/// 通过服务接口提交的回测请求，参数与 backtest 命令相同
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct BacktestRequest {
+    #[schemars(
+        description = "分析链配置，格式与命令行 --config 指定的 JSON 文件相同，但不能包含写入文件的执行器，不指定时使用默认分析链"
+    )]
+    pub config: Option<PipelineConfig>,
+    #[schemars(description = "只回测最近的若干期")]
+    #[serde(default = "default_evaluation_size")]
+    pub evaluation_size: usize,
+    #[schemars(description = "每期只使用之前最近的若干期作为历史")]
+    pub history_window: Option<usize>,
+    #[schemars(description = "历史期数少于该值的开奖期不参与回测")]
+    #[serde(default = "default_minimum_history")]
+    pub minimum_history: usize,
+    #[schemars(description = "随机号码基准的模拟次数，为 0 时不比较，最多 100000 次")]
+    #[serde(default)]
+    pub baseline_trials: usize,
+    #[schemars(description = "随机号码基准的随机数种子")]
+    #[serde(default)]
+    pub seed: u64,
+}
+
+fn default_evaluation_size() -> usize {
+    50
+}
+
+fn default_minimum_history() -> usize {
+    100
+}
+
+impl BacktestRequest {
+    /// 拆分为分析链配置与回测参数
+    pub fn into_config_and_args(self) -> Result<(PipelineConfig, BacktestArgs), Error> {
+        let BacktestRequest {
+            config,
+            evaluation_size,
+            history_window,
+            minimum_history,
+            baseline_trials,
+            seed,
+        } = self;
+        if baseline_trials > MAX_TRIALS {
+            return Err(Error::InvalidArgument(format!(
+                "随机号码基准的模拟次数不能超过 {MAX_TRIALS}：{baseline_trials}"
+            )));
+        }
+        let config = config.unwrap_or_default();
+        config.check_service_request()?;
+        let args = BacktestArgs {
+            evaluation_size,
+            history_window,
+            minimum_history,
+            baseline_trials,
+            seed,
+        };
+        Ok((config, args))
+    }
+}
+
+/// 执行逐期前推回测，需要时与同样注数的随机号码比较。每回测完一期通过 `progress_reporter`
+/// 报告进度，被 `cancellation_token` 取消后停止回测与随机模拟
+pub async fn run_backtest(
+    records: &[PrBusinessObj],
+    config: &PipelineConfig,
+    args: &BacktestArgs,
+    progress_reporter: Option<ProgressReporter>,
+    cancellation_token: CancellationToken,
+) -> Result<BacktestSummary, Error> {
+    let chain_config = config.clone();
+    let report = Backtest::new(move || chain_config.prediction_chain(), config.result_size)
+        .minimum_history_size(args.minimum_history)
+        .history_window(args.history_window)
+        .evaluation_size(Some(args.evaluation_size))
+        .progress_reporter(progress_reporter.clone())
+        .cancellation_token(cancellation_token.clone())
+        .run(records)
+        .await?;
+    if args.baseline_trials == 0 {
+        return Ok(BacktestSummary {
+            report,
+            baseline: None,
+        });
+    }
+    // 随机模拟是同步的计算密集型工作，放到阻塞线程中执行，避免占住异步运行时的工作线程
+    let random_baseline = RandomBaseline::new(args.baseline_trials, args.seed)
+        .progress_reporter(progress_reporter)
+        .cancellation_token(cancellation_token);
+    let records = records.to_vec();
+    tokio::task::spawn_blocking(move || {
+        let baseline = random_baseline.simulate(&records, &report)?;
+        Ok(BacktestSummary {
+            report,
+            baseline: Some(baseline),
+        })
+    })
+    .await
+    .map_err(|e| Error::Other(format!("随机基准模拟异常退出：{e}")))?
+}
+
+pub async fn backtest(
+    records: &[PrBusinessObj],
+    config: &PipelineConfig,
+    args: &BacktestArgs,
+    format: OutputFormat,
+) -> Result<(), Error> {
+    let BacktestSummary { report, baseline } =
+        run_backtest(records, config, args, None, CancellationToken::new()).await?;
+    match format {
+        OutputFormat::Text => {
+            println!(
+                "回测 {} 期，共 {} 注，花费 {} 元，奖金 {} 元，收益率 {:.2}%",
+                report.evaluated_draws(),
+                report.ticket_count(),
+                report.total_cost(),
+                report.total_return(),
+                report.roi() * 100.0
+            );
+            report.grade_hits().iter().for_each(|(grade, hits)| {
+                println!("  {grade}：{hits} 注");
+            });
+            if let Some(baseline) = &baseline {
+                println!(
+                    "随机基准（{} 次模拟）：平均奖金 {:.1} 元，标准差 {:.1} 元，P值 {:.4}",
+                    baseline.trials(),
+                    baseline.mean_return(),
+                    baseline.return_standard_deviation(),
+                    baseline.p_value()
+                );
+            }
+            Ok(())
+        }
+        OutputFormat::Json => print_json(&BacktestSummary { report, baseline }),
+    }
+}
+
+#[derive(Serialize)]
+struct WheelExport<'a> {
+    #[serde(rename = "旋转矩阵")]
+    wheel: &'a Wheel,
+    #[serde(rename = "校验结果")]
+    verification: &'a WheelVerification,
+    #[serde(rename = "号码")]
+    tickets: &'a [FinalProcessorChainResult],
+}
+
+pub async fn export(
+    records: &[PrBusinessObj],
+    config: &PipelineConfig,
+    target: &ExportTarget,
+    output: Option<&Path>,
+    format: OutputFormat,
+) -> Result<(), Error> {
+    let mut writer = output_writer(output)?;
+    match target {
+        ExportTarget::Draws => {
+            let draws = records
+                .iter()
+                .sorted_by_key(|record| record.date)
+                .rev()
+                .collect::<Vec<&PrBusinessObj>>();
+            match format {
+                OutputFormat::Text => {
+                    writeln!(writer, "期号,日期,红球1,红球2,红球3,红球4,红球5,红球6,蓝球")?;
+                    for draw in draws {
+                        writeln!(
+                            writer,
+                            "{},{},{},{}",
+                            draw.code,
+                            draw.date,
+                            draw.red_balls.iter().map(format_ball).join(","),
+                            format_ball(draw.blue_ball)
+                        )?;
+                    }
+                }
+                OutputFormat::Json => serde_json::to_writer_pretty(&mut writer, &draws)?,
+            }
+        }
+        ExportTarget::Predictions => {
+            let mut context = ProcessorContext::new(records, config.result_size);
+            config.prediction_chain().execute(&mut context).await?;
+            let report = PredictionReport::new(&context)?;
+            match format {
+                OutputFormat::Text => {
+                    for ticket in report.final_results {
+                        writeln!(writer, "{}", format_ticket(ticket))?;
+                    }
+                }
+                OutputFormat::Json => serde_json::to_writer_pretty(&mut writer, &report)?,
+            }
+        }
+        ExportTarget::Wheel {
+            pool,
+            guarantee,
+            drawn,
+            blue,
+        } => {
+            let invalid = |value: &usize| Error::InvalidArgument(format!("无效的号码：{value}"));
+            let pool = pool
+                .iter()
+                .map(|value| RedBall::try_from(*value).map_err(|_| invalid(value)))
+                .collect::<Result<Vec<RedBall>, Error>>()?;
+            let blue_balls = blue
+                .iter()
+                .map(|value| BlueBall::try_from(*value).map_err(|_| invalid(value)))
+                .collect::<Result<Vec<BlueBall>, Error>>()?;
+            let generator = WheelGenerator::new(pool, *guarantee, *drawn)?;
+            // 命令行导出不会被取消，按下 Ctrl-C 时直接结束进程
+            let wheel = generator.generate(|| Ok(()))?;
+            let verification = generator.verify(wheel.red_ball_combinations());
+            let tickets = wheel.tickets(&blue_balls);
+            match format {
+                OutputFormat::Text => {
+                    for ticket in &tickets {
+                        writeln!(writer, "{}", format_ticket(ticket))?;
+                    }
+                }
+                OutputFormat::Json => serde_json::to_writer_pretty(
+                    &mut writer,
+                    &WheelExport {
+                        wheel: &wheel,
+                        verification: &verification,
+                        tickets: &tickets,
+                    },
+                )?,
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct FullReport<'a> {
+    #[serde(rename = "最近一期")]
+    latest: &'a PrBusinessObj,
+    #[serde(rename = "分析结果")]
+    analysis: AnalysisReport<'a>,
+    #[serde(rename = "预测")]
+    prediction: PredictionReport<'a>,
+}
+
+pub async fn report(
+    records: &[PrBusinessObj],
+    config: &PipelineConfig,
+    format: OutputFormat,
+) -> Result<(), Error> {
+    let latest = latest_draw(records)?;
+    let mut context = ProcessorContext::new(records, config.result_size);
+    config.prediction_chain().execute(&mut context).await?;
+    let report = FullReport {
+        latest,
+        analysis: AnalysisReport::new(&context),
+        prediction: PredictionReport::new(&context)?,
+    };
+    match format {
+        OutputFormat::Text => {
+            println!("最近一期：{}", format_draw(report.latest));
+            println!("共分析 {} 期中奖数据", report.analysis.record_count);
+            report.analysis.print_randomness_tests();
+            report.analysis.print_expected_value();
+            report.analysis.print_ball_posteriors();
+            println!("预测号码：");
+            report.prediction.print();
+            Ok(())
+        }
+        OutputFormat::Json => print_json(&report),
+    }
+}
+
+/// 同步后对本地钱包兑奖，钱包文件不存在时不做任何事
+pub fn settle_wallet(
+    records: &[PrBusinessObj],
+    path: &Path,
+    format: OutputFormat,
+) -> Result<(), Error> {
+    let settled_count = Wallet::settle_file(path, records)?;
+    if format == OutputFormat::Text && settled_count > 0 {
+        println!("钱包中有 {settled_count} 条购买记录完成兑奖");
+    }
+    Ok(())
+}
+
+fn print_purchase(purchase: &Purchase) {
+    let settlement = match &purchase.settlement {
+        Some(settlement) if settlement.payout > 0 => format!(
+            "中奖 {} 元（{}）",
+            settlement.payout,
+            settlement
+                .grade_counts
+                .iter()
+                .map(|(grade, count)| format!("{grade} {count} 注"))
+                .join("，")
+        ),
+        Some(_) => "未中奖".to_string(),
+        None => "待开奖".to_string(),
+    };
+    println!(
+        "#{} 第 {} 期 {} {} 元 {}{}{}：{settlement}",
+        purchase.id,
+        purchase.code,
+        purchase.ticket,
+        purchase.cost,
+        purchase.owner,
+        purchase
+            .strategy
+            .as_ref()
+            .map_or(String::new(), |strategy| format!("（{strategy}）")),
+        purchase
+            .notes
+            .as_ref()
+            .map_or(String::new(), |notes| format!(" {notes}"))
+    );
+}
+
+fn print_profit_and_loss(name: &str, profit_and_loss: &ProfitAndLoss) {
+    println!(
+        "{name}：购买 {} 次（已开奖 {} 次），投入 {} 元（待开奖 {} 元），奖金 {} 元，盈亏 {} 元",
+        profit_and_loss.purchase_count,
+        profit_and_loss.settled_count,
+        profit_and_loss.total_cost,
+        profit_and_loss.pending_cost,
+        profit_and_loss.total_payout,
+        profit_and_loss.profit
+    );
+}
+
+pub fn wallet(
+    records: &[PrBusinessObj],
+    path: &Path,
+    action: &WalletAction,
+    format: OutputFormat,
+) -> Result<(), Error> {
+    match action {
+        WalletAction::Add {
+            ticket,
+            code,
+            owner,
+            strategy,
+            notes,
+        } => {
+            let ticket = parse_ticket(ticket)?;
+            let purchase = Wallet::update(path, |wallet| {
+                let id = wallet
+                    .add(
+                        ticket,
+                        code.clone(),
+                        owner.clone(),
+                        strategy.clone(),
+                        notes.clone(),
+                    )
+                    .id;
+                wallet.settle(records);
+                Ok(wallet.get(id).cloned())
+            })?;
+            if let Some(purchase) = &purchase {
+                match format {
+                    OutputFormat::Text => print_purchase(purchase),
+                    OutputFormat::Json => print_json(purchase)?,
+                }
+            }
+        }
+        WalletAction::List { owner, pending } => {
+            let mut wallet = Wallet::load(path)?;
+            wallet.settle(records);
+            let purchases = wallet
+                .purchases()
+                .iter()
+                .filter(|purchase| owner.as_ref().is_none_or(|owner| &purchase.owner == owner))
+                .filter(|purchase| !pending || purchase.settlement.is_none())
+                .collect::<Vec<&Purchase>>();
+            match format {
+                OutputFormat::Text => purchases.into_iter().for_each(print_purchase),
+                OutputFormat::Json => print_json(&purchases)?,
+            }
+        }
+        WalletAction::Remove { id } => {
+            let purchase = Wallet::update(path, |wallet| {
+                wallet.settle(records);
+                wallet.remove(*id)
+            })?;
+            match format {
+                OutputFormat::Text => {
+                    print!("已删除：");
+                    print_purchase(&purchase);
+                }
+                OutputFormat::Json => print_json(&purchase)?,
+            }
+        }
+        WalletAction::Settle => {
+            let purchases = Wallet::update(path, |wallet| {
+                wallet.settle(records);
+                Ok(wallet.purchases().to_vec())
+            })?;
+            let settled = purchases
+                .iter()
+                .filter(|purchase| purchase.settlement.is_some())
+                .count();
+            match format {
+                OutputFormat::Text => {
+                    println!("共 {} 条购买记录，已兑奖 {settled} 条", purchases.len())
+                }
+                OutputFormat::Json => print_json(&purchases)?,
+            }
+        }
+        WalletAction::Report { by } => {
+            let mut wallet = Wallet::load(path)?;
+            wallet.settle(records);
+            let report = wallet.report(*by);
+            match format {
+                OutputFormat::Text => {
+                    print_profit_and_loss("合计", &report.total);
+                    report.groups.iter().for_each(|(name, profit_and_loss)| {
+                        print_profit_and_loss(name, profit_and_loss)
+                    });
+                }
+                OutputFormat::Json => print_json(&report)?,
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use serde_json::{Map, Value};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -10,6 +11,18 @@ pub enum Error {
     NoFinalProcessorChainResults,
     #[error(transparent)]
     IoFailure(#[from] std::io::Error),
+    #[error(transparent)]
+    FailToSerde(#[from] serde_json::Error),
+    #[error("无效的参数：{0}")]
+    InvalidArgument(String),
+    #[error("没有找到：{0}")]
+    NotFound(String),
+    #[error("{reason}")]
+    Interrupted {
+        reason: ssq_tool_processor::error::Error,
+        /// 已经执行完的执行器的结果
+        partial_results: Map<String, Value>,
+    },
     #[error("其他错误：{0}")]
     Other(String),
 }
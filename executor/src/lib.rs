@@ -0,0 +1,8 @@
+pub mod attributes;
+pub mod cli;
+pub mod command;
+pub mod error;
+pub mod pipeline;
+pub mod service;
+pub mod ticket_check;
+pub mod wallet;
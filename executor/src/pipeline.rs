@@ -0,0 +1,332 @@
+use crate::error::Error;
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use ssq_tool_processor::association_rule::AssociationRuleProcessor;
+use ssq_tool_processor::ball_occurrence::BallOccurrenceProcessor;
+use ssq_tool_processor::ball_relationship_fp::BallRelationshipFpProcessor;
+use ssq_tool_processor::bayesian_ball_probability::BayesianBallProbabilityProcessor;
+use ssq_tool_processor::blue_ball_markov::{BlueBallMarkovProcessor, TransitionSmoothing};
+use ssq_tool_processor::blue_ball_occurrence_fp::BlueBallFollowingOccurrenceProcessor;
+use ssq_tool_processor::ensemble::Ensemble;
+use ssq_tool_processor::expected_value::ExpectedValueProcessor;
+use ssq_tool_processor::final_result::FinalResultsProcessor;
+use ssq_tool_processor::generate_normalize_data::GenerateNormalizeDataProcessor;
+use ssq_tool_processor::hot_cold_ball::HotColdBallProcessor;
+use ssq_tool_processor::portfolio::{PortfolioOptimizer, PortfolioProcessor};
+use ssq_tool_processor::randomness_test::RandomnessTestProcessor;
+use ssq_tool_processor::red_ball_shape::RedBallShapeProcessor;
+use ssq_tool_processor::red_ball_structure::RedBallStructureProcessor;
+use ssq_tool_processor::strategy::{Strategy, StrategyConfig};
+use ssq_tool_processor::ticket_filter::{FilterScope, TicketFilterEngine, TicketFilterProcessor};
+use ssq_tool_processor::{Processor, ProcessorChain};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::info;
+
+/// 分析链中的一个执行器及其参数，没有给出的参数使用执行器的默认值
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProcessorConfig {
+    BallOccurrence,
+    HotColdBall {
+        #[serde(default = "default_temperature_windows")]
+        windows: Vec<usize>,
+        #[serde(default = "default_hot_threshold")]
+        hot_threshold: f64,
+        #[serde(default = "default_cold_threshold")]
+        cold_threshold: f64,
+    },
+    RedBallShape,
+    RedBallStructure,
+    RandomnessTest {
+        #[serde(default = "default_significance_level")]
+        significance_level: f64,
+    },
+    BallRelationshipFp {
+        /// 频繁项集的最小支持度，不能小于 2
+        #[serde(default = "default_minimum_support")]
+        minimum_support: usize,
+    },
+    AssociationRule {
+        #[serde(default = "default_minimum_lift")]
+        minimum_lift: f64,
+        #[serde(default)]
+        minimum_confidence: f64,
+    },
+    BlueBallFollowingOccurrence,
+    BlueBallMarkov {
+        #[serde(default = "default_markov_order")]
+        order: usize,
+        #[serde(default = "default_transition_smoothing")]
+        smoothing: TransitionSmoothing,
+    },
+    ExpectedValue,
+    BayesianBallProbability {
+        #[serde(default = "default_prior_alpha")]
+        prior_alpha: f64,
+        #[serde(default = "default_decay")]
+        decay: f64,
+    },
+    /// 把归一化数据写入 `path`，默认分析链不包含它，需要在配置文件中显式加入
+    GenerateNormalizeData {
+        path: PathBuf,
+    },
+}
+
+fn default_temperature_windows() -> Vec<usize> {
+    vec![10, 30, 100]
+}
+
+fn default_hot_threshold() -> f64 {
+    1.2
+}
+
+fn default_cold_threshold() -> f64 {
+    0.8
+}
+
+fn default_significance_level() -> f64 {
+    0.05
+}
+
+fn default_minimum_support() -> usize {
+    10
+}
+
+fn default_minimum_lift() -> f64 {
+    1.0
+}
+
+fn default_markov_order() -> usize {
+    1
+}
+
+fn default_transition_smoothing() -> TransitionSmoothing {
+    TransitionSmoothing::Laplace
+}
+
+fn default_prior_alpha() -> f64 {
+    1.0
+}
+
+fn default_decay() -> f64 {
+    1.0
+}
+
+impl ProcessorConfig {
+    pub fn build(&self) -> Box<dyn Processor + Send> {
+        match self {
+            ProcessorConfig::BallOccurrence => Box::new(BallOccurrenceProcessor),
+            ProcessorConfig::HotColdBall {
+                windows,
+                hot_threshold,
+                cold_threshold,
+            } => Box::new(HotColdBallProcessor::new(
+                windows.clone(),
+                *hot_threshold,
+                *cold_threshold,
+            )),
+            ProcessorConfig::RedBallShape => Box::new(RedBallShapeProcessor),
+            ProcessorConfig::RedBallStructure => Box::new(RedBallStructureProcessor),
+            ProcessorConfig::RandomnessTest { significance_level } => {
+                Box::new(RandomnessTestProcessor::new(*significance_level))
+            }
+            ProcessorConfig::BallRelationshipFp { minimum_support } => {
+                Box::new(BallRelationshipFpProcessor::new(*minimum_support))
+            }
+            ProcessorConfig::AssociationRule {
+                minimum_lift,
+                minimum_confidence,
+            } => Box::new(AssociationRuleProcessor::new(
+                *minimum_lift,
+                *minimum_confidence,
+            )),
+            ProcessorConfig::BlueBallFollowingOccurrence => {
+                Box::new(BlueBallFollowingOccurrenceProcessor)
+            }
+            ProcessorConfig::BlueBallMarkov { order, smoothing } => {
+                Box::new(BlueBallMarkovProcessor::new(*order, *smoothing))
+            }
+            ProcessorConfig::ExpectedValue => Box::new(ExpectedValueProcessor),
+            ProcessorConfig::BayesianBallProbability { prior_alpha, decay } => {
+                Box::new(BayesianBallProbabilityProcessor::new(*prior_alpha, *decay))
+            }
+            ProcessorConfig::GenerateNormalizeData { path } => {
+                Box::new(GenerateNormalizeDataProcessor::new(path.clone()))
+            }
+        }
+    }
+
+    /// 执行器是否会写入本地文件
+    pub fn writes_files(&self) -> bool {
+        matches!(self, ProcessorConfig::GenerateNormalizeData { .. })
+    }
+}
+
+/// 参与融合的预测策略及其权重
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct WeightedStrategyConfig {
+    #[serde(flatten)]
+    pub strategy: StrategyConfig,
+    #[serde(default = "default_strategy_weight")]
+    pub weight: f64,
+}
+
+fn default_strategy_weight() -> f64 {
+    1.0
+}
+
+/// 从 JSON 文件读取的分析链配置，没有给出的部分使用默认的分析链
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct PipelineConfig {
+    #[serde(default = "default_result_size")]
+    pub result_size: usize,
+    #[serde(default = "default_processors")]
+    pub processors: Vec<ProcessorConfig>,
+    /// 为空时使用最终结果执行器的默认策略
+    #[serde(default)]
+    pub strategies: Vec<WeightedStrategyConfig>,
+    #[serde(default)]
+    pub ensemble: Ensemble,
+    #[serde(default)]
+    pub filter: Option<TicketFilterEngine>,
+    /// 缩水的范围，`full_space` 时缩水报告统计全部 C(33, 6) × 16 注号码
+    #[serde(default)]
+    pub filter_scope: FilterScope,
+    #[serde(default)]
+    pub portfolio: Option<PortfolioOptimizer>,
+    /// 每个执行器的时间限制（秒），不指定时不限制
+    #[serde(default)]
+    pub processor_timeout_secs: Option<u64>,
+    /// 整个分析链的时间限制（秒），不指定时不限制
+    #[serde(default)]
+    pub chain_timeout_secs: Option<u64>,
+}
+
+fn default_result_size() -> usize {
+    5
+}
+
+fn default_processors() -> Vec<ProcessorConfig> {
+    vec![
+        ProcessorConfig::BallOccurrence,
+        ProcessorConfig::HotColdBall {
+            windows: default_temperature_windows(),
+            hot_threshold: default_hot_threshold(),
+            cold_threshold: default_cold_threshold(),
+        },
+        ProcessorConfig::RedBallShape,
+        ProcessorConfig::RedBallStructure,
+        ProcessorConfig::RandomnessTest {
+            significance_level: default_significance_level(),
+        },
+        ProcessorConfig::BallRelationshipFp {
+            minimum_support: default_minimum_support(),
+        },
+        ProcessorConfig::AssociationRule {
+            minimum_lift: default_minimum_lift(),
+            minimum_confidence: 0.0,
+        },
+        ProcessorConfig::BlueBallFollowingOccurrence,
+        ProcessorConfig::BlueBallMarkov {
+            order: default_markov_order(),
+            smoothing: default_transition_smoothing(),
+        },
+        ProcessorConfig::ExpectedValue,
+        ProcessorConfig::BayesianBallProbability {
+            prior_alpha: default_prior_alpha(),
+            decay: default_decay(),
+        },
+    ]
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            result_size: default_result_size(),
+            processors: default_processors(),
+            strategies: vec![],
+            ensemble: Ensemble::default(),
+            filter: None,
+            filter_scope: FilterScope::default(),
+            portfolio: None,
+            processor_timeout_secs: None,
+            chain_timeout_secs: None,
+        }
+    }
+}
+
+impl PipelineConfig {
+    /// 没有给出配置文件时使用默认的分析链
+    pub fn load(path: Option<&Path>) -> Result<Self, Error> {
+        match path {
+            Some(path) => {
+                info!("从 {} 读取分析链配置...", path.display());
+                Ok(serde_json::from_reader(File::open(path)?)?)
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// 服务请求中的分析链配置来自网络上的调用方，不能包含写入本地文件的执行器，
+    /// 这类执行器只能通过命令行 --config 指定的配置文件使用
+    pub fn check_service_request(&self) -> Result<(), Error> {
+        match self
+            .processors
+            .iter()
+            .find(|processor| processor.writes_files())
+        {
+            Some(processor) => Err(Error::InvalidArgument(format!(
+                "服务请求的分析链配置不能包含写入文件的执行器：{processor:?}"
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// 只包含分析执行器的分析链
+    pub fn analysis_chain(&self) -> ProcessorChain {
+        self.processors.iter().fold(
+            self.limit_time(ProcessorChain::new("analysis_processor_chain")),
+            |chain, processor| chain.add_processor(processor.build()),
+        )
+    }
+
+    fn limit_time(&self, chain: ProcessorChain) -> ProcessorChain {
+        chain
+            .processor_timeout(self.processor_timeout_secs.map(Duration::from_secs))
+            .timeout(self.chain_timeout_secs.map(Duration::from_secs))
+    }
+
+    /// 在分析执行器之后生成最终结果，并按照配置缩水和组合号码的分析链
+    pub fn prediction_chain(&self) -> ProcessorChain {
+        let final_results_processor = if self.strategies.is_empty() {
+            FinalResultsProcessor::new(self.result_size)
+        } else {
+            let strategies = self
+                .strategies
+                .iter()
+                .map(|weighted| (weighted.strategy.build(), weighted.weight))
+                .collect::<Vec<(Box<dyn Strategy + Send>, f64)>>();
+            FinalResultsProcessor::with_strategies(self.result_size, strategies, self.ensemble)
+        };
+        let mut chain = self
+            .processors
+            .iter()
+            .fold(
+                self.limit_time(ProcessorChain::new("prediction_processor_chain")),
+                |chain, processor| chain.add_processor(processor.build()),
+            )
+            .add_processor(Box::new(final_results_processor));
+        if let Some(filter) = &self.filter {
+            chain = chain.add_processor(Box::new(TicketFilterProcessor::new(
+                filter.clone(),
+                self.filter_scope,
+            )));
+        }
+        if let Some(portfolio) = &self.portfolio {
+            chain = chain.add_processor(Box::new(PortfolioProcessor::new(portfolio.clone())));
+        }
+        chain
+    }
+}
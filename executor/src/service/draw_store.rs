@@ -0,0 +1,161 @@
+use crate::error::Error;
+use crate::wallet::{DEFAULT_WALLET_FILE, Wallet};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveTime, Utc, Weekday};
+use serde::Serialize;
+use ssq_tool_collector::Collector;
+use ssq_tool_domain::PrBusinessObj;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+/// 开奖日为每周二、四、日
+const DRAW_DAYS: [Weekday; 3] = [Weekday::Tue, Weekday::Thu, Weekday::Sun];
+/// 北京时间 21:15 开奖，官网通常在半小时内公布开奖结果
+const DRAW_RESULT_PUBLISH_TIME: (u32, u32) = (21, 45);
+/// 北京时间与 UTC 的时差（秒）
+const BEIJING_UTC_OFFSET: i32 = 8 * 3600;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename = "刷新结果")]
+pub struct RefreshOutcome {
+    #[serde(rename = "刷新前期数")]
+    pub previous_count: usize,
+    #[serde(rename = "刷新后期数")]
+    pub count: usize,
+    #[serde(rename = "最近一期期号")]
+    pub latest_code: Option<String>,
+    #[serde(rename = "新增期号")]
+    pub new_codes: Vec<String>,
+    /// 刷新后钱包中完成兑奖的购买记录数
+    #[serde(rename = "兑奖记录数")]
+    pub settled_count: usize,
+}
+
+/// 往期中奖数据的共享快照。读取时取得当前快照的 `Arc`，刷新时整体替换快照，
+/// 正在处理的请求继续使用它取得的旧快照，不会看到更新到一半的数据
+#[derive(Debug)]
+pub struct DrawStore {
+    snapshot: RwLock<Arc<Vec<PrBusinessObj>>>,
+    /// 刷新时只保留最近若干期，`None` 表示保留全部
+    record_size: Option<usize>,
+    /// 同一时间只允许一次刷新
+    refreshing: Mutex<()>,
+    /// 购彩钱包文件，刷新到新的开奖结果后对其兑奖
+    wallet_path: PathBuf,
+}
+
+impl DrawStore {
+    pub fn new(records: Vec<PrBusinessObj>, record_size: Option<usize>) -> Self {
+        Self {
+            snapshot: RwLock::new(Arc::new(records)),
+            record_size,
+            refreshing: Mutex::new(()),
+            wallet_path: PathBuf::from(DEFAULT_WALLET_FILE),
+        }
+    }
+
+    pub fn with_wallet_path(mut self, wallet_path: PathBuf) -> Self {
+        self.wallet_path = wallet_path;
+        self
+    }
+
+    pub fn wallet_path(&self) -> &Path {
+        &self.wallet_path
+    }
+
+    /// 当前的往期中奖数据快照，按照从最近到最早排列
+    pub fn snapshot(&self) -> Arc<Vec<PrBusinessObj>> {
+        match self.snapshot.read() {
+            Ok(snapshot) => snapshot.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// 替换当前快照，返回新旧快照的差异
+    pub fn replace(&self, records: Vec<PrBusinessObj>) -> RefreshOutcome {
+        let records = Arc::new(records);
+        let previous = {
+            let mut snapshot = match self.snapshot.write() {
+                Ok(snapshot) => snapshot,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            std::mem::replace(&mut *snapshot, records.clone())
+        };
+        let new_codes = records
+            .iter()
+            .filter(|record| !previous.iter().any(|old| old.code == record.code))
+            .map(|record| record.code.clone())
+            .collect::<Vec<String>>();
+        RefreshOutcome {
+            previous_count: previous.len(),
+            count: records.len(),
+            latest_code: records
+                .iter()
+                .max_by_key(|record| record.date)
+                .map(|record| record.code.clone()),
+            new_codes,
+            settled_count: 0,
+        }
+    }
+
+    /// 从官网重新收集往期中奖数据并替换快照，同时更新本地数据文件，有新的开奖结果时对钱包兑奖
+    pub async fn refresh(&self) -> Result<RefreshOutcome, Error> {
+        let _guard = self.refreshing.lock().await;
+        info!("开始从官网刷新往期双色球数据...");
+        let records = Collector::Remote.collect(self.record_size).await?;
+        let mut outcome = self.replace(records);
+        // 快照已经替换，兑奖失败不影响刷新结果。钱包的读写会等待文件锁，放到阻塞线程中执行
+        if !outcome.new_codes.is_empty() {
+            let wallet_path = self.wallet_path.clone();
+            let snapshot = self.snapshot();
+            match tokio::task::spawn_blocking(move || Wallet::settle_file(&wallet_path, &snapshot))
+                .await
+            {
+                Ok(Ok(settled_count)) => outcome.settled_count = settled_count,
+                Ok(Err(e)) => error!("刷新后对钱包兑奖失败：{e:?}"),
+                Err(e) => error!("刷新后对钱包兑奖的任务异常退出：{e:?}"),
+            }
+        }
+        info!(
+            "刷新往期双色球数据完成，共 {} 期，新增期号：{:?}",
+            outcome.count, outcome.new_codes
+        );
+        Ok(outcome)
+    }
+
+    /// 当前时间是否已经过了开奖日的开奖结果公布时间，而快照中还没有当天的开奖结果
+    pub fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        let now = now.with_timezone(&FixedOffset::east_opt(BEIJING_UTC_OFFSET).unwrap());
+        let today = now.date_naive();
+        let publish_time =
+            NaiveTime::from_hms_opt(DRAW_RESULT_PUBLISH_TIME.0, DRAW_RESULT_PUBLISH_TIME.1, 0)
+                .unwrap();
+        let latest_date = self.snapshot().iter().map(|record| record.date).max();
+        DRAW_DAYS.contains(&today.weekday())
+            && now.time() >= publish_time
+            && latest_date.is_none_or(|latest_date| latest_date < today)
+    }
+
+    /// 启动后台刷新任务，每隔 `interval` 检查一次，开奖日公布结果后刷新直到取得当天的开奖结果
+    pub fn spawn_refresh_task(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        info!("启动后台刷新任务，检查间隔：{interval:?}");
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if !self.is_stale(Utc::now()) {
+                    continue;
+                }
+                match self.refresh().await {
+                    Ok(outcome) if outcome.new_codes.is_empty() => {
+                        warn!("官网还没有公布今天的开奖结果，稍后重试")
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("后台刷新往期双色球数据失败：{e:?}"),
+                }
+            }
+        })
+    }
+}
@@ -1 +1,4 @@
+pub mod draw_store;
+pub mod rest;
 pub mod ssq_mcp_service;
+pub mod transport;
@@ -0,0 +1,728 @@
+use crate::attributes::{RunChainRequest, attributes_schema, run_chain};
+use crate::command::{BacktestRequest, BacktestSummary, latest_draw, run_backtest};
+use crate::error::Error;
+use crate::service::draw_store::DrawStore;
+use crate::service::ssq_mcp_service::CheckTicketRequest;
+use crate::ticket_check::{CheckScope, TicketCheckReport, check_tickets, parse_ticket};
+use actix_web::http::{Method, StatusCode};
+use actix_web::web::Bytes;
+use actix_web::{HttpResponse, ResponseError, Route, web};
+use chrono::NaiveDate;
+use rmcp::schemars::generate::SchemaSettings;
+use rmcp::schemars::{self, JsonSchema, SchemaGenerator};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use ssq_tool_domain::{PrBusinessObj, Ticket};
+use ssq_tool_processor::error::Error as ProcessorError;
+use ssq_tool_processor::progress::{ProgressEvent, ProgressReporter};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+
+pub const DEFAULT_API_PATH: &str = "/ssq/api";
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename = "错误")]
+pub struct ErrorResponse {
+    #[serde(rename = "错误信息")]
+    pub message: String,
+    /// 分析被取消或者超时时已经执行完的执行器的结果
+    #[serde(rename = "部分结果", skip_serializing_if = "Option::is_none")]
+    pub partial_results: Option<Map<String, Value>>,
+}
+
+impl From<&Error> for ErrorResponse {
+    fn from(e: &Error) -> Self {
+        Self {
+            message: e.to_string(),
+            partial_results: match e {
+                Error::Interrupted {
+                    partial_results, ..
+                } => Some(partial_results.clone()),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Error::InvalidArgument(_) => StatusCode::BAD_REQUEST,
+            Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::Interrupted { reason, .. } | Error::Processor(reason)
+                if reason.is_interrupted() =>
+            {
+                match reason {
+                    ProcessorError::Cancelled(_) => StatusCode::SERVICE_UNAVAILABLE,
+                    _ => StatusCode::GATEWAY_TIMEOUT,
+                }
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(ErrorResponse::from(self))
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DrawQuery {
+    #[schemars(description = "开奖期号")]
+    pub code: Option<String>,
+    #[schemars(description = "星期几，例如 星期二")]
+    pub day: Option<String>,
+    #[schemars(description = "起始日期（包含）")]
+    pub start: Option<NaiveDate>,
+    #[schemars(description = "结束日期（包含）")]
+    pub end: Option<NaiveDate>,
+    #[schemars(description = "只返回最近的若干期")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ChainQuery {
+    #[schemars(description = "只使用最近若干期的中奖数据，不指定时使用全部中奖数据")]
+    pub recent_size: Option<usize>,
+    #[schemars(description = "需要返回的属性名称，多个名称用逗号分隔")]
+    pub attributes: Option<String>,
+}
+
+impl From<ChainQuery> for RunChainRequest {
+    fn from(query: ChainQuery) -> Self {
+        Self {
+            config: None,
+            recent_size: query.recent_size,
+            attributes: query.attributes.map(|attributes| {
+                attributes
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }),
+        }
+    }
+}
+
+type Store = web::Data<Arc<DrawStore>>;
+type Runtime = web::Data<Handle>;
+
+async fn list_draws(
+    store: Store,
+    query: web::Query<DrawQuery>,
+) -> Result<web::Json<Vec<PrBusinessObj>>, Error> {
+    let DrawQuery {
+        code,
+        day,
+        start,
+        end,
+        limit,
+    } = query.into_inner();
+    let draws = store
+        .snapshot()
+        .iter()
+        .filter(|record| code.as_ref().is_none_or(|code| &record.code == code))
+        .filter(|record| {
+            day.as_ref()
+                .is_none_or(|day| record.day.contains(day.as_str()))
+        })
+        .filter(|record| start.is_none_or(|start| record.date >= start))
+        .filter(|record| end.is_none_or(|end| record.date <= end))
+        .take(limit.unwrap_or(usize::MAX))
+        .cloned()
+        .collect();
+    Ok(web::Json(draws))
+}
+
+async fn get_latest_draw(store: Store) -> Result<web::Json<PrBusinessObj>, Error> {
+    let records = store.snapshot();
+    Ok(web::Json(latest_draw(&records)?.clone()))
+}
+
+async fn get_draw(
+    store: Store,
+    code: web::Path<String>,
+) -> Result<web::Json<PrBusinessObj>, Error> {
+    store
+        .snapshot()
+        .iter()
+        .find(|record| record.code == *code)
+        .cloned()
+        .map(web::Json)
+        .ok_or(Error::NotFound(format!("期号 {code}")))
+}
+
+/// 在 `runtime` 上执行 `run` 并等待结果。分析不在 HTTP 工作线程上执行，不会阻塞工作线程上的其他请求，
+/// 客户端断开连接、请求被丢弃时取消仍在执行的分析
+async fn spawn_analysis<F, Fut, T>(runtime: &Handle, run: F) -> Result<T, Error>
+where
+    F: FnOnce(CancellationToken) -> Fut,
+    Fut: Future<Output = Result<T, Error>> + Send + 'static,
+    T: Send + 'static,
+{
+    let cancellation_token = CancellationToken::new();
+    let _guard = cancellation_token.clone().drop_guard();
+    runtime
+        .spawn(run(cancellation_token))
+        .await
+        .map_err(|e| Error::Other(format!("分析任务异常退出：{e}")))?
+}
+
+async fn chain(
+    store: Store,
+    runtime: Runtime,
+    request: RunChainRequest,
+    prediction: bool,
+) -> Result<web::Json<Map<String, Value>>, Error> {
+    let records = store.snapshot();
+    spawn_analysis(&runtime, move |cancellation_token| async move {
+        run_chain(&records, request, prediction, None, cancellation_token).await
+    })
+    .await
+    .map(web::Json)
+}
+
+async fn get_stats(
+    store: Store,
+    runtime: Runtime,
+    query: web::Query<ChainQuery>,
+) -> Result<web::Json<Map<String, Value>>, Error> {
+    chain(store, runtime, query.into_inner().into(), false).await
+}
+
+async fn post_analysis(
+    store: Store,
+    runtime: Runtime,
+    request: web::Json<RunChainRequest>,
+) -> Result<web::Json<Map<String, Value>>, Error> {
+    chain(store, runtime, request.into_inner(), false).await
+}
+
+async fn get_predictions(
+    store: Store,
+    runtime: Runtime,
+    query: web::Query<ChainQuery>,
+) -> Result<web::Json<Map<String, Value>>, Error> {
+    chain(store, runtime, query.into_inner().into(), true).await
+}
+
+async fn post_predictions(
+    store: Store,
+    runtime: Runtime,
+    request: web::Json<RunChainRequest>,
+) -> Result<web::Json<Map<String, Value>>, Error> {
+    chain(store, runtime, request.into_inner(), true).await
+}
+
+async fn post_ticket_check(
+    store: Store,
+    request: web::Json<CheckTicketRequest>,
+) -> Result<web::Json<Vec<TicketCheckReport>>, Error> {
+    let CheckTicketRequest {
+        tickets,
+        code,
+        all_draws,
+    } = request.into_inner();
+    let tickets = tickets
+        .iter()
+        .map(|ticket| parse_ticket(ticket))
+        .collect::<Result<Vec<Ticket>, Error>>()?;
+    if tickets.is_empty() {
+        return Err(Error::InvalidArgument("需要给出至少一张彩票".to_string()));
+    }
+    let scope = match (&code, all_draws) {
+        (Some(code), _) => CheckScope::Code(code),
+        (None, true) => CheckScope::All,
+        (None, false) => CheckScope::Latest,
+    };
+    let records = store.snapshot();
+    Ok(web::Json(check_tickets(&records, &tickets, scope)?))
+}
+
+async fn post_backtest(
+    store: Store,
+    runtime: Runtime,
+    request: web::Json<BacktestRequest>,
+) -> Result<web::Json<BacktestSummary>, Error> {
+    let (config, args) = request.into_inner().into_config_and_args()?;
+    let records = store.snapshot();
+    spawn_analysis(&runtime, move |cancellation_token| async move {
+        run_backtest(&records, &config, &args, None, cancellation_token).await
+    })
+    .await
+    .map(web::Json)
+}
+
+fn sse_frame(event: &str, data: &impl Serialize) -> Bytes {
+    match serde_json::to_string(data) {
+        Ok(data) => Bytes::from(format!("event: {event}\ndata: {data}\n\n")),
+        Err(e) => {
+            error!("序列化服务器推送事件失败：{e:?}");
+            let message = serde_json::to_string(&ErrorResponse {
+                message: e.to_string(),
+                partial_results: None,
+            })
+            .unwrap_or_default();
+            Bytes::from(format!("event: error\ndata: {message}\n\n"))
+        }
+    }
+}
+
+/// 在 `runtime` 上执行 `run`，以服务器推送事件返回执行过程中的 `progress` 事件，
+/// 最后返回 `result` 或 `error` 事件。分析不在 HTTP 工作线程上执行，
+/// 工作线程才能及时发现客户端断开连接并取消分析
+fn event_stream<F, Fut, T>(runtime: &Handle, run: F) -> HttpResponse
+where
+    F: FnOnce(ProgressReporter, CancellationToken) -> Fut,
+    Fut: Future<Output = Result<T, Error>> + Send + 'static,
+    T: Serialize,
+{
+    let (sender, receiver) = mpsc::unbounded_channel::<Bytes>();
+    let progress_sender = sender.clone();
+    let progress_reporter = ProgressReporter::new(move |event| {
+        let _ = progress_sender.send(sse_frame("progress", &event));
+    });
+    let cancellation_token = CancellationToken::new();
+    let task = run(progress_reporter, cancellation_token.clone());
+    runtime.spawn(async move {
+        let frame = match task.await {
+            Ok(result) => sse_frame("result", &result),
+            Err(e) => {
+                info!("分析没有完成：{e}");
+                sse_frame("error", &ErrorResponse::from(&e))
+            }
+        };
+        let _ = sender.send(frame);
+    });
+    // 客户端断开连接、事件流被丢弃时取消仍在执行的分析
+    let guard = cancellation_token.drop_guard();
+    let stream = UnboundedReceiverStream::new(receiver).map(move |frame| {
+        let _guard = &guard;
+        Ok::<Bytes, Infallible>(frame)
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream)
+}
+
+async fn chain_events(
+    store: Store,
+    runtime: Runtime,
+    request: RunChainRequest,
+    prediction: bool,
+) -> HttpResponse {
+    let records = store.snapshot();
+    event_stream(
+        &runtime,
+        move |progress_reporter, cancellation_token| async move {
+            run_chain(
+                &records,
+                request,
+                prediction,
+                Some(progress_reporter),
+                cancellation_token,
+            )
+            .await
+        },
+    )
+}
+
+async fn post_analysis_events(
+    store: Store,
+    runtime: Runtime,
+    request: web::Json<RunChainRequest>,
+) -> HttpResponse {
+    chain_events(store, runtime, request.into_inner(), false).await
+}
+
+async fn post_prediction_events(
+    store: Store,
+    runtime: Runtime,
+    request: web::Json<RunChainRequest>,
+) -> HttpResponse {
+    chain_events(store, runtime, request.into_inner(), true).await
+}
+
+async fn post_backtest_events(
+    store: Store,
+    runtime: Runtime,
+    request: web::Json<BacktestRequest>,
+) -> Result<HttpResponse, Error> {
+    let (config, args) = request.into_inner().into_config_and_args()?;
+    let records = store.snapshot();
+    Ok(event_stream(
+        &runtime,
+        move |progress_reporter, cancellation_token| async move {
+            run_backtest(
+                &records,
+                &config,
+                &args,
+                Some(progress_reporter),
+                cancellation_token,
+            )
+            .await
+        },
+    ))
+}
+
+/// 把查询参数类型的每个字段展开为 OpenAPI 的查询参数
+fn query_parameters<T: JsonSchema>() -> Vec<Value> {
+    let schema = SchemaSettings::openapi3()
+        .into_generator()
+        .into_root_schema_for::<T>();
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .map(|(name, property)| {
+            let mut property = property.clone();
+            let description = property
+                .as_object_mut()
+                .and_then(|property| property.remove("description"));
+            json!({
+                "name": name,
+                "in": "query",
+                "required": required.contains(&Value::String(name.clone())),
+                "description": description,
+                "schema": property,
+            })
+        })
+        .collect()
+}
+
+fn schema<T: JsonSchema>(generator: &mut SchemaGenerator) -> Value {
+    generator.subschema_for::<T>().to_value()
+}
+
+fn operation(
+    generator: &mut SchemaGenerator,
+    summary: &str,
+    parameters: Vec<Value>,
+    request_body: Option<Value>,
+    response: Value,
+) -> Value {
+    let error = schema::<ErrorResponse>(generator);
+    let mut operation = json!({
+        "summary": summary,
+        "parameters": parameters,
+        "responses": {
+            "200": {
+                "description": "成功",
+                "content": { "application/json": { "schema": response } },
+            },
+            "default": {
+                "description": "失败",
+                "content": { "application/json": { "schema": error } },
+            },
+        },
+    });
+    if let Some(request_body) = request_body {
+        operation["requestBody"] = json!({
+            "required": true,
+            "content": { "application/json": { "schema": request_body } },
+        });
+    }
+    operation
+}
+
+/// 以服务器推送事件返回进度与结果的操作，`result` 为结果事件数据的 JSON Schema
+fn event_operation(
+    generator: &mut SchemaGenerator,
+    summary: &str,
+    request_body: Value,
+    result: Value,
+) -> Value {
+    let progress = schema::<ProgressEvent>(generator);
+    let error = schema::<ErrorResponse>(generator);
+    json!({
+        "summary": summary,
+        "requestBody": {
+            "required": true,
+            "content": { "application/json": { "schema": request_body } },
+        },
+        "responses": {
+            "200": {
+                "description": "依次推送 progress 事件，数据为进度；最后推送 result 事件，数据为结果，或者 error 事件，数据为错误。客户端断开连接时取消分析",
+                "content": { "text/event-stream": { "schema": { "type": "string" } } },
+                "x-events": {
+                    "progress": progress,
+                    "result": result,
+                    "error": error,
+                },
+            },
+            "default": {
+                "description": "失败",
+                "content": { "application/json": { "schema": error } },
+            },
+        },
+    })
+}
+
+async fn get_openapi(openapi: web::Data<Value>) -> HttpResponse {
+    HttpResponse::Ok().json(openapi.get_ref())
+}
+
+/// REST 接口的一个路由。`configure` 按照路由表注册处理函数，`openapi_document` 按照同一张表生成文档
+struct Endpoint {
+    method: Method,
+    path: &'static str,
+    /// 把处理函数挂到路由上
+    handler: fn(Route) -> Route,
+    /// 由请求与响应类型的 JSON Schema 生成的 OpenAPI 操作
+    operation: fn(&mut SchemaGenerator) -> Value,
+}
+
+static ENDPOINTS: [Endpoint; 13] = [
+    Endpoint {
+        method: Method::GET,
+        path: "/draws",
+        handler: |route| route.to(list_draws),
+        operation: |generator| {
+            let response = schema::<Vec<PrBusinessObj>>(generator);
+            operation(
+                generator,
+                "按照期号、星期几与日期范围查询往期中奖数据",
+                query_parameters::<DrawQuery>(),
+                None,
+                response,
+            )
+        },
+    },
+    Endpoint {
+        method: Method::GET,
+        path: "/draws/latest",
+        handler: |route| route.to(get_latest_draw),
+        operation: |generator| {
+            let response = schema::<PrBusinessObj>(generator);
+            operation(generator, "最近一期中奖数据", vec![], None, response)
+        },
+    },
+    Endpoint {
+        method: Method::GET,
+        path: "/draws/{code}",
+        handler: |route| route.to(get_draw),
+        operation: |generator| {
+            let response = schema::<PrBusinessObj>(generator);
+            let code = json!({
+                "name": "code",
+                "in": "path",
+                "required": true,
+                "description": "开奖期号",
+                "schema": { "type": "string" },
+            });
+            operation(generator, "指定期号的中奖数据", vec![code], None, response)
+        },
+    },
+    Endpoint {
+        method: Method::GET,
+        path: "/stats",
+        handler: |route| route.to(get_stats),
+        operation: |generator| {
+            let response = attributes_schema(generator);
+            operation(
+                generator,
+                "在默认分析链上统计最近若干期的中奖数据",
+                query_parameters::<ChainQuery>(),
+                None,
+                response,
+            )
+        },
+    },
+    Endpoint {
+        method: Method::POST,
+        path: "/analysis",
+        handler: |route| route.to(post_analysis),
+        operation: |generator| {
+            let request = schema::<RunChainRequest>(generator);
+            let response = attributes_schema(generator);
+            operation(
+                generator,
+                "执行可配置的分析链",
+                vec![],
+                Some(request),
+                response,
+            )
+        },
+    },
+    Endpoint {
+        method: Method::GET,
+        path: "/predictions",
+        handler: |route| route.to(get_predictions),
+        operation: |generator| {
+            let response = attributes_schema(generator);
+            operation(
+                generator,
+                "使用默认预测链生成预测号码",
+                query_parameters::<ChainQuery>(),
+                None,
+                response,
+            )
+        },
+    },
+    Endpoint {
+        method: Method::POST,
+        path: "/predictions",
+        handler: |route| route.to(post_predictions),
+        operation: |generator| {
+            let request = schema::<RunChainRequest>(generator);
+            let response = attributes_schema(generator);
+            operation(
+                generator,
+                "执行可配置的预测链",
+                vec![],
+                Some(request),
+                response,
+            )
+        },
+    },
+    Endpoint {
+        method: Method::POST,
+        path: "/tickets/check",
+        handler: |route| route.to(post_ticket_check),
+        operation: |generator| {
+            let request = schema::<CheckTicketRequest>(generator);
+            let response = schema::<Vec<TicketCheckReport>>(generator);
+            operation(
+                generator,
+                "检查彩票的中奖情况",
+                vec![],
+                Some(request),
+                response,
+            )
+        },
+    },
+    Endpoint {
+        method: Method::POST,
+        path: "/backtests",
+        handler: |route| route.to(post_backtest),
+        operation: |generator| {
+            let request = schema::<BacktestRequest>(generator);
+            let response = schema::<BacktestSummary>(generator);
+            operation(
+                generator,
+                "逐期前推回测预测链",
+                vec![],
+                Some(request),
+                response,
+            )
+        },
+    },
+    Endpoint {
+        method: Method::POST,
+        path: "/analysis/events",
+        handler: |route| route.to(post_analysis_events),
+        operation: |generator| {
+            let request = schema::<RunChainRequest>(generator);
+            let result = attributes_schema(generator);
+            event_operation(
+                generator,
+                "执行可配置的分析链，以服务器推送事件返回进度与结果",
+                request,
+                result,
+            )
+        },
+    },
+    Endpoint {
+        method: Method::POST,
+        path: "/predictions/events",
+        handler: |route| route.to(post_prediction_events),
+        operation: |generator| {
+            let request = schema::<RunChainRequest>(generator);
+            let result = attributes_schema(generator);
+            event_operation(
+                generator,
+                "执行可配置的预测链，以服务器推送事件返回进度与结果",
+                request,
+                result,
+            )
+        },
+    },
+    Endpoint {
+        method: Method::POST,
+        path: "/backtests/events",
+        handler: |route| route.to(post_backtest_events),
+        operation: |generator| {
+            let request = schema::<BacktestRequest>(generator);
+            let result = schema::<BacktestSummary>(generator);
+            event_operation(
+                generator,
+                "逐期前推回测预测链，以服务器推送事件返回进度与结果",
+                request,
+                result,
+            )
+        },
+    },
+    Endpoint {
+        method: Method::GET,
+        path: "/openapi.json",
+        handler: |route| route.to(get_openapi),
+        operation: |generator| {
+            operation(
+                generator,
+                "OpenAPI 文档",
+                vec![],
+                None,
+                json!({ "type": "object" }),
+            )
+        },
+    },
+];
+
+/// 由路由表以及请求与响应类型的 JSON Schema 生成的 OpenAPI 文档
+pub fn openapi_document(api_path: &str) -> Value {
+    let mut generator: SchemaGenerator = SchemaSettings::openapi3().into_generator();
+    let mut paths = Map::new();
+    for endpoint in &ENDPOINTS {
+        let operation = (endpoint.operation)(&mut generator);
+        paths
+            .entry(format!("{api_path}{}", endpoint.path))
+            .or_insert_with(|| json!({}))[endpoint.method.as_str().to_lowercase()] = operation;
+    }
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "双色球数据分析服务",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": generator.take_definitions(true),
+        },
+    })
+}
+
+/// REST 接口，挂载在 `api_path` 下。分析在 `runtime` 上执行
+pub fn configure(draw_store: Arc<DrawStore>, runtime: Handle, api_path: &str) -> actix_web::Scope {
+    let openapi = web::Data::new(openapi_document(api_path));
+    let scope =
+        web::scope(api_path)
+            .app_data(web::Data::new(draw_store))
+            .app_data(web::Data::new(runtime))
+            .app_data(openapi)
+            .app_data(web::JsonConfig::default().error_handler(|e, _| {
+                Error::InvalidArgument(format!("无效的请求内容：{e}")).into()
+            }))
+            .app_data(web::QueryConfig::default().error_handler(|e, _| {
+                Error::InvalidArgument(format!("无效的查询参数：{e}")).into()
+            }));
+    ENDPOINTS.iter().fold(scope, |scope, endpoint| {
+        scope.route(
+            endpoint.path,
+            (endpoint.handler)(web::method(endpoint.method.clone())),
+        )
+    })
+}
@@ -1,13 +1,37 @@
+use crate::attributes::{RunChainRequest, run_chain};
+use crate::command::{BacktestRequest, format_draw, latest_draw, run_backtest};
+use crate::error::Error;
+use crate::service::draw_store::DrawStore;
+use crate::ticket_check::{CheckScope, check_tickets, parse_ticket};
+use crate::wallet::{Purchase, Wallet, WalletGroup};
 use chrono::NaiveDate;
+use itertools::Itertools;
+use rmcp::handler::server::router::prompt::PromptRouter;
 use rmcp::handler::server::tool::ToolRouter;
 use rmcp::handler::server::wrapper::Parameters;
-use rmcp::model::{CallToolResult, Content, ServerCapabilities, ServerInfo};
-use rmcp::{ErrorData, ServerHandler, schemars, tool, tool_handler, tool_router};
-use ssq_tool_domain::PrBusinessObj;
+use rmcp::model::{
+    AnnotateAble, CallToolResult, Content, GetPromptRequestParam, GetPromptResult,
+    ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult, PaginatedRequestParam,
+    ProgressNotificationParam, PromptMessage, PromptMessageRole, RawResource, RawResourceTemplate,
+    ReadResourceRequestParam, ReadResourceResult, ResourceContents, ServerCapabilities, ServerInfo,
+};
+use rmcp::service::RequestContext;
+use rmcp::{
+    ErrorData, RoleServer, ServerHandler, prompt, prompt_handler, prompt_router, schemars, tool,
+    tool_handler, tool_router,
+};
+use ssq_tool_domain::{PrBusinessObj, Ticket};
 use ssq_tool_processor::ball_occurrence::BallOccurrenceProcessor;
 use ssq_tool_processor::context::ProcessorContext;
+use ssq_tool_processor::progress::{ProgressEvent, ProgressReporter};
+use ssq_tool_processor::view::BallOccurrenceView;
 use ssq_tool_processor::{BALL_OCCURRENCE, ProcessorChain};
-use tracing::error;
+use std::sync::Arc;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct FilterByWeekRequest {
@@ -31,28 +55,301 @@ pub struct FilterByDateRangeRequest {
     pub end: NaiveDate,
 }
 
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CheckTicketRequest {
+    #[schemars(
+        description = "彩票号码，支持单式 01,02,03,04,05,06+07、复式 01,02,03,04,05,06,07+08,09 与胆拖 01,02#03,04,05,06,07+08"
+    )]
+    pub tickets: Vec<String>,
+    #[schemars(description = "开奖期号，不指定时使用最近一期")]
+    pub code: Option<String>,
+    #[schemars(description = "是否检查所有往期，只返回中奖的期数")]
+    #[serde(default)]
+    pub all_draws: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct AddWalletPurchaseRequest {
+    #[schemars(description = "彩票号码，格式与检查彩票相同")]
+    pub ticket: String,
+    #[schemars(description = "开奖期号")]
+    pub code: String,
+    #[schemars(description = "购买人")]
+    pub owner: String,
+    #[schemars(description = "号码来源策略")]
+    pub strategy: Option<String>,
+    #[schemars(description = "备注")]
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ListWalletPurchasesRequest {
+    #[schemars(description = "只列出指定购买人的记录")]
+    pub owner: Option<String>,
+    #[schemars(description = "是否只列出还没有开奖的记录")]
+    #[serde(default)]
+    pub pending: bool,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WalletReportRequest {
+    #[schemars(description = "分组方式：owner 按照购买人，strategy 按照策略")]
+    pub group_by: WalletGroup,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct AnalyzeRecentDrawsPrompt {
+    #[schemars(description = "分析最近多少期的中奖数据")]
+    pub recent_size: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct CheckMyTicketPrompt {
+    #[schemars(description = "彩票号码，格式与检查彩票相同，多张彩票用分号分隔")]
+    pub tickets: String,
+    #[schemars(description = "开奖期号，不指定时使用最近一期")]
+    pub code: Option<String>,
+}
+
+/// 参数错误返回给客户端，其他错误作为服务内部错误
+fn to_error_data(e: Error) -> ErrorData {
+    match e {
+        Error::InvalidArgument(_) => ErrorData::invalid_params(e.to_string(), None),
+        Error::Processor(ref reason) if reason.is_interrupted() => {
+            info!("{e}");
+            ErrorData::internal_error(e.to_string(), None)
+        }
+        e => {
+            error!("处理 MCP 请求失败：{e:?}");
+            ErrorData::internal_error(e.to_string(), None)
+        }
+    }
+}
+
+/// 工具调用的进度与取消。客户端在请求中给出进度令牌时把分析的进度转发为 MCP 进度通知，
+/// 客户端取消请求时通过请求的取消令牌停止分析
+struct ToolCallObserver {
+    progress_reporter: Option<ProgressReporter>,
+    cancellation_token: CancellationToken,
+    /// 把进度转发为 MCP 进度通知的任务
+    progress_forwarding: Option<JoinHandle<()>>,
+}
+
+impl ToolCallObserver {
+    fn new(context: &RequestContext<RoleServer>) -> Self {
+        let (progress_reporter, progress_forwarding) = context
+            .meta
+            .get_progress_token()
+            .map(|progress_token| {
+                let (sender, mut receiver) = mpsc::unbounded_channel::<ProgressEvent>();
+                let peer = context.peer.clone();
+                let progress_forwarding = tokio::spawn(async move {
+                    while let Some(event) = receiver.recv().await {
+                        let notification = ProgressNotificationParam {
+                            progress_token: progress_token.clone(),
+                            progress: event.completed() as f64,
+                            total: event.total().map(|total| total as f64),
+                            message: Some(format!("{}：{}", event.stage(), event.message())),
+                        };
+                        if let Err(e) = peer.notify_progress(notification).await {
+                            warn!("发送进度通知失败：{e}");
+                            break;
+                        }
+                    }
+                });
+                let progress_reporter = ProgressReporter::new(move |event| {
+                    let _ = sender.send(event);
+                });
+                (progress_reporter, progress_forwarding)
+            })
+            .unzip();
+        Self {
+            progress_reporter,
+            cancellation_token: context.ct.clone(),
+            progress_forwarding,
+        }
+    }
+
+    /// 等待进度通知全部发出。工具调用的结果发出后客户端不再接收该请求的进度通知，
+    /// 需要在分析结束后、返回结果前调用
+    async fn finish(mut self) {
+        self.progress_reporter.take();
+        if let Some(progress_forwarding) = self.progress_forwarding.take() {
+            let _ = progress_forwarding.await;
+        }
+    }
+}
+
+const LATEST_DRAW_URI: &str = "ssq://draws/latest";
+const DRAW_URI_PREFIX: &str = "ssq://draws/";
+const DRAW_URI_TEMPLATE: &str = "ssq://draws/{code}";
+const OCCURRENCE_STATS_URI: &str = "ssq://stats/occurrence";
+const JSON_MIME_TYPE: &str = "application/json";
+
 #[derive(Debug, Clone)]
-pub struct SsqMcpService<'a> {
-    prize_record_business_obj: &'a [PrBusinessObj],
+pub struct SsqMcpService {
+    draw_store: Arc<DrawStore>,
+    runtime: Handle,
     tool_router: ToolRouter<Self>,
+    prompt_router: PromptRouter<Self>,
 }
 
 #[tool_router]
-impl<'a> SsqMcpService<'a>
-where
-    'a: 'static,
-{
-    pub fn new(prize_record_business_obj: &'a [PrBusinessObj]) -> Self {
+impl SsqMcpService {
+    /// 耗时的分析在 `runtime` 上执行，使处理 MCP 会话的线程能够及时收到取消通知
+    pub fn new(draw_store: Arc<DrawStore>, runtime: Handle) -> Self {
         Self {
-            prize_record_business_obj,
+            draw_store,
+            runtime,
             tool_router: Self::tool_router(),
+            prompt_router: Self::prompt_router(),
         }
     }
 
+    async fn calculate_ball_occurrence(&self) -> Result<Vec<BallOccurrenceView>, ErrorData> {
+        let records = self.draw_store.snapshot();
+        let mut processor_context = ProcessorContext::new(&records, records.len());
+        let processor_chain = ProcessorChain::new("calculate_ball_occurence_processor_chain");
+        let mut processor_chain = processor_chain.add_processor(Box::new(BallOccurrenceProcessor));
+        processor_chain
+            .execute(&mut processor_context)
+            .await
+            .map_err(|e| {
+                error!("计算双色球出现情况失败：{e:?}");
+                ErrorData::internal_error("计算双色球出现情况失败", None)
+            })?;
+        let ball_occurrence = processor_context
+            .get_attribute(&BALL_OCCURRENCE)
+            .ok_or(ErrorData::internal_error("无法找到双色球出现情况", None))?;
+        Ok(BallOccurrenceView::from_map(ball_occurrence))
+    }
+
+    /// 按照资源地址读取中奖数据或统计结果的 JSON
+    async fn read_resource_json(&self, uri: &str) -> Result<String, ErrorData> {
+        let records = self.draw_store.snapshot();
+        let json = match uri {
+            LATEST_DRAW_URI => {
+                let draw = latest_draw(&records)
+                    .map_err(|e| ErrorData::resource_not_found(e.to_string(), None))?;
+                serde_json::to_string_pretty(draw)
+            }
+            OCCURRENCE_STATS_URI => {
+                serde_json::to_string_pretty(&self.calculate_ball_occurrence().await?)
+            }
+            _ => {
+                let draw = uri
+                    .strip_prefix(DRAW_URI_PREFIX)
+                    .and_then(|code| records.iter().find(|record| record.code == code))
+                    .ok_or(ErrorData::resource_not_found(
+                        format!("没有找到资源：{uri}"),
+                        None,
+                    ))?;
+                serde_json::to_string_pretty(draw)
+            }
+        };
+        json.map_err(|e| ErrorData::internal_error(format!("序列化资源失败：{e}"), None))
+    }
+
+    /// 加载钱包并对已开奖的购买记录兑奖，修改后保存。
+    /// 钱包的读写会等待文件锁，放到阻塞线程中执行，避免占住异步运行时的工作线程
+    async fn update_wallet<T: Send + 'static>(
+        &self,
+        update: impl FnOnce(&mut Wallet) -> Result<T, Error> + Send + 'static,
+    ) -> Result<T, ErrorData> {
+        let path = self.draw_store.wallet_path().to_path_buf();
+        let records = self.draw_store.snapshot();
+        tokio::task::spawn_blocking(move || {
+            Wallet::update(&path, |wallet| {
+                wallet.settle(&records);
+                update(wallet)
+            })
+        })
+        .await
+        .map_err(|e| Error::Other(format!("钱包任务异常退出：{e}")))
+        .and_then(|result| result)
+        .map_err(to_error_data)
+    }
+
+    /// 加载钱包并在内存中对已开奖的购买记录兑奖，不修改钱包文件
+    async fn read_wallet(&self) -> Result<Wallet, ErrorData> {
+        let path = self.draw_store.wallet_path().to_path_buf();
+        let records = self.draw_store.snapshot();
+        tokio::task::spawn_blocking(move || {
+            let mut wallet = Wallet::load(&path)?;
+            wallet.settle(&records);
+            Ok(wallet)
+        })
+        .await
+        .map_err(|e| Error::Other(format!("钱包任务异常退出：{e}")))
+        .and_then(|result| result)
+        .map_err(to_error_data)
+    }
+
+    /// 在服务的运行时上执行分析，分析占用 CPU 期间会话线程仍然可以处理取消通知
+    async fn spawn_analysis<T: Send + 'static>(
+        &self,
+        analysis: impl Future<Output = Result<T, Error>> + Send + 'static,
+    ) -> Result<T, Error> {
+        self.runtime
+            .spawn(analysis)
+            .await
+            .map_err(|e| Error::Other(format!("分析任务异常退出：{e}")))?
+    }
+
+    /// 按照请求的配置与窗口执行分析链，返回指定属性的 JSON 表示
+    async fn run_chain(
+        &self,
+        request: RunChainRequest,
+        prediction: bool,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let snapshot = self.draw_store.snapshot();
+        let observer = ToolCallObserver::new(&context);
+        let progress_reporter = observer.progress_reporter.clone();
+        let cancellation_token = observer.cancellation_token.clone();
+        let attributes = self
+            .spawn_analysis(async move {
+                run_chain(
+                    &snapshot,
+                    request,
+                    prediction,
+                    progress_reporter,
+                    cancellation_token,
+                )
+                .await
+            })
+            .await;
+        observer.finish().await;
+        match attributes {
+            Ok(attributes) => Ok(CallToolResult::success(vec![Content::json(attributes)?])),
+            Err(Error::Interrupted {
+                reason,
+                partial_results,
+            }) => {
+                info!("分析没有完成：{reason}");
+                Ok(CallToolResult::error(vec![
+                    Content::text(reason.to_string()),
+                    Content::json(partial_results)?,
+                ]))
+            }
+            Err(e) => Err(to_error_data(e)),
+        }
+    }
+
+    #[tool(description = "从官网刷新往期中奖数据，返回新增的期号")]
+    pub async fn refresh_official_data(&self) -> Result<CallToolResult, ErrorData> {
+        let outcome = self.draw_store.refresh().await.map_err(|e| {
+            error!("刷新往期中奖数据失败：{e:?}");
+            ErrorData::internal_error(format!("刷新往期中奖数据失败：{e}"), None)
+        })?;
+        let call_tool_result = CallToolResult::success(vec![Content::json(outcome)?]);
+        return Ok(call_tool_result);
+    }
+
     #[tool(description = "取得所有官方中奖数据")]
     pub async fn get_all_official_data(&self) -> Result<CallToolResult, ErrorData> {
-        let call_tool_result =
-            CallToolResult::success(vec![Content::json(self.prize_record_business_obj)?]);
+        let records = self.draw_store.snapshot();
+        let call_tool_result = CallToolResult::success(vec![Content::json(&records)?]);
         return Ok(call_tool_result);
     }
 
@@ -61,8 +358,8 @@ where
         &self,
         param: Parameters<FilterByWeekRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        let prize_record_business_objs = self
-            .prize_record_business_obj
+        let records = self.draw_store.snapshot();
+        let prize_record_business_objs = records
             .iter()
             .filter(|reocrd| reocrd.day.contains(&param.0.day))
             .collect::<Vec<&PrBusinessObj>>();
@@ -76,8 +373,8 @@ where
         &self,
         param: Parameters<FilterBySeqRangeRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        let prize_record_business_objs = self
-            .prize_record_business_obj
+        let records = self.draw_store.snapshot();
+        let prize_record_business_objs = records
             .iter()
             .filter(|reocrd| reocrd.seq >= param.0.start && reocrd.seq <= param.0.end)
             .collect::<Vec<&PrBusinessObj>>();
@@ -91,8 +388,8 @@ where
         &self,
         param: Parameters<FilterByDateRangeRequest>,
     ) -> Result<CallToolResult, ErrorData> {
-        let prize_record_business_objs = self
-            .prize_record_business_obj
+        let records = self.draw_store.snapshot();
+        let prize_record_business_objs = records
             .iter()
             .filter(|reocrd| reocrd.date >= param.0.start && reocrd.date <= param.0.end)
             .collect::<Vec<&PrBusinessObj>>();
@@ -103,37 +400,303 @@ where
 
     #[tool(description = "统计双色球出现情况")]
     pub async fn caculate_ball_occurence(&self) -> Result<CallToolResult, ErrorData> {
-        let mut processor_context = ProcessorContext::new(
-            self.prize_record_business_obj,
-            self.prize_record_business_obj.len(),
-        );
-        let processor_chain = ProcessorChain::new("calculate_ball_occurence_processor_chain");
-        let mut processor_chain = processor_chain.add_processor(Box::new(BallOccurrenceProcessor));
-        processor_chain
-            .execute(&mut processor_context)
-            .await
-            .map_err(|e| {
-                error!("计算双色球出现情况失败：{e:?}");
-                ErrorData::internal_error("计算双色球出现情况失败", None)
-            })?;
-        let ball_occurrence = processor_context
-            .get_attribute(&BALL_OCCURRENCE)
-            .ok_or(ErrorData::internal_error("无法找到双色球出现情况", None))?;
+        let ball_occurrence = self.calculate_ball_occurrence().await?;
         let call_tool_result = CallToolResult::success(vec![Content::json(ball_occurrence)?]);
         return Ok(call_tool_result);
     }
+
+    #[tool(
+        description = "在最近若干期中奖数据上执行可配置的分析链，按照属性名称返回分析结果，不指定属性时返回除每期明细以外的所有结果。请求给出进度令牌时在每个执行器开始前发送进度通知"
+    )]
+    pub async fn run_analysis_chain(
+        &self,
+        param: Parameters<RunChainRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        self.run_chain(param.0, false, context).await
+    }
+
+    #[tool(
+        description = "在最近若干期中奖数据上执行可配置的预测链，默认返回最终结果、候选号码、缩水结果与投注组合。请求给出进度令牌时在每个执行器开始前发送进度通知"
+    )]
+    pub async fn predict(
+        &self,
+        param: Parameters<RunChainRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        self.run_chain(param.0, true, context).await
+    }
+
+    #[tool(
+        description = "逐期前推回测预测链，需要时与同样注数的随机号码比较。请求给出进度令牌时每回测完一期发送进度通知"
+    )]
+    pub async fn backtest(
+        &self,
+        param: Parameters<BacktestRequest>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let (config, args) = param.0.into_config_and_args().map_err(to_error_data)?;
+        let snapshot = self.draw_store.snapshot();
+        let observer = ToolCallObserver::new(&context);
+        let progress_reporter = observer.progress_reporter.clone();
+        let cancellation_token = observer.cancellation_token.clone();
+        let summary = self
+            .spawn_analysis(async move {
+                run_backtest(
+                    &snapshot,
+                    &config,
+                    &args,
+                    progress_reporter,
+                    cancellation_token,
+                )
+                .await
+            })
+            .await;
+        observer.finish().await;
+        let summary = summary.map_err(to_error_data)?;
+        Ok(CallToolResult::success(vec![Content::json(summary)?]))
+    }
+
+    #[tool(description = "检查彩票在最近一期、指定期号或所有往期的中奖情况")]
+    pub async fn check_ticket(
+        &self,
+        param: Parameters<CheckTicketRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let records = self.draw_store.snapshot();
+        let CheckTicketRequest {
+            tickets,
+            code,
+            all_draws,
+        } = param.0;
+        let tickets = tickets
+            .iter()
+            .map(|ticket| parse_ticket(ticket))
+            .collect::<Result<Vec<Ticket>, _>>()
+            .map_err(|e| ErrorData::invalid_params(e.to_string(), None))?;
+        let scope = match (&code, all_draws) {
+            (Some(code), _) => CheckScope::Code(code),
+            (None, true) => CheckScope::All,
+            (None, false) => CheckScope::Latest,
+        };
+        let reports = check_tickets(&records, &tickets, scope)
+            .map_err(|e| ErrorData::invalid_params(e.to_string(), None))?;
+        let call_tool_result = CallToolResult::success(vec![Content::json(reports)?]);
+        return Ok(call_tool_result);
+    }
+
+    #[tool(description = "在钱包中记录一张已购买的彩票，已开奖时立即兑奖")]
+    pub async fn add_wallet_purchase(
+        &self,
+        param: Parameters<AddWalletPurchaseRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let records = self.draw_store.snapshot();
+        let AddWalletPurchaseRequest {
+            ticket,
+            code,
+            owner,
+            strategy,
+            notes,
+        } = param.0;
+        let purchase = self
+            .update_wallet(move |wallet| {
+                let ticket = parse_ticket(&ticket)?;
+                let id = wallet.add(ticket, code, owner, strategy, notes).id;
+                wallet.settle(&records);
+                Ok(wallet.get(id).cloned())
+            })
+            .await?;
+        let call_tool_result = CallToolResult::success(vec![Content::json(purchase)?]);
+        return Ok(call_tool_result);
+    }
+
+    #[tool(description = "列出钱包中的购买记录及兑奖结果")]
+    pub async fn list_wallet_purchases(
+        &self,
+        param: Parameters<ListWalletPurchasesRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let ListWalletPurchasesRequest { owner, pending } = param.0;
+        let purchases = self
+            .read_wallet()
+            .await?
+            .purchases()
+            .iter()
+            .filter(|purchase| owner.as_ref().is_none_or(|owner| &purchase.owner == owner))
+            .filter(|purchase| !pending || purchase.settlement.is_none())
+            .cloned()
+            .collect::<Vec<Purchase>>();
+        let call_tool_result = CallToolResult::success(vec![Content::json(purchases)?]);
+        return Ok(call_tool_result);
+    }
+
+    #[tool(description = "按照购买人或策略统计钱包的投入、奖金与盈亏")]
+    pub async fn get_wallet_report(
+        &self,
+        param: Parameters<WalletReportRequest>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let report = self.read_wallet().await?.report(param.0.group_by);
+        let call_tool_result = CallToolResult::success(vec![Content::json(report)?]);
+        return Ok(call_tool_result);
+    }
+}
+
+#[prompt_router]
+impl SsqMcpService {
+    #[prompt(
+        name = "analyze_recent_draws",
+        description = "分析最近若干期的双色球中奖数据"
+    )]
+    pub async fn analyze_recent_draws(
+        &self,
+        param: Parameters<AnalyzeRecentDrawsPrompt>,
+    ) -> Result<GetPromptResult, ErrorData> {
+        let records = self.draw_store.snapshot();
+        let recent_size = param
+            .0
+            .recent_size
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|recent_size| *recent_size > 0)
+            .ok_or(ErrorData::invalid_params(
+                format!("中奖数据期数必须为正整数：{}", param.0.recent_size),
+                None,
+            ))?;
+        let draws = records
+            .iter()
+            .sorted_by_key(|record| record.date)
+            .rev()
+            .take(recent_size)
+            .map(format_draw)
+            .join("\n");
+        Ok(GetPromptResult {
+            description: Some(format!("分析最近 {recent_size} 期双色球中奖数据")),
+            messages: vec![PromptMessage::new_text(
+                PromptMessageRole::User,
+                format!(
+                    "以下是最近 {recent_size} 期双色球的开奖结果，从最近一期开始排列：\n{draws}\n\n\
+                     请分析这些开奖结果的号码分布、冷热号与遗漏情况。需要更详细的统计时，\
+                     可以调用 run_analysis_chain 工具并把 recent_size 设置为 {recent_size}。"
+                ),
+            )],
+        })
+    }
+
+    #[prompt(
+        name = "check_my_ticket",
+        description = "检查彩票在最近一期或指定期号的中奖情况"
+    )]
+    pub async fn check_my_ticket(
+        &self,
+        param: Parameters<CheckMyTicketPrompt>,
+    ) -> Result<GetPromptResult, ErrorData> {
+        let records = self.draw_store.snapshot();
+        let CheckMyTicketPrompt { tickets, code } = param.0;
+        let tickets = tickets
+            .split([';', '；', '\n'])
+            .map(str::trim)
+            .filter(|ticket| !ticket.is_empty())
+            .map(parse_ticket)
+            .collect::<Result<Vec<Ticket>, _>>()
+            .map_err(|e| ErrorData::invalid_params(e.to_string(), None))?;
+        if tickets.is_empty() {
+            return Err(ErrorData::invalid_params("需要给出至少一张彩票", None));
+        }
+        let scope = match &code {
+            Some(code) => CheckScope::Code(code),
+            None => CheckScope::Latest,
+        };
+        let reports = check_tickets(&records, &tickets, scope)
+            .map_err(|e| ErrorData::invalid_params(e.to_string(), None))?;
+        let reports = serde_json::to_string_pretty(&reports)
+            .map_err(|e| ErrorData::internal_error(format!("序列化兑奖结果失败：{e}"), None))?;
+        Ok(GetPromptResult {
+            description: Some("检查我的彩票中奖情况".to_string()),
+            messages: vec![PromptMessage::new_text(
+                PromptMessageRole::User,
+                format!(
+                    "我买了 {} 张双色球彩票，兑奖结果如下：\n{reports}\n\n\
+                     请说明每张彩票命中的号码、中奖奖级与奖金，以及总的投入与回报。",
+                    tickets.len()
+                ),
+            )],
+        })
+    }
 }
 
 #[tool_handler]
-impl<'a> ServerHandler for SsqMcpService<'a>
-where
-    'a: 'static,
-{
+#[prompt_handler]
+impl ServerHandler for SsqMcpService {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             instructions: Some("双色球数据分析服务".into()),
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_resources()
+                .enable_prompts()
+                .build(),
             ..Default::default()
         }
     }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, ErrorData> {
+        let resource = |uri: &str, name: &str, description: &str| {
+            RawResource {
+                description: Some(description.to_string()),
+                mime_type: Some(JSON_MIME_TYPE.to_string()),
+                ..RawResource::new(uri, name)
+            }
+            .no_annotation()
+        };
+        Ok(ListResourcesResult::with_all_items(vec![
+            resource(
+                LATEST_DRAW_URI,
+                "最近一期中奖数据",
+                "最近一期的开奖号码与奖级详情",
+            ),
+            resource(
+                OCCURRENCE_STATS_URI,
+                "双色球出现情况",
+                "所有往期中每个号码的出现次数与平均出现间隔",
+            ),
+        ]))
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, ErrorData> {
+        Ok(ListResourceTemplatesResult::with_all_items(vec![
+            RawResourceTemplate {
+                uri_template: DRAW_URI_TEMPLATE.to_string(),
+                name: "指定期号的中奖数据".to_string(),
+                title: None,
+                description: Some(
+                    "按照期号取得开奖号码与奖级详情，例如 ssq://draws/2024001".to_string(),
+                ),
+                mime_type: Some(JSON_MIME_TYPE.to_string()),
+            }
+            .no_annotation(),
+        ]))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, ErrorData> {
+        let text = self.read_resource_json(&request.uri).await?;
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: request.uri,
+                mime_type: Some(JSON_MIME_TYPE.to_string()),
+                text,
+                meta: None,
+            }],
+        })
+    }
 }
@@ -0,0 +1,101 @@
+use crate::error::Error;
+use crate::service::draw_store::DrawStore;
+use crate::service::rest;
+use crate::service::ssq_mcp_service::SsqMcpService;
+use actix_web::dev::Server;
+use actix_web::{App, HttpServer, web};
+use rmcp::ServiceExt;
+use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
+use rmcp_actix_web::transport::StreamableHttpService;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::runtime::Handle;
+use tracing::info;
+
+pub const DEFAULT_HTTP_BIND_ADDRESS: &str = "127.0.0.1:20080";
+pub const DEFAULT_HTTP_PATH: &str = "/ssq/mcp";
+
+/// 通过标准输入输出提供 MCP 服务，直到客户端关闭连接。标准输出只用于 MCP 消息，日志需要写到别处
+pub async fn serve_stdio(draw_store: Arc<DrawStore>) -> Result<(), Error> {
+    serve_io(draw_store, tokio::io::stdin(), tokio::io::stdout()).await
+}
+
+/// 在任意的读写流上提供 MCP 服务，直到对方关闭连接
+pub async fn serve_io<R, W>(draw_store: Arc<DrawStore>, reader: R, writer: W) -> Result<(), Error>
+where
+    R: AsyncRead + Send + Unpin + 'static,
+    W: AsyncWrite + Send + Unpin + 'static,
+{
+    info!("通过标准输入输出提供 MCP 服务...");
+    let running_service = SsqMcpService::new(draw_store, Handle::current())
+        .serve((reader, writer))
+        .await
+        .map_err(|e| Error::Other(format!("初始化 MCP 服务失败：{e}")))?;
+    let quit_reason = running_service
+        .waiting()
+        .await
+        .map_err(|e| Error::Other(format!("MCP 服务异常退出：{e}")))?;
+    info!("MCP 服务已停止：{quit_reason:?}");
+    Ok(())
+}
+
+/// 绑定地址并创建可流式传输的 HTTP MCP 服务与 REST 接口，返回待运行的服务与实际监听的地址
+pub fn bind_http(
+    draw_store: Arc<DrawStore>,
+    bind_address: &str,
+    path: &str,
+    api_path: &str,
+) -> Result<(Server, Vec<SocketAddr>), Error> {
+    if !path.starts_with('/') {
+        return Err(Error::InvalidArgument(format!(
+            "MCP 服务路径必须以 / 开头：{path}"
+        )));
+    }
+    if !api_path.starts_with('/') {
+        return Err(Error::InvalidArgument(format!(
+            "REST 接口路径必须以 / 开头：{api_path}"
+        )));
+    }
+    let rest_draw_store = draw_store.clone();
+    let runtime = Handle::try_current()
+        .map_err(|e| Error::Other(format!("需要在 tokio 运行时中启动 HTTP 服务：{e}")))?;
+    let service_runtime = runtime.clone();
+    let ssq_mcp_service = StreamableHttpService::builder()
+        .service_factory(Arc::new(move || {
+            Ok(SsqMcpService::new(
+                draw_store.clone(),
+                service_runtime.clone(),
+            ))
+        }))
+        .session_manager(Arc::new(LocalSessionManager::default()))
+        .stateful_mode(true)
+        .build();
+    let path = path.trim_end_matches('/').to_string();
+    let api_path = api_path.trim_end_matches('/').to_string();
+    let http_server = HttpServer::new(move || {
+        App::new()
+            .service(web::scope(&path).service(ssq_mcp_service.clone().scope()))
+            .service(rest::configure(
+                rest_draw_store.clone(),
+                runtime.clone(),
+                &api_path,
+            ))
+    })
+    .bind(bind_address)?;
+    let addresses = http_server.addrs();
+    info!("MCP 服务与 REST 接口监听在 {addresses:?}");
+    Ok((http_server.run(), addresses))
+}
+
+/// 提供可流式传输的 HTTP MCP 服务与 REST 接口，直到服务停止
+pub async fn serve_http(
+    draw_store: Arc<DrawStore>,
+    bind_address: &str,
+    path: &str,
+    api_path: &str,
+) -> Result<(), Error> {
+    let (http_server, _) = bind_http(draw_store, bind_address, path, api_path)?;
+    http_server.await?;
+    Ok(())
+}
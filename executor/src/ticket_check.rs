@@ -0,0 +1,96 @@
+use crate::error::Error;
+use rmcp::schemars;
+use serde::Serialize;
+use ssq_tool_domain::{PrBusinessObj, Ticket, TicketCheck};
+use std::path::Path;
+
+/// 兑奖的开奖范围
+#[derive(Debug, Clone, Copy)]
+pub enum CheckScope<'a> {
+    /// 最近一期
+    Latest,
+    /// 指定期号
+    Code(&'a str),
+    /// 所有往期
+    All,
+}
+
+#[derive(Debug, Clone, Serialize, schemars::JsonSchema)]
+#[serde(rename = "彩票兑奖报告")]
+pub struct TicketCheckReport {
+    #[serde(rename = "号码")]
+    pub ticket: String,
+    #[serde(rename = "彩票")]
+    pub detail: Ticket,
+    #[serde(rename = "注数")]
+    pub bet_count: u64,
+    #[serde(rename = "检查期数")]
+    pub checked_draws: usize,
+    #[serde(rename = "投注金额")]
+    pub total_cost: u64,
+    #[serde(rename = "总奖金")]
+    pub total_payout: u64,
+    /// 检查所有往期时只包含中奖的期数
+    #[serde(rename = "兑奖结果")]
+    pub checks: Vec<TicketCheck>,
+}
+
+/// 读取彩票文件，每行一张彩票，忽略空行与 `#` 开头的注释行
+pub fn read_tickets_file(path: &Path) -> Result<Vec<Ticket>, Error> {
+    std::fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_ticket)
+        .collect()
+}
+
+pub fn parse_ticket(ticket: &str) -> Result<Ticket, Error> {
+    ticket
+        .parse::<Ticket>()
+        .map_err(|e| Error::InvalidArgument(e.to_string()))
+}
+
+pub fn check_tickets(
+    records: &[PrBusinessObj],
+    tickets: &[Ticket],
+    scope: CheckScope,
+) -> Result<Vec<TicketCheckReport>, Error> {
+    let draws = match scope {
+        CheckScope::Latest => records
+            .iter()
+            .max_by_key(|record| record.date)
+            .into_iter()
+            .collect::<Vec<&PrBusinessObj>>(),
+        CheckScope::Code(code) => vec![records.iter().find(|record| record.code == code).ok_or(
+            Error::InvalidArgument(format!("没有找到第 {code} 期的中奖数据")),
+        )?],
+        CheckScope::All => {
+            let mut draws = records.iter().collect::<Vec<&PrBusinessObj>>();
+            draws.sort_by_key(|record| std::cmp::Reverse(record.date));
+            draws
+        }
+    };
+    if draws.is_empty() {
+        return Err(Error::Other("没有往期中奖数据".to_string()));
+    }
+    Ok(tickets
+        .iter()
+        .map(|ticket| {
+            let checks = draws
+                .iter()
+                .map(|draw| ticket.check(draw))
+                .filter(|check| !matches!(scope, CheckScope::All) || check.payout > 0)
+                .collect::<Vec<TicketCheck>>();
+            TicketCheckReport {
+                ticket: ticket.to_string(),
+                detail: ticket.clone(),
+                bet_count: ticket.bet_count(),
+                checked_draws: draws.len(),
+                total_cost: ticket.cost() * draws.len() as u64,
+                total_payout: checks.iter().map(|check| check.payout).sum(),
+                checks,
+            }
+        })
+        .collect())
+}
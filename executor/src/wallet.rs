@@ -0,0 +1,242 @@
+use crate::error::Error;
+use chrono::{Local, NaiveDate};
+use clap::ValueEnum;
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::{PrBusinessObj, Ticket, TicketCheck};
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use tracing::info;
+
+pub const DEFAULT_WALLET_FILE: &str = "wallet.json";
+
+/// 同一进程内修改钱包文件时需要持有该锁，不同进程之间由钱包旁边的锁文件上的文件锁互斥
+static WALLET_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename = "购买记录")]
+pub struct Purchase {
+    #[serde(rename = "编号")]
+    pub id: u64,
+    #[serde(rename = "彩票")]
+    pub ticket: Ticket,
+    #[serde(rename = "开奖期号")]
+    pub code: String,
+    #[serde(rename = "金额")]
+    pub cost: u64,
+    #[serde(rename = "购买人")]
+    pub owner: String,
+    #[serde(rename = "策略", default)]
+    pub strategy: Option<String>,
+    #[serde(rename = "备注", default)]
+    pub notes: Option<String>,
+    #[serde(rename = "购买日期")]
+    pub purchased_at: NaiveDate,
+    /// 开奖后的兑奖结果，未开奖时为空
+    #[serde(rename = "兑奖结果", default)]
+    pub settlement: Option<TicketCheck>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WalletGroup {
+    /// 按照购买人分组
+    Owner,
+    /// 按照策略分组
+    Strategy,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename = "盈亏统计")]
+pub struct ProfitAndLoss {
+    #[serde(rename = "购买次数")]
+    pub purchase_count: usize,
+    #[serde(rename = "已开奖次数")]
+    pub settled_count: usize,
+    #[serde(rename = "总投入")]
+    pub total_cost: u64,
+    #[serde(rename = "待开奖投入")]
+    pub pending_cost: u64,
+    #[serde(rename = "总奖金")]
+    pub total_payout: u64,
+    /// 已开奖部分的奖金减去投入
+    #[serde(rename = "盈亏")]
+    pub profit: i64,
+}
+
+impl ProfitAndLoss {
+    fn add(&mut self, purchase: &Purchase) {
+        self.purchase_count += 1;
+        self.total_cost += purchase.cost;
+        match &purchase.settlement {
+            Some(settlement) => {
+                self.settled_count += 1;
+                self.total_payout += settlement.payout;
+                self.profit += settlement.payout as i64 - purchase.cost as i64;
+            }
+            None => self.pending_cost += purchase.cost,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename = "钱包报告")]
+pub struct WalletReport {
+    #[serde(rename = "合计")]
+    pub total: ProfitAndLoss,
+    #[serde(rename = "分组")]
+    pub groups: BTreeMap<String, ProfitAndLoss>,
+}
+
+/// 保存在本地 JSON 文件中的购彩记录
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Wallet {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(rename = "购买记录", default)]
+    purchases: Vec<Purchase>,
+}
+
+impl Wallet {
+    /// 钱包文件不存在时返回空钱包
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut wallet = if path.exists() {
+            serde_json::from_reader::<File, Wallet>(File::open(path)?)?
+        } else {
+            Wallet::default()
+        };
+        wallet.path = path.to_path_buf();
+        Ok(wallet)
+    }
+
+    /// 在钱包锁与文件锁内加载钱包、执行 `update` 并保存，`update` 失败时不保存。
+    /// 同时运行的命令行与 MCP 服务不会互相覆盖对方的修改
+    pub fn update<T>(
+        path: &Path,
+        update: impl FnOnce(&mut Wallet) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let _guard = WALLET_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let lock_file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(sibling_path(path, "lock"))?;
+        lock_file.lock()?;
+        let mut wallet = Wallet::load(path)?;
+        let result = update(&mut wallet)?;
+        wallet.save()?;
+        Ok(result)
+    }
+
+    /// 钱包文件存在时对已开奖的购买记录兑奖并保存，返回本次兑奖的记录数
+    pub fn settle_file(path: &Path, records: &[PrBusinessObj]) -> Result<usize, Error> {
+        if !path.exists() {
+            return Ok(0);
+        }
+        Wallet::update(path, |wallet| Ok(wallet.settle(records)))
+    }
+
+    /// 先写入临时文件再重命名为钱包文件，写入中途失败不会损坏原有的钱包文件
+    fn save(&self) -> Result<(), Error> {
+        let temp_path = sibling_path(&self.path, "tmp");
+        let mut file = File::create(&temp_path)?;
+        serde_json::to_writer_pretty(&mut file, self)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+
+    pub fn purchases(&self) -> &[Purchase] {
+        &self.purchases
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Purchase> {
+        self.purchases.iter().find(|purchase| purchase.id == id)
+    }
+
+    pub fn add(
+        &mut self,
+        ticket: Ticket,
+        code: String,
+        owner: String,
+        strategy: Option<String>,
+        notes: Option<String>,
+    ) -> &Purchase {
+        let id = self
+            .purchases
+            .iter()
+            .map(|purchase| purchase.id)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        self.purchases.push(Purchase {
+            id,
+            cost: ticket.cost(),
+            ticket,
+            code,
+            owner,
+            strategy,
+            notes,
+            purchased_at: Local::now().date_naive(),
+            settlement: None,
+        });
+        &self.purchases[self.purchases.len() - 1]
+    }
+
+    pub fn remove(&mut self, id: u64) -> Result<Purchase, Error> {
+        let index = self
+            .purchases
+            .iter()
+            .position(|purchase| purchase.id == id)
+            .ok_or(Error::InvalidArgument(format!(
+                "没有找到编号为 {id} 的购买记录"
+            )))?;
+        Ok(self.purchases.remove(index))
+    }
+
+    /// 对已经开奖但还没有兑奖的购买记录兑奖，返回本次兑奖的记录数
+    pub fn settle(&mut self, records: &[PrBusinessObj]) -> usize {
+        let draws_by_code = records
+            .iter()
+            .map(|record| (record.code.as_str(), record))
+            .collect::<HashMap<&str, &PrBusinessObj>>();
+        let mut settled_count = 0;
+        self.purchases
+            .iter_mut()
+            .filter(|purchase| purchase.settlement.is_none())
+            .for_each(|purchase| {
+                if let Some(draw) = draws_by_code.get(purchase.code.as_str()) {
+                    purchase.settlement = Some(purchase.ticket.check(draw));
+                    settled_count += 1;
+                }
+            });
+        info!("钱包中共有 {settled_count} 条购买记录完成兑奖");
+        settled_count
+    }
+
+    /// 按照购买人或策略分组统计盈亏，没有策略的记录归入 `未指定`
+    pub fn report(&self, group: WalletGroup) -> WalletReport {
+        let mut total = ProfitAndLoss::default();
+        let mut groups = BTreeMap::<String, ProfitAndLoss>::new();
+        self.purchases.iter().for_each(|purchase| {
+            let key = match group {
+                WalletGroup::Owner => purchase.owner.clone(),
+                WalletGroup::Strategy => purchase.strategy.clone().unwrap_or("未指定".to_string()),
+            };
+            total.add(purchase);
+            groups.entry(key).or_default().add(purchase);
+        });
+        WalletReport { total, groups }
+    }
+}
+
+/// 钱包文件同一目录下的辅助文件，文件名为钱包文件名加上 `extension` 后缀
+fn sibling_path(path: &Path, extension: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
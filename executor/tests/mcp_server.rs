@@ -0,0 +1,355 @@
+use chrono::NaiveDate;
+use executor::service::draw_store::DrawStore;
+use executor::service::rest::DEFAULT_API_PATH;
+use executor::service::transport::{bind_http, serve_io};
+use rmcp::ServiceExt;
+use rmcp::model::{
+    CallToolRequestParam, GetPromptRequestParam, PromptMessageContent, ReadResourceRequestParam,
+    ResourceContents,
+};
+use rmcp::service::{RoleClient, RunningService};
+use rmcp::transport::StreamableHttpClientTransport;
+use serde_json::{Value, json};
+use ssq_tool_domain::{BlueBall, PrBusinessObj, PrizeGrade, PrizeGradeDetail, RedBall};
+use std::sync::Arc;
+
+fn draw(
+    seq: usize,
+    code: &str,
+    date: (i32, u32, u32),
+    red_balls: [usize; 6],
+    blue_ball: usize,
+) -> PrBusinessObj {
+    PrBusinessObj {
+        code: code.to_string(),
+        seq,
+        date: NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap(),
+        day: "星期日".to_string(),
+        blue_ball: BlueBall::try_from(blue_ball).unwrap(),
+        red_balls: red_balls.map(|ball| RedBall::try_from(ball).unwrap()),
+        total_tickets: 100_000_000,
+        total_prized_tickets: 5_000_000,
+        prize_grades: vec![PrizeGradeDetail {
+            grade: PrizeGrade::First,
+            winning_tickets: 2,
+            money: 5_000_000,
+        }],
+        pool_money: 1_000_000_000,
+    }
+}
+
+/// 按照从最近到最早排列的三期中奖数据
+fn records() -> Vec<PrBusinessObj> {
+    vec![
+        draw(0, "2025003", (2025, 1, 5), [3, 8, 15, 21, 27, 33], 9),
+        draw(1, "2025002", (2025, 1, 2), [1, 6, 12, 18, 24, 30], 4),
+        draw(2, "2025001", (2024, 12, 31), [2, 9, 14, 20, 26, 31], 12),
+    ]
+}
+
+fn draw_store() -> Arc<DrawStore> {
+    Arc::new(DrawStore::new(records(), None))
+}
+
+fn tool_text(result: &rmcp::model::CallToolResult) -> Value {
+    let text = result.content[0]
+        .as_text()
+        .expect("工具结果应为文本")
+        .text
+        .as_str();
+    serde_json::from_str(text).unwrap()
+}
+
+async fn assert_service_works(client: &RunningService<RoleClient, ()>) {
+    let tools = client.list_all_tools().await.unwrap();
+    for name in [
+        "check_ticket",
+        "run_analysis_chain",
+        "predict",
+        "get_all_official_data",
+        "refresh_official_data",
+    ] {
+        assert!(
+            tools.iter().any(|tool| tool.name == name),
+            "缺少工具 {name}"
+        );
+    }
+
+    let official_data = client
+        .call_tool(CallToolRequestParam {
+            name: "get_all_official_data".into(),
+            arguments: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(tool_text(&official_data).as_array().unwrap().len(), 3);
+
+    let check = client
+        .call_tool(CallToolRequestParam {
+            name: "check_ticket".into(),
+            arguments: json!({"tickets": ["03,08,15,21,27,33+09"]})
+                .as_object()
+                .cloned(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(tool_text(&check)[0]["总奖金"], 5_000_000);
+
+    let resources = client.list_all_resources().await.unwrap();
+    assert!(
+        resources
+            .iter()
+            .any(|resource| resource.uri == "ssq://draws/latest")
+    );
+    let latest = client
+        .read_resource(ReadResourceRequestParam {
+            uri: "ssq://draws/latest".to_string(),
+        })
+        .await
+        .unwrap();
+    let ResourceContents::TextResourceContents { text, .. } = &latest.contents[0] else {
+        panic!("资源内容应为文本");
+    };
+    assert_eq!(
+        serde_json::from_str::<Value>(text).unwrap()["中奖期号"],
+        "2025003"
+    );
+    let missing = client
+        .read_resource(ReadResourceRequestParam {
+            uri: "ssq://draws/1999001".to_string(),
+        })
+        .await;
+    assert!(missing.is_err());
+
+    let prompt = client
+        .get_prompt(GetPromptRequestParam {
+            name: "analyze_recent_draws".to_string(),
+            arguments: json!({"recent_size": "2"}).as_object().cloned(),
+        })
+        .await
+        .unwrap();
+    let PromptMessageContent::Text { text } = &prompt.messages[0].content else {
+        panic!("提示内容应为文本");
+    };
+    assert!(text.contains("2025003") && text.contains("2025002") && !text.contains("2025001"));
+}
+
+async fn call_tool(client: &RunningService<RoleClient, ()>, name: &str, arguments: Value) -> Value {
+    let result = client
+        .call_tool(CallToolRequestParam {
+            name: name.to_string().into(),
+            arguments: arguments.as_object().cloned(),
+        })
+        .await
+        .unwrap();
+    tool_text(&result)
+}
+
+#[tokio::test]
+async fn wallet_tools_use_configured_wallet_and_only_save_changes() {
+    let wallet_path = std::env::temp_dir().join(format!("ssq-wallet-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&wallet_path);
+    let draw_store =
+        Arc::new(DrawStore::new(records(), None).with_wallet_path(wallet_path.clone()));
+    let (client_stream, server_stream) = tokio::io::duplex(64 * 1024);
+    let (server_reader, server_writer) = tokio::io::split(server_stream);
+    let server = tokio::spawn(serve_io(draw_store, server_reader, server_writer));
+    let client = ().serve(client_stream).await.unwrap();
+
+    let purchases = call_tool(&client, "list_wallet_purchases", json!({})).await;
+    assert!(purchases.as_array().unwrap().is_empty());
+    assert!(!wallet_path.exists(), "只读的钱包工具不应写入钱包文件");
+
+    let purchase = call_tool(
+        &client,
+        "add_wallet_purchase",
+        json!({"ticket": "03,08,15,21,27,33+09", "code": "2025003", "owner": "张三"}),
+    )
+    .await;
+    assert_eq!(purchase["兑奖结果"]["奖金"], 5_000_000);
+    let saved = std::fs::read_to_string(&wallet_path).unwrap();
+
+    let report = call_tool(&client, "get_wallet_report", json!({"group_by": "owner"})).await;
+    assert_eq!(report["合计"]["总奖金"], 5_000_000);
+    assert_eq!(std::fs::read_to_string(&wallet_path).unwrap(), saved);
+
+    client.cancel().await.unwrap();
+    server.await.unwrap().unwrap();
+    let _ = std::fs::remove_file(&wallet_path);
+    let _ = std::fs::remove_file(wallet_path.with_extension("json.lock"));
+}
+
+#[tokio::test]
+async fn stdio_transport_serves_mcp_requests() {
+    let (client_stream, server_stream) = tokio::io::duplex(64 * 1024);
+    let (server_reader, server_writer) = tokio::io::split(server_stream);
+    let server = tokio::spawn(serve_io(draw_store(), server_reader, server_writer));
+
+    let client = ().serve(client_stream).await.unwrap();
+    assert_service_works(&client).await;
+    client.cancel().await.unwrap();
+
+    server.await.unwrap().unwrap();
+}
+
+async fn assert_rest_api_works(base: &str) {
+    let client = reqwest::Client::new();
+
+    let latest: Value = client
+        .get(format!("{base}/draws/latest"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(latest["中奖期号"], "2025003");
+
+    let draws: Value = client
+        .get(format!("{base}/draws?start=2025-01-01&end=2025-01-04"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(draws.as_array().unwrap().len(), 1);
+    assert_eq!(draws[0]["中奖期号"], "2025002");
+
+    let missing = client
+        .get(format!("{base}/draws/1999001"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+
+    let check = client
+        .post(format!("{base}/tickets/check"))
+        .json(&json!({"tickets": ["03,08,15,21,27,33+09"]}))
+        .send()
+        .await
+        .unwrap();
+    assert!(check.status().is_success());
+    assert_eq!(check.json::<Value>().await.unwrap()[0]["总奖金"], 5_000_000);
+
+    let invalid = client
+        .post(format!("{base}/tickets/check"))
+        .json(&json!({"tickets": ["not a ticket"]}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(invalid.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let output = std::env::temp_dir().join(format!("ssq-normalize-{}.txt", std::process::id()));
+    for path in ["analysis", "predictions", "backtests"] {
+        let writes_file = client
+            .post(format!("{base}/{path}"))
+            .json(&json!({
+                "config": {"processors": [{"type": "generate_normalize_data", "path": output}]},
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(writes_file.status(), reqwest::StatusCode::BAD_REQUEST);
+        let error: Value = writes_file.json().await.unwrap();
+        assert!(error["错误信息"].as_str().unwrap().contains("写入文件"));
+    }
+    assert!(!output.exists(), "服务请求不应写入本地文件");
+
+    let unknown_processor = client
+        .post(format!("{base}/analysis"))
+        .json(&json!({"config": {"processors": [{"type": "unknown"}]}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(unknown_processor.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let events = client
+        .post(format!("{base}/analysis/events"))
+        .json(&json!({
+            "config": {"processors": [{"type": "ball_occurrence"}]},
+            "attributes": ["BALL_OCCURRENCE"],
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        events.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+    let events = events.text().await.unwrap();
+    let progress_at = events.find("event: progress").expect("缺少进度事件");
+    let result_at = events.find("event: result").expect("缺少结果事件");
+    assert!(progress_at < result_at && events.contains("BALL_OCCURRENCE"));
+
+    let timeout = client
+        .post(format!("{base}/analysis"))
+        .json(&json!({
+            "config": {"processors": [{"type": "ball_occurrence"}], "chain_timeout_secs": 0},
+            "attributes": ["BALL_OCCURRENCE"],
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(timeout.status(), reqwest::StatusCode::GATEWAY_TIMEOUT);
+    let timeout: Value = timeout.json().await.unwrap();
+    assert!(timeout["部分结果"].is_object());
+
+    let openapi: Value = client
+        .get(format!("{base}/openapi.json"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(openapi["paths"]["/custom/api/backtests"]["post"].is_object());
+    assert!(openapi["components"]["schemas"]["BacktestRequest"].is_object());
+    let processor_config = &openapi["components"]["schemas"]["ProcessorConfig"];
+    assert!(processor_config.to_string().contains("ball_occurrence"));
+    assert!(openapi["paths"]["/custom/api/backtests/events"]["post"].is_object());
+    let analysis = &openapi["paths"]["/custom/api/analysis"]["post"]["responses"]["200"];
+    let schema = &analysis["content"]["application/json"]["schema"];
+    assert!(schema["properties"]["BALL_OCCURRENCE"].is_object());
+    assert!(schema["properties"]["PORTFOLIO"].is_object());
+
+    let analysis: Value = client
+        .post(format!("{base}/analysis"))
+        .json(&json!({
+            "config": {"processors": [{"type": "ball_occurrence"}, {"type": "hot_cold_ball"}]},
+            "attributes": ["BALL_OCCURRENCE", "BALL_TEMPERATURE"],
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(analysis["BALL_OCCURRENCE"].is_array());
+    assert_eq!(
+        analysis["BALL_TEMPERATURE"][0]["出现频率"][0]["双色球"]["蓝球"],
+        "1"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn http_transport_serves_on_configured_address_and_path() {
+    let (http_server, addresses) =
+        bind_http(draw_store(), "127.0.0.1:0", "/custom/mcp/", "/custom/api/").unwrap();
+    let server_handle = http_server.handle();
+    let server = tokio::spawn(http_server);
+
+    let uri = format!("http://{}/custom/mcp", addresses[0]);
+    let client = ().serve(StreamableHttpClientTransport::from_uri(uri)).await.unwrap();
+    assert_service_works(&client).await;
+    client.cancel().await.unwrap();
+    assert_rest_api_works(&format!("http://{}/custom/api", addresses[0])).await;
+
+    server_handle.stop(true).await;
+    server.await.unwrap().unwrap();
+}
+
+#[test]
+fn http_transport_rejects_relative_path() {
+    assert!(bind_http(draw_store(), "127.0.0.1:0", "ssq/mcp", DEFAULT_API_PATH).is_err());
+}
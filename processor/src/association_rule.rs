@@ -0,0 +1,210 @@
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::{
+    BALL_ASSOCIATION_RULES, BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP, Processor,
+    RED_BALL_AND_RED_BALL_RELATIONSHIP_FP,
+};
+use fp_growth::algorithm::FPResult;
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::{Ball, RedBall};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use tracing::debug;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "关联规则")]
+pub struct AssociationRule {
+    #[serde(rename = "前项")]
+    antecedent: Vec<Ball>,
+    #[serde(rename = "后项")]
+    consequent: Vec<Ball>,
+    #[serde(rename = "支持度")]
+    support: f64,
+    #[serde(rename = "置信度")]
+    confidence: f64,
+    #[serde(rename = "提升度")]
+    lift: f64,
+    #[serde(rename = "确信度")]
+    conviction: Option<f64>,
+}
+
+impl AssociationRule {
+    pub fn antecedent(&self) -> &[Ball] {
+        &self.antecedent
+    }
+
+    pub fn consequent(&self) -> &[Ball] {
+        &self.consequent
+    }
+
+    /// 前项与后项同时出现的期数占全部期数的比例
+    pub fn support(&self) -> f64 {
+        self.support
+    }
+
+    /// 前项出现时后项同时出现的条件概率
+    pub fn confidence(&self) -> f64 {
+        self.confidence
+    }
+
+    /// 置信度与后项自身出现概率的比值，大于 1 表示正相关
+    pub fn lift(&self) -> f64 {
+        self.lift
+    }
+
+    /// 后项不出现概率与规则出错概率的比值，置信度为 1 时规则从不出错，确信度无上界，为 `None`
+    pub fn conviction(&self) -> Option<f64> {
+        self.conviction
+    }
+
+    /// 后项中的红球
+    pub fn consequent_red_balls(&self) -> Vec<RedBall> {
+        self.consequent
+            .iter()
+            .filter_map(|ball| match ball {
+                Ball::Red(red_ball) => Some(*red_ball),
+                Ball::Blue(_) => None,
+            })
+            .collect()
+    }
+}
+
+fn red_balls_mask(red_balls: &[RedBall]) -> u64 {
+    red_balls
+        .iter()
+        .fold(0u64, |mask, ball| mask | (1u64 << (*ball as usize)))
+}
+
+/// 基于 FP-growth 的频繁项集推导关联规则（X → Y），并计算支持度、置信度、提升度与确信度
+pub struct AssociationRuleProcessor {
+    minimum_lift: f64,
+    minimum_confidence: f64,
+}
+
+impl AssociationRuleProcessor {
+    pub fn new(minimum_lift: f64, minimum_confidence: f64) -> Self {
+        Self {
+            minimum_lift,
+            minimum_confidence,
+        }
+    }
+
+    /// `antecedent_count` 为前项出现的期数，`fp_result` 为前项出现时其余红球的频繁项集
+    fn derive_rules(
+        &self,
+        antecedent: Ball,
+        antecedent_count: usize,
+        fp_result: &FPResult<RedBall>,
+        draw_red_ball_masks: &[u64],
+    ) -> Vec<AssociationRule> {
+        let draw_count = draw_red_ball_masks.len() as f64;
+        fp_result
+            .frequent_patterns()
+            .into_iter()
+            .filter_map(|(consequent, joint_count)| {
+                let consequent_mask = red_balls_mask(&consequent);
+                let consequent_count = draw_red_ball_masks
+                    .iter()
+                    .filter(|mask| **mask & consequent_mask == consequent_mask)
+                    .count();
+                if antecedent_count == 0 || consequent_count == 0 {
+                    return None;
+                }
+                let consequent_probability = consequent_count as f64 / draw_count;
+                let confidence = joint_count as f64 / antecedent_count as f64;
+                let lift = confidence / consequent_probability;
+                let conviction =
+                    (confidence < 1.0).then(|| (1.0 - consequent_probability) / (1.0 - confidence));
+                Some(AssociationRule {
+                    antecedent: vec![antecedent],
+                    consequent: consequent.into_iter().sorted().map(Ball::Red).collect(),
+                    support: joint_count as f64 / draw_count,
+                    confidence,
+                    lift,
+                    conviction,
+                })
+            })
+            .filter(|rule| rule.lift >= self.minimum_lift)
+            .filter(|rule| rule.confidence >= self.minimum_confidence)
+            .collect()
+    }
+}
+
+impl Default for AssociationRuleProcessor {
+    fn default() -> Self {
+        Self::new(1.0, 0.0)
+    }
+}
+
+#[async_trait::async_trait]
+impl Processor for AssociationRuleProcessor {
+    fn name(&self) -> &str {
+        "AssociationRuleProcessor"
+    }
+
+    async fn execute(&mut self, context: &mut ProcessorContext) -> Result<(), Error> {
+        let blue_ball_and_red_ball_fp = context
+            .get_attribute(&BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP)
+            .ok_or(Error::ContextAttrNotExist(
+                BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP.to_string(),
+            ))?;
+        let red_ball_and_red_ball_fp = context
+            .get_attribute(&RED_BALL_AND_RED_BALL_RELATIONSHIP_FP)
+            .ok_or(Error::ContextAttrNotExist(
+                RED_BALL_AND_RED_BALL_RELATIONSHIP_FP.to_string(),
+            ))?;
+        let records = context.get_prize_records();
+        let draw_red_ball_masks = records
+            .iter()
+            .map(|record| red_balls_mask(&record.red_balls))
+            .collect::<Vec<u64>>();
+        let mut antecedent_counts = HashMap::<Ball, usize>::new();
+        records.iter().for_each(|record| {
+            *antecedent_counts
+                .entry(Ball::Blue(record.blue_ball))
+                .or_default() += 1;
+            record.red_balls.iter().for_each(|red_ball| {
+                *antecedent_counts.entry(Ball::Red(*red_ball)).or_default() += 1;
+            });
+        });
+
+        let antecedent_fp_results = blue_ball_and_red_ball_fp
+            .iter()
+            .map(|(blue_ball, fp_result)| (Ball::Blue(*blue_ball), fp_result))
+            .chain(
+                red_ball_and_red_ball_fp
+                    .iter()
+                    .map(|(red_ball, fp_result)| (Ball::Red(*red_ball), fp_result)),
+            );
+        let mut rules = Vec::<AssociationRule>::new();
+        for (antecedent, fp_result) in antecedent_fp_results {
+            context.check_cancelled(self.name())?;
+            let antecedent_count = antecedent_counts.get(&antecedent).copied().unwrap_or(0);
+            rules.extend(self.derive_rules(
+                antecedent,
+                antecedent_count,
+                fp_result,
+                &draw_red_ball_masks,
+            ));
+        }
+        let rules = rules
+            .into_iter()
+            .sorted_by(|a, b| {
+                b.lift
+                    .partial_cmp(&a.lift)
+                    .unwrap_or(Ordering::Equal)
+                    .then_with(|| a.antecedent.cmp(&b.antecedent))
+                    .then_with(|| a.consequent.cmp(&b.consequent))
+            })
+            .collect::<Vec<AssociationRule>>();
+        debug!(
+            "共推导出 {} 条提升度不低于 {} 的关联规则",
+            rules.len(),
+            self.minimum_lift
+        );
+        context.set_attribute(&BALL_ASSOCIATION_RULES, rules);
+        Ok(())
+    }
+}
@@ -0,0 +1,306 @@
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::progress::{ProgressEvent, ProgressReporter};
+use crate::{FINAL_PROCESSOR_CHAIN_RESULTS, FinalProcessorChainResult, ProcessorChain};
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::{PrBusinessObj, PrizeGrade};
+use std::collections::BTreeMap;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info};
+
+/// 每注彩票的价格（元）
+pub const TICKET_PRICE: u64 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "单期回测结果")]
+pub struct BacktestDrawResult {
+    #[serde(rename = "中奖期号")]
+    code: String,
+    #[serde(rename = "历史期数")]
+    history_size: usize,
+    #[serde(rename = "预测注数")]
+    ticket_count: usize,
+    #[serde(rename = "中奖奖级")]
+    prize_grades: Vec<PrizeGrade>,
+    #[serde(rename = "投入金额")]
+    cost: u64,
+    #[serde(rename = "中奖金额")]
+    prize_return: u64,
+}
+
+impl BacktestDrawResult {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn history_size(&self) -> usize {
+        self.history_size
+    }
+
+    pub fn ticket_count(&self) -> usize {
+        self.ticket_count
+    }
+
+    /// 本期所有中奖注的奖级
+    pub fn prize_grades(&self) -> &[PrizeGrade] {
+        &self.prize_grades
+    }
+
+    pub fn cost(&self) -> u64 {
+        self.cost
+    }
+
+    pub fn prize_return(&self) -> u64 {
+        self.prize_return
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "回测报告")]
+pub struct BacktestReport {
+    #[serde(rename = "回测期数")]
+    evaluated_draws: usize,
+    #[serde(rename = "预测总注数")]
+    ticket_count: usize,
+    #[serde(rename = "各奖级中奖注数")]
+    grade_hits: BTreeMap<PrizeGrade, usize>,
+    #[serde(rename = "总投入")]
+    total_cost: u64,
+    #[serde(rename = "总奖金")]
+    total_return: u64,
+    #[serde(rename = "投资回报率")]
+    roi: f64,
+    #[serde(rename = "每期结果")]
+    draw_results: Vec<BacktestDrawResult>,
+}
+
+impl BacktestReport {
+    fn new(draw_results: Vec<BacktestDrawResult>) -> Self {
+        let mut grade_hits = BTreeMap::<PrizeGrade, usize>::new();
+        draw_results
+            .iter()
+            .flat_map(|result| &result.prize_grades)
+            .for_each(|grade| *grade_hits.entry(*grade).or_default() += 1);
+        let total_cost = draw_results.iter().map(|result| result.cost).sum::<u64>();
+        let total_return = draw_results
+            .iter()
+            .map(|result| result.prize_return)
+            .sum::<u64>();
+        let roi = if total_cost == 0 {
+            0.0
+        } else {
+            (total_return as f64 - total_cost as f64) / total_cost as f64
+        };
+        Self {
+            evaluated_draws: draw_results.len(),
+            ticket_count: draw_results.iter().map(|result| result.ticket_count).sum(),
+            grade_hits,
+            total_cost,
+            total_return,
+            roi,
+            draw_results,
+        }
+    }
+
+    pub fn evaluated_draws(&self) -> usize {
+        self.evaluated_draws
+    }
+
+    pub fn ticket_count(&self) -> usize {
+        self.ticket_count
+    }
+
+    /// 每个奖级的中奖注数
+    pub fn grade_hits(&self) -> &BTreeMap<PrizeGrade, usize> {
+        &self.grade_hits
+    }
+
+    pub fn total_cost(&self) -> u64 {
+        self.total_cost
+    }
+
+    pub fn total_return(&self) -> u64 {
+        self.total_return
+    }
+
+    /// (总奖金 - 总投入) / 总投入
+    pub fn roi(&self) -> f64 {
+        self.roi
+    }
+
+    /// 按照从早到晚排列的每期回测结果
+    pub fn draw_results(&self) -> &[BacktestDrawResult] {
+        &self.draw_results
+    }
+}
+
+/// 计算一组预测号码在给定开奖期的中奖奖级与奖金，每个预测结果按一注单式计算
+pub fn score_tickets(
+    draw: &PrBusinessObj,
+    tickets: &[FinalProcessorChainResult],
+) -> (Vec<PrizeGrade>, u64) {
+    let prize_grades = tickets
+        .iter()
+        .filter_map(|ticket| draw.check(ticket.red_ball(), *ticket.blue_ball()))
+        .collect::<Vec<PrizeGrade>>();
+    let prize_return = prize_grades
+        .iter()
+        .map(|grade| draw.prize_money(*grade))
+        .sum();
+    (prize_grades, prize_return)
+}
+
+/// 逐期前推的回测：对每一期只使用它之前的中奖记录构建执行器上下文，
+/// 执行分析链后用该期的开奖结果为预测号码计奖
+pub struct Backtest {
+    chain_factory: Box<dyn Fn() -> ProcessorChain + Send + Sync>,
+    expect_result_size: usize,
+    minimum_history_size: usize,
+    history_window: Option<usize>,
+    evaluation_size: Option<usize>,
+    progress_reporter: Option<ProgressReporter>,
+    cancellation_token: CancellationToken,
+}
+
+impl Backtest {
+    /// `chain_factory` 为每一期创建一个新的分析链，分析链需要产出 `FINAL_PROCESSOR_CHAIN_RESULTS`
+    pub fn new(
+        chain_factory: impl Fn() -> ProcessorChain + Send + Sync + 'static,
+        expect_result_size: usize,
+    ) -> Self {
+        Self {
+            chain_factory: Box::new(chain_factory),
+            expect_result_size,
+            minimum_history_size: 100,
+            history_window: None,
+            evaluation_size: None,
+            progress_reporter: None,
+            cancellation_token: CancellationToken::new(),
+        }
+    }
+
+    /// 历史期数少于该值的开奖期不参与回测
+    pub fn minimum_history_size(mut self, minimum_history_size: usize) -> Self {
+        self.minimum_history_size = minimum_history_size;
+        self
+    }
+
+    /// 只使用每期之前最近的若干期作为历史，`None` 表示使用全部历史
+    pub fn history_window(mut self, history_window: Option<usize>) -> Self {
+        self.history_window = history_window;
+        self
+    }
+
+    /// 只回测最近的若干期，`None` 表示回测全部历史
+    pub fn evaluation_size(mut self, evaluation_size: Option<usize>) -> Self {
+        self.evaluation_size = evaluation_size;
+        self
+    }
+
+    /// 每回测完一期通过 `progress_reporter` 报告进度
+    pub fn progress_reporter(mut self, progress_reporter: Option<ProgressReporter>) -> Self {
+        self.progress_reporter = progress_reporter;
+        self
+    }
+
+    /// 调用方通过 `cancellation_token` 取消回测，每一期的分析链也会因此停止
+    pub fn cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    fn report_progress(&self, completed: usize, total: usize, message: String) {
+        if let Some(progress_reporter) = &self.progress_reporter {
+            progress_reporter.report(ProgressEvent::new("回测", completed, Some(total), message));
+        }
+    }
+
+    pub async fn run(&self, prize_records: &[PrBusinessObj]) -> Result<BacktestReport, Error> {
+        // 对中奖记录按照从最近到最早进行排序
+        let sorted_records = prize_records
+            .iter()
+            .sorted_by_key(|record| record.date)
+            .rev()
+            .collect::<Vec<&PrBusinessObj>>();
+        let evaluation_size = self
+            .evaluation_size
+            .unwrap_or(sorted_records.len())
+            .min(sorted_records.len());
+        let mut draw_results = Vec::new();
+        for (completed, (index, draw)) in sorted_records
+            .iter()
+            .enumerate()
+            .take(evaluation_size)
+            .rev()
+            .enumerate()
+        {
+            if self.cancellation_token.is_cancelled() {
+                return Err(Error::Cancelled(format!(
+                    "回测在第 {} 期之前被取消",
+                    draw.code
+                )));
+            }
+            self.report_progress(
+                completed,
+                evaluation_size,
+                format!("回测第 {} 期", draw.code),
+            );
+            let history_size = self
+                .history_window
+                .unwrap_or(usize::MAX)
+                .min(sorted_records.len() - index - 1);
+            if history_size < self.minimum_history_size {
+                debug!(
+                    "第 {} 期的历史期数 {history_size} 不足，跳过回测",
+                    draw.code
+                );
+                continue;
+            }
+            // 重新编排历史记录的索引，使其与直接采集的数据保持一致
+            let history = sorted_records[index + 1..index + 1 + history_size]
+                .iter()
+                .enumerate()
+                .map(|(seq, record)| PrBusinessObj {
+                    seq,
+                    ..(*record).clone()
+                })
+                .collect::<Vec<PrBusinessObj>>();
+            let mut context = ProcessorContext::new(&history, self.expect_result_size)
+                .with_cancellation_token(self.cancellation_token.clone());
+            let mut processor_chain = (self.chain_factory)();
+            processor_chain.execute(&mut context).await?;
+            let tickets = context
+                .get_attribute(&FINAL_PROCESSOR_CHAIN_RESULTS)
+                .ok_or(Error::ContextAttrNotExist(
+                    FINAL_PROCESSOR_CHAIN_RESULTS.to_string(),
+                ))?;
+            let (prize_grades, prize_return) = score_tickets(draw, tickets);
+            debug!(
+                "第 {} 期回测完成，预测 {} 注，中奖奖级：{prize_grades:?}，奖金：{prize_return}",
+                draw.code,
+                tickets.len()
+            );
+            draw_results.push(BacktestDrawResult {
+                code: draw.code.clone(),
+                history_size,
+                ticket_count: tickets.len(),
+                prize_grades,
+                cost: tickets.len() as u64 * TICKET_PRICE,
+                prize_return,
+            });
+        }
+        self.report_progress(evaluation_size, evaluation_size, "回测完成".to_string());
+        let report = BacktestReport::new(draw_results);
+        info!(
+            "回测 {} 期，共 {} 注，总投入 {}，总奖金 {}，投资回报率 {:.2}%",
+            report.evaluated_draws,
+            report.ticket_count,
+            report.total_cost,
+            report.total_return,
+            report.roi * 100.0
+        );
+        Ok(report)
+    }
+}
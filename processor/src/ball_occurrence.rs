@@ -2,9 +2,33 @@ use std::collections::HashMap;
 
 use crate::context::OccurrenceDetail;
 use crate::{BALL_OCCURRENCE, Processor, ProcessorContext, error::Error};
-use ssq_tool_domain::Ball;
+use ssq_tool_domain::{Ball, PrBusinessObj};
 use tracing::trace;
 
+/// 收集每个双色球在给定中奖记录中出现的索引
+pub(crate) fn collect_balls_occur_seq<'r>(
+    records: impl IntoIterator<Item = &'r PrBusinessObj>,
+) -> HashMap<Ball, Vec<usize>> {
+    let mut balls_occur_seq = HashMap::<Ball, Vec<usize>>::new();
+    records.into_iter().for_each(|record| {
+        balls_occur_seq
+            .entry(record.blue_ball.into())
+            .and_modify(|seqs| {
+                seqs.push(record.seq);
+            })
+            .or_insert(vec![record.seq]);
+        record.red_balls.iter().for_each(|red_ball| {
+            balls_occur_seq
+                .entry((*red_ball).into())
+                .and_modify(|seqs| {
+                    seqs.push(record.seq);
+                })
+                .or_insert(vec![record.seq]);
+        });
+    });
+    balls_occur_seq
+}
+
 pub struct BallOccurrenceProcessor;
 
 #[async_trait::async_trait]
@@ -14,24 +38,8 @@ impl Processor for BallOccurrenceProcessor {
     }
 
     async fn execute(&mut self, context: &mut ProcessorContext) -> Result<(), Error> {
-        let mut balls_occur_seq = HashMap::<Ball, Vec<usize>>::new();
+        let balls_occur_seq = collect_balls_occur_seq(context.get_prize_records());
         let total_prized_record_num = context.get_prize_records().len();
-        context.get_prize_records().iter().for_each(|record| {
-            balls_occur_seq
-                .entry(record.blue_ball.into())
-                .and_modify(|seqs| {
-                    seqs.push(record.seq);
-                })
-                .or_insert(vec![record.seq]);
-            record.red_balls.iter().for_each(|red_ball| {
-                balls_occur_seq
-                    .entry((*red_ball).into())
-                    .and_modify(|seqs| {
-                        seqs.push(record.seq);
-                    })
-                    .or_insert(vec![record.seq]);
-            });
-        });
         balls_occur_seq.iter().for_each(|(k, v)| match k {
             Ball::Blue(ball) => {
                 trace!("红球 {ball} 出现索引：{v:?}");
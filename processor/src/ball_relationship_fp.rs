@@ -1,5 +1,6 @@
 use crate::context::ProcessorContext;
 use crate::error::Error;
+use crate::progress::ProgressEvent;
 use crate::{
     BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP, Processor, RED_BALL_AND_RED_BALL_RELATIONSHIP_FP,
 };
@@ -7,6 +8,12 @@ use fp_growth::algorithm::{FPGrowth, FPResult};
 use ssq_tool_domain::{BlueBall, RedBall};
 use std::collections::HashMap;
 
+/// 最小支持度的下限。支持度为 1 时每期号码的所有子集都是频繁项集，结果没有意义且挖掘最慢
+pub const MIN_SUPPORT: usize = 2;
+
+/// 按照蓝球以及每个红球分组挖掘红球的频繁项集。单次挖掘无法中途停止，
+/// 因此只在每个号码的挖掘之间检查是否被取消或者超时；每期最多 6 个红球，
+/// 单次挖掘的频繁项集不超过该组中奖记录数的 63 倍，耗时有限
 pub struct BallRelationshipFpProcessor {
     minimum_support: usize,
 }
@@ -16,6 +23,7 @@ impl BallRelationshipFpProcessor {
         BallRelationshipFpProcessor { minimum_support }
     }
 }
+
 #[async_trait::async_trait]
 impl Processor for BallRelationshipFpProcessor {
     fn name(&self) -> &str {
@@ -23,6 +31,12 @@ impl Processor for BallRelationshipFpProcessor {
     }
 
     async fn execute(&mut self, context: &mut ProcessorContext) -> Result<(), Error> {
+        if self.minimum_support < MIN_SUPPORT {
+            return Err(Error::InvalidProcessorConfig(format!(
+                "频繁项集的最小支持度不能小于 {MIN_SUPPORT}：{}",
+                self.minimum_support
+            )));
+        }
         let mut blue_ball_and_red_ball_transactions = HashMap::<BlueBall, Vec<Vec<RedBall>>>::new();
         context.get_prize_records().iter().for_each(|record| {
             blue_ball_and_red_ball_transactions
@@ -59,21 +73,41 @@ impl Processor for BallRelationshipFpProcessor {
             });
         });
 
+        // 每个号码的频繁项集计算完后报告一次进度
+        let total =
+            blue_ball_and_red_ball_transactions.len() + red_ball_and_red_ball_transactions.len();
+        let mut completed = 0;
+        let mut report_progress = |context: &ProcessorContext, message: String| {
+            completed += 1;
+            context.report_progress(ProgressEvent::new(
+                "频繁项集",
+                completed,
+                Some(total),
+                message,
+            ));
+        };
+
         let blue_ball_and_red_ball_fp_growth = blue_ball_and_red_ball_transactions
             .into_iter()
             .map(|(blue_ball, red_ball_transactions)| {
+                context.check_cancelled(self.name())?;
                 let fp_growth = FPGrowth::new(red_ball_transactions, self.minimum_support);
-                (blue_ball, fp_growth.find_frequent_patterns())
+                let fp_result = fp_growth.find_frequent_patterns();
+                report_progress(context, format!("蓝球 {blue_ball} 与红球的频繁项集"));
+                Ok((blue_ball, fp_result))
             })
-            .collect::<HashMap<BlueBall, FPResult<RedBall>>>();
+            .collect::<Result<HashMap<BlueBall, FPResult<RedBall>>, Error>>()?;
 
         let red_ball_and_red_ball_fp_growth = red_ball_and_red_ball_transactions
             .into_iter()
             .map(|(red_ball, red_ball_transactions)| {
+                context.check_cancelled(self.name())?;
                 let fp_growth = FPGrowth::new(red_ball_transactions, self.minimum_support);
-                (red_ball, fp_growth.find_frequent_patterns())
+                let fp_result = fp_growth.find_frequent_patterns();
+                report_progress(context, format!("红球 {red_ball} 与红球的频繁项集"));
+                Ok((red_ball, fp_result))
             })
-            .collect::<HashMap<RedBall, FPResult<RedBall>>>();
+            .collect::<Result<HashMap<RedBall, FPResult<RedBall>>, Error>>()?;
 
         context.set_attribute(
             &BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP,
@@ -86,3 +120,18 @@ impl Processor for BallRelationshipFpProcessor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rejects_minimum_support_below_lower_bound() {
+        let mut context = ProcessorContext::new(&[], 5);
+        let error = BallRelationshipFpProcessor::new(MIN_SUPPORT - 1)
+            .execute(&mut context)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::InvalidProcessorConfig(_)));
+    }
+}
@@ -0,0 +1,212 @@
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::statistics::beta_quantile;
+use crate::{BAYESIAN_BALL_PROBABILITIES, Processor};
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::{Ball, BlueBall, RedBall};
+use std::collections::BTreeMap;
+use strum::{EnumCount, IntoEnumIterator};
+use tracing::info;
+
+/// 可信区间的置信水平
+const CREDIBLE_LEVEL: f64 = 0.95;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "后验概率")]
+pub struct BallPosterior {
+    #[serde(rename = "加权出现次数")]
+    weighted_count: f64,
+    #[serde(rename = "后验均值")]
+    mean: f64,
+    #[serde(rename = "可信区间下限")]
+    lower: f64,
+    #[serde(rename = "可信区间上限")]
+    upper: f64,
+    #[serde(rename = "下期出现概率")]
+    draw_probability: f64,
+}
+
+impl BallPosterior {
+    /// 按照衰减加权后的出现次数
+    pub fn weighted_count(&self) -> f64 {
+        self.weighted_count
+    }
+
+    /// 该号码在同色球出现次数中所占比例的后验均值
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// 95% 可信区间的下限
+    pub fn lower(&self) -> f64 {
+        self.lower
+    }
+
+    /// 95% 可信区间的上限
+    pub fn upper(&self) -> f64 {
+        self.upper
+    }
+
+    /// 下一期开出该号码的概率，即后验均值乘以每期开出的同色球个数
+    pub fn draw_probability(&self) -> f64 {
+        self.draw_probability
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "双色球后验分布")]
+pub struct BallPosteriors {
+    #[serde(rename = "先验伪计数")]
+    prior_alpha: f64,
+    #[serde(rename = "衰减系数")]
+    decay: f64,
+    #[serde(rename = "有效期数")]
+    effective_draws: f64,
+    #[serde(rename = "红球")]
+    red: BTreeMap<RedBall, BallPosterior>,
+    #[serde(rename = "蓝球")]
+    blue: BTreeMap<BlueBall, BallPosterior>,
+}
+
+impl BallPosteriors {
+    pub fn prior_alpha(&self) -> f64 {
+        self.prior_alpha
+    }
+
+    pub fn decay(&self) -> f64 {
+        self.decay
+    }
+
+    /// 加权后的有效期数
+    pub fn effective_draws(&self) -> f64 {
+        self.effective_draws
+    }
+
+    pub fn red(&self) -> &BTreeMap<RedBall, BallPosterior> {
+        &self.red
+    }
+
+    pub fn blue(&self) -> &BTreeMap<BlueBall, BallPosterior> {
+        &self.blue
+    }
+
+    pub fn get(&self, ball: &Ball) -> Option<&BallPosterior> {
+        match ball {
+            Ball::Red(red_ball) => self.red.get(red_ball),
+            Ball::Blue(blue_ball) => self.blue.get(blue_ball),
+        }
+    }
+}
+
+/// 计算 Dirichlet 后验分布的边缘贝塔分布的均值与可信区间
+fn posteriors<T: Ord + Copy>(
+    weighted_counts: &BTreeMap<T, f64>,
+    prior_alpha: f64,
+    balls_per_draw: usize,
+) -> BTreeMap<T, BallPosterior> {
+    let alpha_sum = weighted_counts
+        .values()
+        .map(|count| count + prior_alpha)
+        .sum::<f64>();
+    let tail = (1.0 - CREDIBLE_LEVEL) / 2.0;
+    weighted_counts
+        .iter()
+        .map(|(ball, count)| {
+            let alpha = count + prior_alpha;
+            let beta = alpha_sum - alpha;
+            let mean = alpha / alpha_sum;
+            let posterior = BallPosterior {
+                weighted_count: *count,
+                mean,
+                lower: beta_quantile(alpha, beta, tail),
+                upper: beta_quantile(alpha, beta, 1.0 - tail),
+                draw_probability: (mean * balls_per_draw as f64).min(1.0),
+            };
+            (*ball, posterior)
+        })
+        .collect()
+}
+
+/// 使用 Dirichlet-多项分布模型估计每个号码的出现概率，每个号码的先验伪计数为 `prior_alpha`，
+/// 距今第 n 期的中奖记录权重为 `decay` 的 n 次方，`decay` 为 1 时不衰减
+pub struct BayesianBallProbabilityProcessor {
+    prior_alpha: f64,
+    decay: f64,
+}
+
+impl BayesianBallProbabilityProcessor {
+    pub fn new(prior_alpha: f64, decay: f64) -> Self {
+        Self { prior_alpha, decay }
+    }
+}
+
+impl Default for BayesianBallProbabilityProcessor {
+    fn default() -> Self {
+        Self::new(1.0, 1.0)
+    }
+}
+
+#[async_trait::async_trait]
+impl Processor for BayesianBallProbabilityProcessor {
+    fn name(&self) -> &str {
+        "BayesianBallProbabilityProcessor"
+    }
+
+    async fn execute(&mut self, context: &mut ProcessorContext) -> Result<(), Error> {
+        if self.prior_alpha.is_nan() || self.prior_alpha <= 0.0 {
+            return Err(Error::InvalidProcessorConfig(format!(
+                "先验伪计数必须为正数：{}",
+                self.prior_alpha
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.decay) || self.decay == 0.0 {
+            return Err(Error::InvalidProcessorConfig(format!(
+                "衰减系数必须在 0 到 1 之间：{}",
+                self.decay
+            )));
+        }
+        let mut red_counts = RedBall::iter()
+            .map(|ball| (ball, 0.0))
+            .collect::<BTreeMap<RedBall, f64>>();
+        let mut blue_counts = BlueBall::iter()
+            .map(|ball| (ball, 0.0))
+            .collect::<BTreeMap<BlueBall, f64>>();
+        let mut effective_draws = 0.0;
+        context
+            .get_prize_records()
+            .iter()
+            .sorted_by_key(|record| record.date)
+            .rev()
+            .enumerate()
+            .for_each(|(age, record)| {
+                let weight = self.decay.powi(age as i32);
+                effective_draws += weight;
+                record.red_balls.iter().for_each(|ball| {
+                    *red_counts.entry(*ball).or_default() += weight;
+                });
+                *blue_counts.entry(record.blue_ball).or_default() += weight;
+            });
+        let result = BallPosteriors {
+            prior_alpha: self.prior_alpha,
+            decay: self.decay,
+            effective_draws,
+            red: posteriors(&red_counts, self.prior_alpha, 6),
+            blue: posteriors(&blue_counts, self.prior_alpha, 1),
+        };
+        let widest_red_interval = result
+            .red
+            .values()
+            .map(|posterior| posterior.upper - posterior.lower)
+            .fold(0.0, f64::max);
+        info!(
+            "有效期数 {:.1}，红球均匀分布比例为 {:.4}，红球可信区间最大宽度为 {:.4}",
+            effective_draws,
+            1.0 / RedBall::COUNT as f64,
+            widest_red_interval
+        );
+        context.set_attribute(&BAYESIAN_BALL_PROBABILITIES, result);
+        Ok(())
+    }
+}
@@ -0,0 +1,367 @@
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::{BLUE_BALL_MARKOV_MODEL, Processor};
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::BlueBall;
+use std::collections::{BTreeMap, HashMap};
+use strum::{EnumCount, IntoEnumIterator};
+use tracing::{debug, info, warn};
+
+/// 马尔可夫链支持的最高阶数。平滑后所有状态之间都可以转移，求平稳分布时状态数为 16 的阶数次幂，
+/// 2 阶时为 256 个状态
+const MAX_ORDER: usize = 2;
+const STATIONARY_MAX_ITERATIONS: usize = 10_000;
+const STATIONARY_TOLERANCE: f64 = 1e-12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "平滑方式")]
+pub enum TransitionSmoothing {
+    /// 不做平滑，没有观测到的状态按照均匀分布处理
+    #[serde(rename = "无")]
+    None,
+    /// 每个转移次数加 1
+    #[serde(rename = "拉普拉斯")]
+    Laplace,
+    /// 每个转移次数加上给定的伪计数
+    #[serde(rename = "加性")]
+    Additive(f64),
+}
+
+impl TransitionSmoothing {
+    fn pseudo_count(&self) -> f64 {
+        match self {
+            TransitionSmoothing::None => 0.0,
+            TransitionSmoothing::Laplace => 1.0,
+            TransitionSmoothing::Additive(pseudo_count) => *pseudo_count,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "蓝球状态转移")]
+pub struct BlueBallTransition {
+    #[serde(rename = "状态")]
+    state: Vec<BlueBall>,
+    #[serde(rename = "观测次数")]
+    observation_count: usize,
+    #[serde(rename = "转移概率")]
+    probabilities: BTreeMap<BlueBall, f64>,
+}
+
+impl BlueBallTransition {
+    /// 按照从早到晚排列的最近若干期蓝球
+    pub fn state(&self) -> &[BlueBall] {
+        &self.state
+    }
+
+    pub fn observation_count(&self) -> usize {
+        self.observation_count
+    }
+
+    pub fn probabilities(&self) -> &BTreeMap<BlueBall, f64> {
+        &self.probabilities
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "蓝球马尔可夫模型")]
+pub struct BlueBallMarkovModel {
+    #[serde(rename = "阶数")]
+    order: usize,
+    #[serde(rename = "平滑方式")]
+    smoothing: TransitionSmoothing,
+    #[serde(rename = "状态转移")]
+    transitions: Vec<BlueBallTransition>,
+    #[serde(rename = "平稳分布")]
+    stationary_distribution: BTreeMap<BlueBall, f64>,
+    #[serde(rename = "平稳分布已收敛")]
+    stationary_converged: bool,
+    #[serde(rename = "最近蓝球")]
+    recent_history: Vec<BlueBall>,
+    #[serde(rename = "下期蓝球概率")]
+    next_draw_probabilities: BTreeMap<BlueBall, f64>,
+}
+
+impl BlueBallMarkovModel {
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    pub fn smoothing(&self) -> TransitionSmoothing {
+        self.smoothing
+    }
+
+    /// 所有观测到的状态的归一化转移概率
+    pub fn transitions(&self) -> &[BlueBallTransition] {
+        &self.transitions
+    }
+
+    /// 蓝球的平稳分布
+    pub fn stationary_distribution(&self) -> &BTreeMap<BlueBall, f64> {
+        &self.stationary_distribution
+    }
+
+    /// 求平稳分布的迭代是否在最大迭代次数内收敛，没有收敛时平稳分布只是近似值
+    pub fn stationary_converged(&self) -> bool {
+        self.stationary_converged
+    }
+
+    /// 按照从早到晚排列的最近 `order` 期蓝球
+    pub fn recent_history(&self) -> &[BlueBall] {
+        &self.recent_history
+    }
+
+    /// 基于最近蓝球得出的下期每个蓝球的出现概率
+    pub fn next_draw_probabilities(&self) -> &BTreeMap<BlueBall, f64> {
+        &self.next_draw_probabilities
+    }
+}
+
+struct TransitionCounts {
+    pseudo_count: f64,
+    counts: HashMap<Vec<BlueBall>, HashMap<BlueBall, usize>>,
+}
+
+impl TransitionCounts {
+    fn probability(&self, state: &[BlueBall], next: BlueBall) -> f64 {
+        let next_counts = self.counts.get(state);
+        let observation_count = next_counts.map_or(0, |counts| counts.values().sum::<usize>());
+        let denominator = observation_count as f64 + self.pseudo_count * BlueBall::COUNT as f64;
+        if denominator <= 0.0 {
+            return 1.0 / BlueBall::COUNT as f64;
+        }
+        let count = next_counts
+            .and_then(|counts| counts.get(&next))
+            .copied()
+            .unwrap_or(0);
+        (count as f64 + self.pseudo_count) / denominator
+    }
+
+    fn probabilities(&self, state: &[BlueBall]) -> BTreeMap<BlueBall, f64> {
+        BlueBall::iter()
+            .map(|next| (next, self.probability(state, next)))
+            .collect()
+    }
+
+    /// 从观测到的状态频率出发做幂迭代，再按照状态的最后一个蓝球求边缘分布，同时返回迭代是否收敛。
+    /// 不做平滑时链可能是周期的（例如蓝球 1、2 交替出现），直接幂迭代会来回振荡，
+    /// 因此对惰性链 (I + P) / 2 迭代，它与原链的平稳分布相同且没有周期。
+    /// 状态按照 16 进制编码为下标，转移概率在迭代前一次算好，每次迭代前通过 `check_cancelled` 检查是否需要停止
+    fn stationary_distribution(
+        &self,
+        order: usize,
+        initial: &HashMap<Vec<BlueBall>, f64>,
+        check_cancelled: impl Fn() -> Result<(), Error>,
+    ) -> Result<(BTreeMap<BlueBall, f64>, bool), Error> {
+        let blue_balls = BlueBall::iter().collect::<Vec<BlueBall>>();
+        let state_count = BlueBall::COUNT.pow(order as u32);
+        let transition_rows = (0..state_count)
+            .map(|index| {
+                let state = (0..order)
+                    .rev()
+                    .map(|position| {
+                        blue_balls[index / BlueBall::COUNT.pow(position as u32) % BlueBall::COUNT]
+                    })
+                    .collect::<Vec<BlueBall>>();
+                blue_balls
+                    .iter()
+                    .map(|next| self.probability(&state, *next))
+                    .collect::<Vec<f64>>()
+            })
+            .collect::<Vec<Vec<f64>>>();
+        let mut distribution = vec![0.0; state_count];
+        initial.iter().for_each(|(state, mass)| {
+            let index = state.iter().fold(0, |index, ball| {
+                index * BlueBall::COUNT + *ball as usize - 1
+            });
+            distribution[index] += mass;
+        });
+        let mut next_distribution = vec![0.0; state_count];
+        let mut converged = false;
+        for iteration in 0..STATIONARY_MAX_ITERATIONS {
+            check_cancelled()?;
+            next_distribution
+                .iter_mut()
+                .zip(&distribution)
+                .for_each(|(next_mass, mass)| *next_mass = mass / 2.0);
+            distribution
+                .iter()
+                .zip(&transition_rows)
+                .enumerate()
+                .filter(|(_, (mass, _))| **mass > 0.0)
+                .for_each(|(index, (mass, row))| {
+                    // 去掉最早的蓝球，在末尾加上下一期的蓝球
+                    let shifted = index * BlueBall::COUNT % state_count;
+                    row.iter().enumerate().for_each(|(next, probability)| {
+                        next_distribution[shifted + next] += mass * probability / 2.0;
+                    });
+                });
+            let difference = next_distribution
+                .iter()
+                .zip(&distribution)
+                .map(|(next_mass, mass)| (next_mass - mass).abs())
+                .sum::<f64>();
+            std::mem::swap(&mut distribution, &mut next_distribution);
+            if difference < STATIONARY_TOLERANCE {
+                debug!("蓝球平稳分布在第 {iteration} 次迭代后收敛");
+                converged = true;
+                break;
+            }
+        }
+        if !converged {
+            warn!("蓝球平稳分布在 {STATIONARY_MAX_ITERATIONS} 次迭代后仍未收敛，结果只是近似值");
+        }
+        let mut marginal = BlueBall::iter()
+            .map(|ball| (ball, 0.0))
+            .collect::<BTreeMap<BlueBall, f64>>();
+        distribution.iter().enumerate().for_each(|(index, mass)| {
+            *marginal
+                .entry(blue_balls[index % BlueBall::COUNT])
+                .or_default() += mass;
+        });
+        Ok((marginal, converged))
+    }
+}
+
+/// 可配置阶数与平滑方式的蓝球马尔可夫链
+pub struct BlueBallMarkovProcessor {
+    order: usize,
+    smoothing: TransitionSmoothing,
+}
+
+impl BlueBallMarkovProcessor {
+    pub fn new(order: usize, smoothing: TransitionSmoothing) -> Self {
+        Self { order, smoothing }
+    }
+}
+
+impl Default for BlueBallMarkovProcessor {
+    fn default() -> Self {
+        Self::new(1, TransitionSmoothing::Laplace)
+    }
+}
+
+#[async_trait::async_trait]
+impl Processor for BlueBallMarkovProcessor {
+    fn name(&self) -> &str {
+        "BlueBallMarkovProcessor"
+    }
+
+    async fn execute(&mut self, context: &mut ProcessorContext) -> Result<(), Error> {
+        if self.order == 0 || self.order > MAX_ORDER {
+            return Err(Error::InvalidProcessorConfig(format!(
+                "马尔可夫链阶数必须在 1 到 {MAX_ORDER} 之间：{}",
+                self.order
+            )));
+        }
+        let pseudo_count = self.smoothing.pseudo_count();
+        if !pseudo_count.is_finite() || pseudo_count < 0.0 {
+            return Err(Error::InvalidProcessorConfig(format!(
+                "平滑伪计数必须为非负数：{pseudo_count}"
+            )));
+        }
+        //对蓝球的出现情况进行日期从小到大排序
+        let prized_blue_balls = context
+            .get_prize_records()
+            .iter()
+            .sorted_by_key(|record| record.date)
+            .map(|record| record.blue_ball)
+            .collect::<Vec<BlueBall>>();
+        if prized_blue_balls.len() <= self.order {
+            return Err(Error::OtherFailure(format!(
+                "{} 阶马尔可夫链至少需要 {} 期中奖记录",
+                self.order,
+                self.order + 1
+            )));
+        }
+
+        let mut counts = HashMap::<Vec<BlueBall>, HashMap<BlueBall, usize>>::new();
+        let mut state_frequencies = HashMap::<Vec<BlueBall>, f64>::new();
+        prized_blue_balls
+            .windows(self.order + 1)
+            .for_each(|window| {
+                let (state, next) = window.split_at(self.order);
+                *counts
+                    .entry(state.to_vec())
+                    .or_default()
+                    .entry(next[0])
+                    .or_default() += 1;
+                *state_frequencies.entry(state.to_vec()).or_default() += 1.0;
+            });
+        let total_states = state_frequencies.values().sum::<f64>();
+        state_frequencies
+            .values_mut()
+            .for_each(|frequency| *frequency /= total_states);
+        let transition_counts = TransitionCounts {
+            pseudo_count,
+            counts,
+        };
+
+        let transitions = transition_counts
+            .counts
+            .iter()
+            .sorted_by(|a, b| a.0.cmp(b.0))
+            .map(|(state, next_counts)| BlueBallTransition {
+                state: state.clone(),
+                observation_count: next_counts.values().sum(),
+                probabilities: transition_counts.probabilities(state),
+            })
+            .collect::<Vec<BlueBallTransition>>();
+        let (stationary_distribution, stationary_converged) = transition_counts
+            .stationary_distribution(self.order, &state_frequencies, || {
+                context.check_cancelled(self.name())
+            })?;
+        let recent_history = prized_blue_balls[prized_blue_balls.len() - self.order..].to_vec();
+        let next_draw_probabilities = transition_counts.probabilities(&recent_history);
+        info!("最近蓝球 {recent_history:?} 之后下期蓝球的概率：{next_draw_probabilities:?}");
+
+        context.set_attribute(
+            &BLUE_BALL_MARKOV_MODEL,
+            BlueBallMarkovModel {
+                order: self.order,
+                smoothing: self.smoothing,
+                transitions,
+                stationary_distribution,
+                stationary_converged,
+                recent_history,
+                next_draw_probabilities,
+            },
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stationary_distribution_converges_for_periodic_chain() {
+        let one = BlueBall::try_from(1).unwrap();
+        let two = BlueBall::try_from(2).unwrap();
+        // 蓝球 1、2 严格交替，不做平滑时是周期为 2 的链，初始状态频率为 1 占 2/3、2 占 1/3
+        let counts = HashMap::from([
+            (vec![one], HashMap::from([(two, 2)])),
+            (vec![two], HashMap::from([(one, 1)])),
+        ]);
+        let transition_counts = TransitionCounts {
+            pseudo_count: TransitionSmoothing::None.pseudo_count(),
+            counts,
+        };
+        let initial = HashMap::from([(vec![one], 2.0 / 3.0), (vec![two], 1.0 / 3.0)]);
+        let (distribution, converged) = transition_counts
+            .stationary_distribution(1, &initial, || Ok(()))
+            .unwrap();
+        assert!(converged);
+        assert!((distribution[&one] - 0.5).abs() < 1e-9);
+        assert!((distribution[&two] - 0.5).abs() < 1e-9);
+        assert!(
+            distribution
+                .iter()
+                .filter(|(ball, _)| **ball != one && **ball != two)
+                .all(|(_, mass)| *mass == 0.0)
+        );
+    }
+}
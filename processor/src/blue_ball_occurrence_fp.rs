@@ -32,13 +32,10 @@ impl Processor for BlueBallFollowingOccurrenceProcessor {
             let next = records[1];
             blue_ball_following_occurrences
                 .entry(current)
-                .and_modify(|next_occurrences| {
-                    next_occurrences
-                        .entry(next)
-                        .and_modify(|count| *count += 1)
-                        .or_insert(1);
-                })
-                .or_default();
+                .or_default()
+                .entry(next)
+                .and_modify(|count| *count += 1)
+                .or_insert(1);
         });
 
         info!("蓝球后续出现情况：{blue_ball_following_occurrences:?}");
@@ -1,4 +1,7 @@
+use crate::error::Error;
+use crate::progress::{ProgressEvent, ProgressReporter};
 use derive_more::Display;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use ssq_tool_domain::PrBusinessObj;
 use std::{
@@ -7,6 +10,8 @@ use std::{
     collections::HashMap,
     marker::PhantomData,
 };
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Display)]
 #[display("{name}")]
@@ -36,6 +41,9 @@ pub struct ProcessorContext<'a> {
     prize_records: &'a [PrBusinessObj],
     expect_result_size: usize,
     attributes: HashMap<String, Box<dyn Any + Send + 'static>>,
+    progress_reporter: Option<ProgressReporter>,
+    cancellation_token: CancellationToken,
+    deadline: Option<Instant>,
 }
 
 impl<'a> ProcessorContext<'a> {
@@ -44,9 +52,64 @@ impl<'a> ProcessorContext<'a> {
             attributes: HashMap::new(),
             expect_result_size,
             prize_records,
+            progress_reporter: None,
+            cancellation_token: CancellationToken::new(),
+            deadline: None,
         }
     }
 
+    /// 执行过程中通过 `progress_reporter` 报告进度
+    pub fn with_progress_reporter(mut self, progress_reporter: ProgressReporter) -> Self {
+        self.progress_reporter = Some(progress_reporter);
+        self
+    }
+
+    /// 调用方通过 `cancellation_token` 取消执行
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    pub fn progress_reporter(&self) -> Option<&ProgressReporter> {
+        self.progress_reporter.as_ref()
+    }
+
+    pub fn report_progress(&self, event: ProgressEvent) {
+        if let Some(progress_reporter) = &self.progress_reporter {
+            progress_reporter.report(event);
+        }
+    }
+
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation_token
+    }
+
+    /// 当前执行器必须在该时间之前执行完，由执行器链按照时间限制设置
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    pub(crate) fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// 调用方取消了执行，或者已经超过了当前执行器的时间限制。
+    /// 耗时较长的执行器应当在循环中检查，尽早停止
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation_token.is_cancelled()
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// 被取消或者超时时返回错误，执行器链会把它转换为相应的取消或超时错误
+    pub fn check_cancelled(&self, processor_name: &str) -> Result<(), Error> {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled(format!("{processor_name} 没有执行完")));
+        }
+        Ok(())
+    }
+
     pub fn get_prize_records(&self) -> &'a [PrBusinessObj] {
         self.prize_records
     }
@@ -87,7 +150,7 @@ impl<'a> ProcessorContext<'a> {
     }
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 #[serde(rename = "出现细节")]
 pub struct OccurrenceDetail {
     #[serde(rename = "平均出现间隔")]
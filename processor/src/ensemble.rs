@@ -0,0 +1,253 @@
+use crate::FinalProcessorChainResult;
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::strategy::{BallScores, ScoredTicket, Strategy, tickets_from_ball_scores};
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::Hash;
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreNormalization {
+    /// 除以最大绝对值
+    #[default]
+    Max,
+    /// 线性缩放到 0 到 1 之间
+    MinMax,
+    /// 减去均值后除以标准差
+    ZScore,
+}
+
+impl ScoreNormalization {
+    fn normalize(&self, scores: &[f64]) -> Vec<f64> {
+        if scores.is_empty() {
+            return Vec::new();
+        }
+        match self {
+            ScoreNormalization::Max => {
+                let max = scores.iter().fold(0f64, |max, score| max.max(score.abs()));
+                scores
+                    .iter()
+                    .map(|score| if max > 0.0 { score / max } else { 0.0 })
+                    .collect()
+            }
+            ScoreNormalization::MinMax => {
+                let min = scores.iter().copied().fold(f64::INFINITY, f64::min);
+                let max = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                scores
+                    .iter()
+                    .map(|score| {
+                        if max > min {
+                            (score - min) / (max - min)
+                        } else {
+                            1.0
+                        }
+                    })
+                    .collect()
+            }
+            ScoreNormalization::ZScore => {
+                let n = scores.len() as f64;
+                let mean = scores.iter().sum::<f64>() / n;
+                let standard_deviation =
+                    (scores.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+                scores
+                    .iter()
+                    .map(|score| {
+                        if standard_deviation > 0.0 {
+                            (score - mean) / standard_deviation
+                        } else {
+                            0.0
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EnsembleMethod {
+    /// 各策略的得分归一化后按照权重求和
+    WeightedScore { normalization: ScoreNormalization },
+    /// Borda 计数，长度为 n 的排名中第 r 名（从 0 开始）得到 n - r 分，再乘以权重
+    Borda,
+    /// 倒数排名融合，第 r 名（从 0 开始）得到 权重 / (k + r + 1) 分
+    ReciprocalRank { k: f64 },
+}
+
+impl Default for EnsembleMethod {
+    fn default() -> Self {
+        EnsembleMethod::WeightedScore {
+            normalization: ScoreNormalization::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EnsembleLevel {
+    /// 融合各策略生成的候选号码
+    #[default]
+    Ticket,
+    /// 融合各策略给出的双色球得分，再按照融合后的得分组合号码
+    Ball,
+}
+
+/// 把多个策略的得分或排名融合为一个排名
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Ensemble {
+    #[serde(default)]
+    method: EnsembleMethod,
+    #[serde(default)]
+    level: EnsembleLevel,
+}
+
+impl Ensemble {
+    pub fn new(method: EnsembleMethod, level: EnsembleLevel) -> Self {
+        Self { method, level }
+    }
+
+    pub fn method(&self) -> EnsembleMethod {
+        self.method
+    }
+
+    pub fn level(&self) -> EnsembleLevel {
+        self.level
+    }
+
+    /// 融合多个带权重的得分列表，返回按照融合得分从高到低排列的结果
+    pub fn combine<K>(&self, ranked_lists: Vec<(Vec<(K, f64)>, f64)>) -> Vec<(K, f64)>
+    where
+        K: Hash + Eq + Clone + Ord,
+    {
+        let mut combined = HashMap::<K, f64>::new();
+        ranked_lists.into_iter().for_each(|(list, weight)| {
+            let list = list
+                .into_iter()
+                .sorted_by(|a, b| {
+                    b.1.partial_cmp(&a.1)
+                        .unwrap_or(Ordering::Equal)
+                        .then_with(|| a.0.cmp(&b.0))
+                })
+                .collect::<Vec<(K, f64)>>();
+            let list_size = list.len();
+            let scores = match self.method {
+                EnsembleMethod::WeightedScore { normalization } => normalization
+                    .normalize(&list.iter().map(|(_, score)| *score).collect::<Vec<f64>>()),
+                EnsembleMethod::Borda => (0..list_size)
+                    .map(|rank| (list_size - rank) as f64)
+                    .collect(),
+                EnsembleMethod::ReciprocalRank { k } => (0..list_size)
+                    .map(|rank| 1.0 / (k + rank as f64 + 1.0))
+                    .collect(),
+            };
+            list.into_iter().zip(scores).for_each(|((key, _), score)| {
+                *combined.entry(key).or_default() += score * weight;
+            });
+        });
+        combined
+            .into_iter()
+            .sorted_by(|a, b| {
+                b.1.partial_cmp(&a.1)
+                    .unwrap_or(Ordering::Equal)
+                    .then_with(|| a.0.cmp(&b.0))
+            })
+            .collect()
+    }
+
+    /// 融合多个带权重的双色球得分
+    pub fn combine_ball_scores(&self, ball_scores: Vec<(BallScores, f64)>) -> BallScores {
+        let (red_lists, blue_lists): (Vec<_>, Vec<_>) = ball_scores
+            .into_iter()
+            .map(|(scores, weight)| {
+                (
+                    (
+                        scores
+                            .red()
+                            .iter()
+                            .map(|(ball, score)| (*ball, *score))
+                            .collect::<Vec<_>>(),
+                        weight,
+                    ),
+                    (
+                        scores
+                            .blue()
+                            .iter()
+                            .map(|(ball, score)| (*ball, *score))
+                            .collect::<Vec<_>>(),
+                        weight,
+                    ),
+                )
+            })
+            .unzip();
+        BallScores::new(
+            self.combine(red_lists).into_iter().collect(),
+            self.combine(blue_lists).into_iter().collect(),
+        )
+    }
+
+    /// 按照融合方式调用各策略并生成融合后的候选号码
+    pub fn generate(
+        &self,
+        context: &ProcessorContext,
+        strategies: &mut [(Box<dyn Strategy + Send>, f64)],
+        size: usize,
+    ) -> Result<Vec<ScoredTicket>, Error> {
+        match self.level {
+            EnsembleLevel::Ticket => {
+                let mut sources = HashMap::<FinalProcessorChainResult, Vec<String>>::new();
+                let mut ranked_lists = Vec::new();
+                for (strategy, weight) in strategies.iter_mut() {
+                    let tickets = strategy.generate(context, size)?;
+                    info!("策略 {} 生成 {} 注候选号码", strategy.name(), tickets.len());
+                    let list = tickets
+                        .into_iter()
+                        .map(|ticket| {
+                            let strategies = sources.entry(ticket.ticket().clone()).or_default();
+                            if !strategies.iter().any(|name| name == strategy.name()) {
+                                strategies.push(strategy.name().to_owned());
+                            }
+                            (ticket.ticket().clone(), ticket.score())
+                        })
+                        .collect::<Vec<_>>();
+                    ranked_lists.push((list, *weight));
+                }
+                Ok(self
+                    .combine(ranked_lists)
+                    .into_iter()
+                    .map(|(ticket, score)| {
+                        let strategies = sources.remove(&ticket).unwrap_or_default();
+                        ScoredTicket::with_strategies(ticket, score, strategies)
+                    })
+                    .collect())
+            }
+            EnsembleLevel::Ball => {
+                let mut ball_scores = Vec::new();
+                for (strategy, weight) in strategies.iter_mut() {
+                    ball_scores.push((strategy.ball_scores(context)?, *weight));
+                }
+                let combined = self.combine_ball_scores(ball_scores);
+                let strategy_names = strategies
+                    .iter()
+                    .map(|(strategy, _)| strategy.name().to_owned())
+                    .collect::<Vec<String>>();
+                Ok(tickets_from_ball_scores(&combined, size, "Ensemble")
+                    .into_iter()
+                    .map(|ticket| {
+                        let score = ticket.score();
+                        ScoredTicket::with_strategies(
+                            ticket.into_ticket(),
+                            score,
+                            strategy_names.clone(),
+                        )
+                    })
+                    .collect())
+            }
+        }
+    }
+}
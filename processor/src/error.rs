@@ -1,3 +1,4 @@
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -8,6 +9,24 @@ pub enum Error {
     ContextAttrNotExist(String),
     #[error(transparent)]
     IoFailure(#[from] std::io::Error),
+    #[error("无效的执行器配置：{0}")]
+    InvalidProcessorConfig(String),
+    #[error("分析被取消：{0}")]
+    Cancelled(String),
+    #[error("分析链 {0} 超时")]
+    ChainTimeout(String),
+    #[error("执行器 {0} 超时，时间限制为 {1:?}")]
+    ProcessorTimeout(String, Duration),
     #[error("其他错误: {0}")]
     OtherFailure(String),
 }
+
+impl Error {
+    /// 分析是否因为被取消或者超时而没有执行完，这时上下文中保留着已经执行完的执行器的结果
+    pub fn is_interrupted(&self) -> bool {
+        matches!(
+            self,
+            Error::Cancelled(_) | Error::ChainTimeout(_) | Error::ProcessorTimeout(..)
+        )
+    }
+}
@@ -0,0 +1,363 @@
+use crate::backtest::TICKET_PRICE;
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::{EXPECTED_VALUES, NEXT_DRAW_EXPECTED_VALUE, Processor};
+use chrono::NaiveDate;
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::{BlueBall, PrBusinessObj, PrizeGrade, RedBall};
+use std::collections::BTreeMap;
+use strum::EnumCount;
+use tracing::info;
+
+/// 当期奖金占销售额的比例
+const PRIZE_FUND_RATE: f64 = 0.51;
+/// 奖池达到该金额后一等奖分为两部分派奖
+const HIGH_POOL_THRESHOLD: u64 = 100_000_000;
+/// 奖池低于阈值时一等奖占高等奖奖金的比例
+const FIRST_PRIZE_RATE: f64 = 0.75;
+/// 奖池高于阈值时一等奖第一部分占高等奖奖金的比例
+const FIRST_PRIZE_HIGH_POOL_RATE: f64 = 0.55;
+/// 奖池高于阈值时一等奖第二部分占高等奖奖金的比例
+const FIRST_PRIZE_EXTRA_RATE: f64 = 0.2;
+/// 二等奖占高等奖奖金的比例
+const SECOND_PRIZE_RATE: f64 = 0.25;
+/// 浮动奖级的单注奖金上限
+const FLOATING_PRIZE_CAP: f64 = 5_000_000.0;
+
+fn combinations(n: usize, k: usize) -> f64 {
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+/// 一注单式号码中各个奖级的概率
+pub fn prize_grade_probabilities() -> BTreeMap<PrizeGrade, f64> {
+    let red_count = RedBall::COUNT;
+    let blue_count = BlueBall::COUNT;
+    let total = combinations(red_count, 6) * blue_count as f64;
+    let mut probabilities = BTreeMap::<PrizeGrade, f64>::new();
+    (0..=6)
+        .cartesian_product([true, false])
+        .for_each(|(red_hits, blue_hit)| {
+            if let Some(grade) = PrizeGrade::from_hits(red_hits, blue_hit) {
+                let blue_ways = if blue_hit { 1 } else { blue_count - 1 } as f64;
+                let ways = combinations(6, red_hits)
+                    * combinations(red_count - 6, 6 - red_hits)
+                    * blue_ways;
+                *probabilities.entry(grade).or_default() += ways / total;
+            }
+        });
+    probabilities
+}
+
+/// 其他中奖注数服从泊松分布时，与其他人平分奖金后单注奖金的期望
+fn expected_shared_prize(fund: f64, others_mean: f64, cap: f64) -> f64 {
+    if fund <= 0.0 {
+        return 0.0;
+    }
+    let max_others = (others_mean + 10.0 * others_mean.sqrt() + 20.0).ceil() as usize;
+    let mut probability = (-others_mean).exp();
+    let mut expected = 0.0;
+    for others in 0..=max_others {
+        expected += probability * (fund / (others + 1) as f64).min(cap);
+        probability *= others_mean / (others + 1) as f64;
+    }
+    expected
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "单注期望收益")]
+pub struct ExpectedValue {
+    #[serde(rename = "销售额")]
+    sales: u64,
+    #[serde(rename = "奖池金额")]
+    jackpot_pool: u64,
+    #[serde(rename = "高等奖奖金")]
+    high_grade_fund: f64,
+    #[serde(rename = "一等奖预计单注奖金")]
+    first_prize_estimate: f64,
+    #[serde(rename = "二等奖校准系数")]
+    second_prize_calibration: f64,
+    #[serde(rename = "二等奖预计单注奖金")]
+    second_prize_estimate: f64,
+    #[serde(rename = "一等奖分奖概率")]
+    jackpot_sharing_probability: f64,
+    #[serde(rename = "一等奖分奖比例期望")]
+    jackpot_share_expectation: f64,
+    #[serde(rename = "各奖级期望收益")]
+    grade_expected_values: BTreeMap<PrizeGrade, f64>,
+    #[serde(rename = "期望收益")]
+    expected_value: f64,
+    #[serde(rename = "返奖率")]
+    return_rate: f64,
+}
+
+impl ExpectedValue {
+    /// 根据本期销售额与上期滚入的奖池估算一注单式号码的期望收益，
+    /// 固定奖级与浮动奖级的中奖注数都按照期望注数估算
+    pub fn new(sales: u64, jackpot_pool: u64) -> Self {
+        Self::calibrated(sales, jackpot_pool, 1.0)
+    }
+
+    /// 与 [`ExpectedValue::new`] 相同，二等奖的估算值再乘以 `second_prize_calibration`，
+    /// 校准系数来自历史上实际派发的二等奖奖金与估算值的比值
+    pub fn calibrated(sales: u64, jackpot_pool: u64, second_prize_calibration: f64) -> Self {
+        let ticket_count = (sales / TICKET_PRICE) as f64;
+        let probabilities = prize_grade_probabilities();
+        let fixed_prize_fund = probabilities
+            .iter()
+            .filter_map(|(grade, probability)| {
+                grade
+                    .fixed_money()
+                    .map(|money| money as f64 * probability * ticket_count)
+            })
+            .sum::<f64>();
+        let high_grade_fund = (sales as f64 * PRIZE_FUND_RATE - fixed_prize_fund).max(0.0);
+        let first_others = ticket_count * probabilities[&PrizeGrade::First];
+        let second_others = ticket_count * probabilities[&PrizeGrade::Second];
+        let first_prize_estimate = if jackpot_pool < HIGH_POOL_THRESHOLD {
+            expected_shared_prize(
+                high_grade_fund * FIRST_PRIZE_RATE + jackpot_pool as f64,
+                first_others,
+                FLOATING_PRIZE_CAP,
+            )
+        } else {
+            expected_shared_prize(
+                high_grade_fund * FIRST_PRIZE_HIGH_POOL_RATE + jackpot_pool as f64,
+                first_others,
+                FLOATING_PRIZE_CAP,
+            ) + expected_shared_prize(
+                high_grade_fund * FIRST_PRIZE_EXTRA_RATE,
+                first_others,
+                FLOATING_PRIZE_CAP,
+            )
+        };
+        let second_prize_estimate = expected_shared_prize(
+            high_grade_fund * SECOND_PRIZE_RATE,
+            second_others,
+            FLOATING_PRIZE_CAP,
+        ) * second_prize_calibration;
+        let grade_expected_values = probabilities
+            .iter()
+            .map(|(grade, probability)| {
+                let money = match grade {
+                    PrizeGrade::First => first_prize_estimate,
+                    PrizeGrade::Second => second_prize_estimate,
+                    _ => grade.fixed_money().unwrap_or(0) as f64,
+                };
+                (*grade, money * probability)
+            })
+            .collect::<BTreeMap<PrizeGrade, f64>>();
+        let expected_value = grade_expected_values.values().sum::<f64>();
+        let jackpot_share_expectation = if first_others > 0.0 {
+            (1.0 - (-first_others).exp()) / first_others
+        } else {
+            1.0
+        };
+        Self {
+            sales,
+            jackpot_pool,
+            high_grade_fund,
+            first_prize_estimate,
+            second_prize_calibration,
+            second_prize_estimate,
+            jackpot_sharing_probability: 1.0 - (-first_others).exp(),
+            jackpot_share_expectation,
+            grade_expected_values,
+            expected_value,
+            return_rate: expected_value / TICKET_PRICE as f64,
+        }
+    }
+
+    pub fn sales(&self) -> u64 {
+        self.sales
+    }
+
+    pub fn jackpot_pool(&self) -> u64 {
+        self.jackpot_pool
+    }
+
+    /// 扣除固定奖级后用于一、二等奖的奖金
+    pub fn high_grade_fund(&self) -> f64 {
+        self.high_grade_fund
+    }
+
+    pub fn first_prize_estimate(&self) -> f64 {
+        self.first_prize_estimate
+    }
+
+    /// 二等奖估算值的校准系数，1 表示没有校准
+    pub fn second_prize_calibration(&self) -> f64 {
+        self.second_prize_calibration
+    }
+
+    pub fn second_prize_estimate(&self) -> f64 {
+        self.second_prize_estimate
+    }
+
+    /// 中一等奖时与其他人平分奖金的概率
+    pub fn jackpot_sharing_probability(&self) -> f64 {
+        self.jackpot_sharing_probability
+    }
+
+    /// 中一等奖时分到的奖金比例的期望 E[1/(1+K)]
+    pub fn jackpot_share_expectation(&self) -> f64 {
+        self.jackpot_share_expectation
+    }
+
+    pub fn grade_expected_values(&self) -> &BTreeMap<PrizeGrade, f64> {
+        &self.grade_expected_values
+    }
+
+    pub fn expected_value(&self) -> f64 {
+        self.expected_value
+    }
+
+    /// 期望收益与单注价格的比值
+    pub fn return_rate(&self) -> f64 {
+        self.return_rate
+    }
+
+    /// 按照开奖后公布的各奖级单注奖金计算的期望收益，一、二等奖没有人中奖时使用估算值
+    fn recorded_expected_value(&self, record: &PrBusinessObj) -> Option<f64> {
+        if record.prize_grades.is_empty() {
+            return None;
+        }
+        let expected_value = prize_grade_probabilities()
+            .into_iter()
+            .map(|(grade, probability)| {
+                let money = match (record.prize_money(grade), grade) {
+                    (0, PrizeGrade::First) => self.first_prize_estimate,
+                    (0, PrizeGrade::Second) => self.second_prize_estimate,
+                    (money, _) => money as f64,
+                };
+                money * probability
+            })
+            .sum();
+        Some(expected_value)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "每期期望收益")]
+pub struct DrawExpectedValue {
+    #[serde(rename = "中奖期号")]
+    code: String,
+    #[serde(rename = "中奖日期")]
+    date: NaiveDate,
+    #[serde(rename = "期望收益")]
+    expected_value: ExpectedValue,
+    #[serde(rename = "按实际奖金计算的期望收益")]
+    recorded_expected_value: Option<f64>,
+}
+
+impl DrawExpectedValue {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    /// 开奖前按照销售额与奖池估算的期望收益
+    pub fn expected_value(&self) -> &ExpectedValue {
+        &self.expected_value
+    }
+
+    /// 开奖后按照公布的各奖级单注奖金计算的期望收益，没有奖级详情时为 `None`
+    pub fn recorded_expected_value(&self) -> Option<f64> {
+        self.recorded_expected_value
+    }
+}
+
+/// 历史上实际派发的二等奖单注奖金与估算值之比的中位数，没有二等奖记录时为 1
+fn second_prize_calibration(draw_expected_values: &[(&PrBusinessObj, ExpectedValue)]) -> f64 {
+    let ratios = draw_expected_values
+        .iter()
+        .filter(|(_, expected_value)| expected_value.second_prize_estimate > 0.0)
+        .filter_map(|(record, expected_value)| {
+            record
+                .prize_grades
+                .iter()
+                .find(|detail| {
+                    detail.grade == PrizeGrade::Second
+                        && detail.winning_tickets > 0
+                        && detail.money > 0
+                })
+                .map(|detail| detail.money as f64 / expected_value.second_prize_estimate)
+        })
+        .sorted_by(f64::total_cmp)
+        .collect::<Vec<f64>>();
+    match ratios.len() {
+        0 => 1.0,
+        len if len % 2 == 1 => ratios[len / 2],
+        len => (ratios[len / 2 - 1] + ratios[len / 2]) / 2.0,
+    }
+}
+
+/// 计算每期一注单式号码的期望收益，奖池使用上一期开奖后滚入的金额，并与按照实际奖金计算的期望收益对照。
+/// 下一期的期望收益使用最近一期的销售额与奖池估算，二等奖按照历史实际奖金校准
+pub struct ExpectedValueProcessor;
+
+#[async_trait::async_trait]
+impl Processor for ExpectedValueProcessor {
+    fn name(&self) -> &str {
+        "ExpectedValueProcessor"
+    }
+
+    async fn execute(&mut self, context: &mut ProcessorContext) -> Result<(), Error> {
+        let records = context
+            .get_prize_records()
+            .iter()
+            .sorted_by_key(|record| record.date)
+            .rev()
+            .collect::<Vec<&PrBusinessObj>>();
+        let estimates = records
+            .iter()
+            .tuple_windows()
+            .map(|(current, previous)| {
+                (
+                    *current,
+                    ExpectedValue::new(current.total_tickets * TICKET_PRICE, previous.pool_money),
+                )
+            })
+            .collect::<Vec<(&PrBusinessObj, ExpectedValue)>>();
+        let calibration = second_prize_calibration(&estimates);
+        let draw_expected_values = estimates
+            .into_iter()
+            .map(|(record, expected_value)| DrawExpectedValue {
+                code: record.code.clone(),
+                date: record.date,
+                recorded_expected_value: expected_value.recorded_expected_value(record),
+                expected_value,
+            })
+            .collect::<Vec<DrawExpectedValue>>();
+        if let Some(best) = draw_expected_values.iter().max_by(|a, b| {
+            a.expected_value
+                .expected_value
+                .total_cmp(&b.expected_value.expected_value)
+        }) {
+            info!(
+                "共计算 {} 期期望收益，最高为第 {} 期的 {:.3} 元",
+                draw_expected_values.len(),
+                best.code,
+                best.expected_value.expected_value
+            );
+        }
+        if let Some(latest) = records.first() {
+            let next_draw_expected_value = ExpectedValue::calibrated(
+                latest.total_tickets * TICKET_PRICE,
+                latest.pool_money,
+                calibration,
+            );
+            info!(
+                "按照第 {} 期的销售额与奖池估算，二等奖校准系数 {:.3}，下一期单注期望收益为 {:.3} 元",
+                latest.code, calibration, next_draw_expected_value.expected_value
+            );
+            context.set_attribute(&NEXT_DRAW_EXPECTED_VALUE, next_draw_expected_value);
+        }
+        context.set_attribute(&EXPECTED_VALUES, draw_expected_values);
+        Ok(())
+    }
+}
@@ -1,20 +1,37 @@
 use crate::context::ProcessorContext;
+use crate::ensemble::Ensemble;
 use crate::error::Error;
-use crate::{
-    FinalProcessorChainResult, Processor,
-    BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP, BLUE_BALL_FOLLOWING_OCCURRENCES, FINAL_PROCESSOR_CHAIN_RESULTS,
-};
-use itertools::Itertools;
-use ssq_tool_domain::{BlueBall, RedBall};
+use crate::strategy::{FollowingBlueFpStrategy, ScoredTicket, Strategy};
+use crate::{FINAL_PROCESSOR_CHAIN_RESULTS, FinalProcessorChainResult, Processor, SCORED_TICKETS};
 use tracing::info;
 
 pub struct FinalResultsProcessor {
     final_result_size: usize,
+    strategies: Vec<(Box<dyn Strategy + Send>, f64)>,
+    ensemble: Ensemble,
 }
 
 impl FinalResultsProcessor {
+    /// 使用最近一期蓝球的后续蓝球与其红球频繁项集生成最终结果
     pub fn new(final_result_size: usize) -> Self {
-        Self { final_result_size }
+        Self::with_strategies(
+            final_result_size,
+            vec![(Box::new(FollowingBlueFpStrategy), 1.0)],
+            Ensemble::default(),
+        )
+    }
+
+    /// 用 `ensemble` 融合多个带权重的策略生成最终结果
+    pub fn with_strategies(
+        final_result_size: usize,
+        strategies: Vec<(Box<dyn Strategy + Send>, f64)>,
+        ensemble: Ensemble,
+    ) -> Self {
+        Self {
+            final_result_size,
+            strategies,
+            ensemble,
+        }
     }
 }
 
@@ -25,85 +42,27 @@ impl Processor for FinalResultsProcessor {
     }
 
     async fn execute(&mut self, context: &mut ProcessorContext) -> Result<(), Error> {
-        // 对中奖记录按照从最近到最早进行排序
-        let sorted_blue_balls = context
-            .get_prize_records()
-            .iter()
-            .sorted_by_key(|record| record.date)
-            .rev()
-            .map(|record| record.blue_ball)
-            .collect::<Vec<BlueBall>>();
-
-        let blue_ball_following_occurrences = context
-            .get_attribute(&BLUE_BALL_FOLLOWING_OCCURRENCES)
-            .ok_or(Error::ContextAttrNotExist(
-                BLUE_BALL_FOLLOWING_OCCURRENCES.to_string(),
-            ))?;
-        // 最近一次中奖蓝球
-        let last_occur_blue_ball = sorted_blue_balls[0];
-        let following_blue_ball_occurrences =
-            blue_ball_following_occurrences.get(&last_occur_blue_ball);
-        info!(
-            "最后一次中奖的篮球 {last_occur_blue_ball} 后续可能出现的蓝球情况：{following_blue_ball_occurrences:?}"
-        );
-        let mut result_blue_balls = Vec::<BlueBall>::new();
-        if let Some(possible_blue_balls) = following_blue_ball_occurrences {
-            // 按照出现次数从大到小排列
-            possible_blue_balls
-                .iter()
-                .sorted_by_key(|kv| kv.1)
-                .rev()
-                .for_each(|(ball, _)| {
-                    result_blue_balls.push(*ball);
-                });
+        if self.strategies.is_empty() {
+            return Err(Error::InvalidProcessorConfig(
+                "至少需要一个预测策略".to_string(),
+            ));
         }
+        let scored_tickets =
+            self.ensemble
+                .generate(context, &mut self.strategies, self.final_result_size)?;
         info!(
-            "最后一次中奖的篮球 {last_occur_blue_ball} 后续可能出现的蓝球序列：{result_blue_balls:?}"
+            "按照 {:?} 融合 {} 个策略，共得到 {} 注候选号码",
+            self.ensemble.method(),
+            self.strategies.len(),
+            scored_tickets.len()
         );
-        let result_blue_balls = result_blue_balls
-            .into_iter()
-            .unique()
+        let final_results = scored_tickets
+            .iter()
             .take(self.final_result_size)
-            .collect::<Vec<BlueBall>>();
-        let red_ball_occurrence_fp = context
-            .get_attribute(&BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP)
-            .ok_or(Error::ContextAttrNotExist(
-                BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP.to_string(),
-            ))?;
-
-        let mut final_results = Vec::<FinalProcessorChainResult>::new();
-        result_blue_balls.iter().try_for_each(|blue_ball| {
-            let top_related_red_balls = red_ball_occurrence_fp
-                .get(blue_ball)
-                .map(|red_ball_fp_result| {
-                    let red_ball_occurrence_fp_pattern = red_ball_fp_result.frequent_patterns();
-                    let red_ball_occurrence_fp_ave_support = red_ball_occurrence_fp_pattern
-                        .iter()
-                        .map(|pattern| pattern.1)
-                        .sum::<usize>()
-                        / red_ball_occurrence_fp_pattern.len();
-                    red_ball_occurrence_fp_pattern
-                        .iter()
-                        .sorted_by_key(|pattern| pattern.1)
-                        .rev()
-                        .filter(|pattern| pattern.1 > red_ball_occurrence_fp_ave_support)
-                        .take(6)
-                        .flat_map(|pattern| &pattern.0)
-                        .copied()
-                        .unique()
-                        .sorted()
-                        .take(6)
-                        .collect::<Vec<RedBall>>()
-                })
-                .ok_or(Error::OtherFailure(format!(
-                    "没有找到蓝球出现情况：{blue_ball}"
-                )))?;
-            let one_result =
-                FinalProcessorChainResult::new(*blue_ball, top_related_red_balls.to_vec());
-            final_results.push(one_result);
-
-            Ok::<(), Error>(())
-        })?;
+            .map(ScoredTicket::ticket)
+            .cloned()
+            .collect::<Vec<FinalProcessorChainResult>>();
+        context.set_attribute(&SCORED_TICKETS, scored_tickets);
         context.set_attribute(&FINAL_PROCESSOR_CHAIN_RESULTS, final_results);
         Ok(())
     }
@@ -49,7 +49,7 @@ impl Processor for GenerateNormalizeDataProcessor {
                     .iter()
                     .map(|pattern| pattern.1)
                     .sum::<usize>()
-                    / red_ball_occurrence_fp_pattern.len();
+                    / red_ball_occurrence_fp_pattern.len().max(1);
                 red_ball_fp_result.
                     frequent_patterns().iter()
                     .sorted_by_key(|pattern| pattern.1)
@@ -0,0 +1,219 @@
+use crate::ball_occurrence::collect_balls_occur_seq;
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::{BALL_TEMPERATURE, Processor};
+use derive_more::Display;
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::{Ball, BlueBall, PrBusinessObj, RedBall};
+use strum::{EnumCount, IntoEnumIterator};
+use tracing::debug;
+
+/// 每期开出的红球个数
+const RED_BALLS_PER_DRAW: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "冷热状态")]
+pub enum BallTemperature {
+    #[display("热")]
+    #[serde(rename = "热")]
+    Hot,
+    #[display("温")]
+    #[serde(rename = "温")]
+    Warm,
+    #[display("冷")]
+    #[serde(rename = "冷")]
+    Cold,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "窗口出现频率")]
+pub struct BallFrequency {
+    #[serde(rename = "双色球")]
+    ball: Ball,
+    #[serde(rename = "出现次数")]
+    occurrence_count: usize,
+    #[serde(rename = "期望出现次数")]
+    expected_occurrence_count: f64,
+    #[serde(rename = "出现频率比")]
+    frequency_ratio: f64,
+    #[serde(rename = "冷热状态")]
+    temperature: BallTemperature,
+}
+
+impl BallFrequency {
+    pub fn ball(&self) -> Ball {
+        self.ball
+    }
+
+    pub fn occurrence_count(&self) -> usize {
+        self.occurrence_count
+    }
+
+    pub fn expected_occurrence_count(&self) -> f64 {
+        self.expected_occurrence_count
+    }
+
+    /// 实际出现次数与期望出现次数的比值
+    pub fn frequency_ratio(&self) -> f64 {
+        self.frequency_ratio
+    }
+
+    pub fn temperature(&self) -> BallTemperature {
+        self.temperature
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "冷热窗口")]
+pub struct BallTemperatureWindow {
+    #[serde(rename = "窗口大小")]
+    window_size: usize,
+    /// 按照双色球排序，蓝球在前、红球在后
+    #[serde(rename = "出现频率")]
+    frequencies: Vec<BallFrequency>,
+}
+
+impl BallTemperatureWindow {
+    /// 窗口内实际参与统计的中奖记录数
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    pub fn frequencies(&self) -> &[BallFrequency] {
+        &self.frequencies
+    }
+
+    pub fn temperature(&self, ball: &Ball) -> Option<BallTemperature> {
+        self.frequencies
+            .binary_search_by_key(ball, BallFrequency::ball)
+            .ok()
+            .map(|index| self.frequencies[index].temperature)
+    }
+
+    /// 取得窗口内处于指定冷热状态的所有双色球
+    pub fn balls_with_temperature(&self, temperature: BallTemperature) -> Vec<Ball> {
+        self.frequencies
+            .iter()
+            .filter(|frequency| frequency.temperature == temperature)
+            .map(BallFrequency::ball)
+            .collect()
+    }
+}
+
+/// 按照最近若干期的窗口把每个红球和蓝球标记为热、温、冷
+pub struct HotColdBallProcessor {
+    windows: Vec<usize>,
+    hot_threshold: f64,
+    cold_threshold: f64,
+}
+
+impl HotColdBallProcessor {
+    /// `hot_threshold` 与 `cold_threshold` 是实际出现次数与期望出现次数的比值，
+    /// 比值不低于 `hot_threshold` 为热，不高于 `cold_threshold` 为冷，其余为温
+    pub fn new(windows: Vec<usize>, hot_threshold: f64, cold_threshold: f64) -> Self {
+        Self {
+            windows,
+            hot_threshold,
+            cold_threshold,
+        }
+    }
+
+    fn temperature_of(&self, frequency_ratio: f64) -> BallTemperature {
+        if frequency_ratio >= self.hot_threshold {
+            BallTemperature::Hot
+        } else if frequency_ratio <= self.cold_threshold {
+            BallTemperature::Cold
+        } else {
+            BallTemperature::Warm
+        }
+    }
+
+    fn calculate_window(&self, window_records: &[&PrBusinessObj]) -> BallTemperatureWindow {
+        let window_size = window_records.len();
+        let balls_occur_seq = collect_balls_occur_seq(window_records.iter().copied());
+        let red_balls = RedBall::iter().map(|ball| {
+            let expected = window_size as f64 * RED_BALLS_PER_DRAW as f64 / RedBall::COUNT as f64;
+            (Ball::Red(ball), expected)
+        });
+        let blue_balls = BlueBall::iter().map(|ball| {
+            let expected = window_size as f64 / BlueBall::COUNT as f64;
+            (Ball::Blue(ball), expected)
+        });
+        let frequencies = red_balls
+            .chain(blue_balls)
+            .map(|(ball, expected_occurrence_count)| {
+                let occurrence_count = balls_occur_seq.get(&ball).map_or(0, Vec::len);
+                let frequency_ratio = if expected_occurrence_count > 0.0 {
+                    occurrence_count as f64 / expected_occurrence_count
+                } else {
+                    0.0
+                };
+                BallFrequency {
+                    ball,
+                    occurrence_count,
+                    expected_occurrence_count,
+                    frequency_ratio,
+                    temperature: self.temperature_of(frequency_ratio),
+                }
+            })
+            .sorted_by_key(BallFrequency::ball)
+            .collect::<Vec<BallFrequency>>();
+        BallTemperatureWindow {
+            window_size,
+            frequencies,
+        }
+    }
+}
+
+impl Default for HotColdBallProcessor {
+    fn default() -> Self {
+        Self::new(vec![10, 30, 100], 1.2, 0.8)
+    }
+}
+
+#[async_trait::async_trait]
+impl Processor for HotColdBallProcessor {
+    fn name(&self) -> &str {
+        "HotColdBallProcessor"
+    }
+
+    async fn execute(&mut self, context: &mut ProcessorContext) -> Result<(), Error> {
+        if self.windows.is_empty() || self.windows.contains(&0) {
+            return Err(Error::InvalidProcessorConfig(format!(
+                "冷热窗口必须为正数：{:?}",
+                self.windows
+            )));
+        }
+        if self.cold_threshold >= self.hot_threshold {
+            return Err(Error::InvalidProcessorConfig(format!(
+                "冷号阈值 {} 必须小于热号阈值 {}",
+                self.cold_threshold, self.hot_threshold
+            )));
+        }
+        // 对中奖记录按照从最近到最早进行排序
+        let sorted_records = context
+            .get_prize_records()
+            .iter()
+            .sorted_by_key(|record| record.date)
+            .rev()
+            .collect::<Vec<&PrBusinessObj>>();
+        let temperature_windows = self
+            .windows
+            .iter()
+            .map(|window| {
+                let window_records = &sorted_records[..(*window).min(sorted_records.len())];
+                let temperature_window = self.calculate_window(window_records);
+                debug!(
+                    "最近 {window} 期冷热情况：热号 {:?}，冷号 {:?}",
+                    temperature_window.balls_with_temperature(BallTemperature::Hot),
+                    temperature_window.balls_with_temperature(BallTemperature::Cold)
+                );
+                temperature_window
+            })
+            .collect::<Vec<BallTemperatureWindow>>();
+        context.set_attribute(&BALL_TEMPERATURE, temperature_windows);
+        Ok(())
+    }
+}
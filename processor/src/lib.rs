@@ -1,6 +1,20 @@
+use crate::association_rule::AssociationRule;
+use crate::bayesian_ball_probability::BallPosteriors;
+use crate::blue_ball_markov::BlueBallMarkovModel;
 use crate::context::{OccurrenceDetail, ProcessorContext, ProcessorContextAttr};
 use crate::error::Error;
+use crate::expected_value::{DrawExpectedValue, ExpectedValue};
+use crate::hot_cold_ball::BallTemperatureWindow;
+use crate::portfolio::Portfolio;
+use crate::progress::ProgressEvent;
+use crate::randomness_test::RandomnessTestResult;
+use crate::red_ball_shape::{DrawRedBallShape, RedBallShapeDistribution};
+use crate::red_ball_structure::{DrawRedBallStructure, RedBallStructureDistribution};
+use crate::strategy::ScoredTicket;
+use crate::ticket_filter::{FilterReport, HistoricalFilterResult};
 use derive_more::Display;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 use ::fp_growth::algorithm::FPResult;
 use ssq_tool_domain::{Ball, BlueBall, RedBall};
@@ -8,20 +22,59 @@ use std::{
     borrow::Borrow,
     collections::HashMap,
     sync::{Arc, LazyLock},
+    time::Duration,
 };
+use tokio::time::Instant;
 use tracing::debug;
 
+pub mod association_rule;
+pub mod backtest;
 pub mod ball_occurrence;
 pub mod ball_relationship_fp;
+pub mod bayesian_ball_probability;
+pub mod blue_ball_markov;
 pub mod blue_ball_occurrence_fp;
 pub mod context;
+pub mod ensemble;
 pub mod error;
+pub mod expected_value;
 pub mod final_result;
 pub mod generate_normalize_data;
+pub mod hot_cold_ball;
+pub mod portfolio;
+pub mod progress;
+pub mod random_baseline;
+pub mod randomness_test;
+pub mod red_ball_shape;
+pub mod red_ball_structure;
+mod statistics;
+pub mod strategy;
+pub mod ticket_filter;
+pub mod view;
+pub mod wheel;
 
 pub static BALL_OCCURRENCE: LazyLock<Arc<ProcessorContextAttr<HashMap<Ball, OccurrenceDetail>>>> =
     LazyLock::new(|| Arc::new(ProcessorContextAttr::new("BALL_OCCURRENCE")));
 
+pub static RED_BALL_STRUCTURES: LazyLock<ProcessorContextAttr<Vec<DrawRedBallStructure>>> =
+    LazyLock::new(|| ProcessorContextAttr::new("RED_BALL_STRUCTURES"));
+
+pub static RED_BALL_STRUCTURE_DISTRIBUTION: LazyLock<
+    ProcessorContextAttr<RedBallStructureDistribution>,
+> = LazyLock::new(|| ProcessorContextAttr::new("RED_BALL_STRUCTURE_DISTRIBUTION"));
+
+pub static BALL_TEMPERATURE: LazyLock<ProcessorContextAttr<Vec<BallTemperatureWindow>>> =
+    LazyLock::new(|| ProcessorContextAttr::new("BALL_TEMPERATURE"));
+
+pub static RED_BALL_SHAPES: LazyLock<ProcessorContextAttr<Vec<DrawRedBallShape>>> =
+    LazyLock::new(|| ProcessorContextAttr::new("RED_BALL_SHAPES"));
+
+pub static RED_BALL_SHAPE_DISTRIBUTION: LazyLock<ProcessorContextAttr<RedBallShapeDistribution>> =
+    LazyLock::new(|| ProcessorContextAttr::new("RED_BALL_SHAPE_DISTRIBUTION"));
+
+pub static RANDOMNESS_TEST_RESULTS: LazyLock<ProcessorContextAttr<Vec<RandomnessTestResult>>> =
+    LazyLock::new(|| ProcessorContextAttr::new("RANDOMNESS_TEST_RESULTS"));
+
 pub static BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP: LazyLock<
     ProcessorContextAttr<HashMap<BlueBall, FPResult<RedBall>>>,
 > = LazyLock::new(|| ProcessorContextAttr::new("BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP"));
@@ -30,18 +83,50 @@ pub static RED_BALL_AND_RED_BALL_RELATIONSHIP_FP: LazyLock<
     ProcessorContextAttr<HashMap<RedBall, FPResult<RedBall>>>,
 > = LazyLock::new(|| ProcessorContextAttr::new("RED_BALL_AND_RED_BALL_RELATIONSHIP_FP"));
 
+pub static BALL_ASSOCIATION_RULES: LazyLock<ProcessorContextAttr<Vec<AssociationRule>>> =
+    LazyLock::new(|| ProcessorContextAttr::new("BALL_ASSOCIATION_RULES"));
+
 pub static BLUE_BALL_FOLLOWING_OCCURRENCES: LazyLock<
     ProcessorContextAttr<HashMap<BlueBall, HashMap<BlueBall, usize>>>,
 > = LazyLock::new(|| ProcessorContextAttr::new("BLUE_BALL_NEXT_OCCURRENCES"));
 
+pub static BLUE_BALL_MARKOV_MODEL: LazyLock<ProcessorContextAttr<BlueBallMarkovModel>> =
+    LazyLock::new(|| ProcessorContextAttr::new("BLUE_BALL_MARKOV_MODEL"));
+
 pub static FINAL_PROCESSOR_CHAIN_RESULTS: LazyLock<
     ProcessorContextAttr<Vec<FinalProcessorChainResult>>,
 > = LazyLock::new(|| ProcessorContextAttr::new("CUSTOMIZE_SUMMARIES"));
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Display)]
+pub static SCORED_TICKETS: LazyLock<ProcessorContextAttr<Vec<ScoredTicket>>> =
+    LazyLock::new(|| ProcessorContextAttr::new("SCORED_TICKETS"));
+
+pub static TICKET_FILTER_REPORT: LazyLock<ProcessorContextAttr<FilterReport>> =
+    LazyLock::new(|| ProcessorContextAttr::new("TICKET_FILTER_REPORT"));
+
+pub static TICKET_FILTER_HISTORY: LazyLock<ProcessorContextAttr<Vec<HistoricalFilterResult>>> =
+    LazyLock::new(|| ProcessorContextAttr::new("TICKET_FILTER_HISTORY"));
+
+pub static PORTFOLIO: LazyLock<ProcessorContextAttr<Portfolio>> =
+    LazyLock::new(|| ProcessorContextAttr::new("PORTFOLIO"));
+
+pub static EXPECTED_VALUES: LazyLock<ProcessorContextAttr<Vec<DrawExpectedValue>>> =
+    LazyLock::new(|| ProcessorContextAttr::new("EXPECTED_VALUES"));
+
+pub static NEXT_DRAW_EXPECTED_VALUE: LazyLock<ProcessorContextAttr<ExpectedValue>> =
+    LazyLock::new(|| ProcessorContextAttr::new("NEXT_DRAW_EXPECTED_VALUE"));
+
+pub static BAYESIAN_BALL_PROBABILITIES: LazyLock<ProcessorContextAttr<BallPosteriors>> =
+    LazyLock::new(|| ProcessorContextAttr::new("BAYESIAN_BALL_PROBABILITIES"));
+
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Display, Serialize, Deserialize, JsonSchema,
+)]
 #[display("红球：{red_balls:?}; 蓝球：{blue_ball}")]
+#[serde(rename = "预测结果")]
 pub struct FinalProcessorChainResult {
+    #[serde(rename = "蓝球")]
     blue_ball: BlueBall,
+    #[serde(rename = "红球")]
     red_balls: Vec<RedBall>,
 }
 
@@ -74,6 +159,8 @@ pub trait Processor {
 pub struct ProcessorChain {
     name: String,
     processors: Vec<Box<dyn Processor + Send>>,
+    processor_timeout: Option<Duration>,
+    timeout: Option<Duration>,
 }
 
 impl ProcessorChain {
@@ -81,6 +168,8 @@ impl ProcessorChain {
         Self {
             name: name.borrow().to_owned(),
             processors: Default::default(),
+            processor_timeout: None,
+            timeout: None,
         }
     }
 
@@ -90,22 +179,134 @@ impl ProcessorChain {
         self
     }
 
-    /// Execute all the processors in the chain
+    /// Limit the execution time of each processor, `None` means no limit
+    pub fn processor_timeout(mut self, processor_timeout: Option<Duration>) -> Self {
+        self.processor_timeout = processor_timeout;
+        self
+    }
+
+    /// Limit the execution time of the whole chain, `None` means no limit
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Execute all the processors in the chain. The chain stops with `Error::Cancelled` once the
+    /// context is cancelled, and with a timeout error once a processor or the whole chain runs
+    /// past its time limit. Cancellation is cooperative: a running processor is only interrupted
+    /// at its await points or when it checks `ProcessorContext::is_cancelled`, otherwise the
+    /// chain stops right after it. The attributes set by the finished processors stay in the
+    /// context as partial results
     pub async fn execute<'a>(&mut self, context: &mut ProcessorContext<'a>) -> Result<(), Error> {
-        for processor in self.processors.iter_mut() {
+        let outer_deadline = context.deadline();
+        let chain_deadline = earliest(
+            outer_deadline,
+            self.timeout.map(|timeout| Instant::now() + timeout),
+        );
+        let result = self.execute_processors(context, chain_deadline).await;
+        context.set_deadline(outer_deadline);
+        result
+    }
+
+    async fn execute_processors(
+        &mut self,
+        context: &mut ProcessorContext<'_>,
+        chain_deadline: Option<Instant>,
+    ) -> Result<(), Error> {
+        let total = self.processors.len();
+        for (index, processor) in self.processors.iter_mut().enumerate() {
+            if let Some(e) =
+                interruption(context, &self.name, processor.name(), chain_deadline, None)
+            {
+                return Err(e);
+            }
+            context.report_progress(ProgressEvent::new(
+                &self.name,
+                index,
+                Some(total),
+                format!("开始执行: {}", processor.name()),
+            ));
+            let processor_deadline = self
+                .processor_timeout
+                .map(|timeout| (Instant::now() + timeout, timeout));
+            let deadline = earliest(
+                chain_deadline,
+                processor_deadline.map(|(deadline, _)| deadline),
+            );
+            context.set_deadline(deadline);
+            let cancellation_token = context.cancellation_token().clone();
             debug!("开始执行: {}", processor.name());
-            processor.execute(context).await?;
+            // 被取消或者超时时不再等待执行器，由下面的检查返回相应的错误
+            let result = tokio::select! {
+                result = processor.execute(context) => result,
+                _ = cancellation_token.cancelled() => Ok(()),
+                _ = sleep_until(deadline) => Ok(()),
+            };
+            if let Some(e) = interruption(
+                context,
+                &self.name,
+                processor.name(),
+                chain_deadline,
+                processor_deadline,
+            ) {
+                return Err(e);
+            }
+            result?;
             debug!("成功执行: {}", processor.name());
         }
+        context.report_progress(ProgressEvent::new(
+            &self.name,
+            total,
+            Some(total),
+            "执行完成",
+        ));
         Ok(())
     }
 }
 
+fn earliest(first: Option<Instant>, second: Option<Instant>) -> Option<Instant> {
+    first.into_iter().chain(second).min()
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// 执行器链被取消或者超过了时间限制时返回相应的错误，两个时间限制都超过时返回先到期的那个
+fn interruption(
+    context: &ProcessorContext,
+    chain_name: &str,
+    processor_name: &str,
+    chain_deadline: Option<Instant>,
+    processor_deadline: Option<(Instant, Duration)>,
+) -> Option<Error> {
+    let now = Instant::now();
+    if context.cancellation_token().is_cancelled() {
+        return Some(Error::Cancelled(format!(
+            "{chain_name} 被取消，没有执行完 {processor_name}"
+        )));
+    }
+    if let Some((deadline, timeout)) = processor_deadline
+        && now >= deadline
+        && chain_deadline.is_none_or(|chain_deadline| deadline <= chain_deadline)
+    {
+        return Some(Error::ProcessorTimeout(processor_name.to_string(), timeout));
+    }
+    chain_deadline
+        .is_some_and(|chain_deadline| now >= chain_deadline)
+        .then(|| Error::ChainTimeout(chain_name.to_string()))
+}
+
 impl From<Vec<Box<dyn Processor + Send>>> for ProcessorChain {
     fn from(processors: Vec<Box<dyn Processor + Send>>) -> Self {
         Self {
             processors,
             name: "ProcessorChain".to_string(),
+            processor_timeout: None,
+            timeout: None,
         }
     }
 }
@@ -120,3 +321,154 @@ impl Processor for ProcessorChain {
         self.execute(context).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_util::sync::CancellationToken;
+
+    static STEP: LazyLock<ProcessorContextAttr<usize>> =
+        LazyLock::new(|| ProcessorContextAttr::new("STEP"));
+
+    /// 设置属性后立即结束的执行器
+    struct StepProcessor(usize);
+
+    #[async_trait::async_trait]
+    impl Processor for StepProcessor {
+        fn name(&self) -> &str {
+            "StepProcessor"
+        }
+
+        async fn execute(&mut self, context: &mut ProcessorContext) -> Result<(), Error> {
+            context.set_attribute(&STEP, self.0);
+            Ok(())
+        }
+    }
+
+    /// 一直占用 CPU、从不让出执行权的执行器，只能通过检查取消停止
+    struct BusyProcessor;
+
+    #[async_trait::async_trait]
+    impl Processor for BusyProcessor {
+        fn name(&self) -> &str {
+            "BusyProcessor"
+        }
+
+        async fn execute(&mut self, context: &mut ProcessorContext) -> Result<(), Error> {
+            loop {
+                context.check_cancelled(self.name())?;
+            }
+        }
+    }
+
+    fn chain() -> ProcessorChain {
+        ProcessorChain::new("test_chain")
+            .add_processor(Box::new(StepProcessor(1)))
+            .add_processor(Box::new(BusyProcessor))
+            .add_processor(Box::new(StepProcessor(2)))
+    }
+
+    #[test]
+    fn earliest_picks_the_earlier_deadline() {
+        let first = Instant::now();
+        let second = first + Duration::from_secs(1);
+        assert_eq!(earliest(None, None), None);
+        assert_eq!(earliest(Some(second), None), Some(second));
+        assert_eq!(earliest(None, Some(first)), Some(first));
+        assert_eq!(earliest(Some(second), Some(first)), Some(first));
+    }
+
+    #[test]
+    fn interruption_reports_the_deadline_that_expired_first() {
+        let timeout = Duration::from_millis(1);
+        let first = Instant::now();
+        std::thread::sleep(Duration::from_millis(5));
+        let second = Instant::now();
+        let context = ProcessorContext::new(&[], 5);
+
+        assert!(interruption(&context, "链", "执行器", None, None).is_none());
+        assert!(
+            interruption(
+                &context,
+                "链",
+                "执行器",
+                Some(second + Duration::from_secs(60)),
+                Some((second + Duration::from_secs(60), timeout)),
+            )
+            .is_none()
+        );
+        assert!(matches!(
+            interruption(&context, "链", "执行器", Some(second), Some((first, timeout))),
+            Some(Error::ProcessorTimeout(name, _)) if name == "执行器"
+        ));
+        assert!(matches!(
+            interruption(&context, "链", "执行器", Some(first), Some((second, timeout))),
+            Some(Error::ChainTimeout(name)) if name == "链"
+        ));
+    }
+
+    #[test]
+    fn interruption_prefers_cancellation_over_timeouts() {
+        let deadline = Instant::now();
+        let cancellation_token = CancellationToken::new();
+        cancellation_token.cancel();
+        let context = ProcessorContext::new(&[], 5).with_cancellation_token(cancellation_token);
+        assert!(matches!(
+            interruption(
+                &context,
+                "链",
+                "执行器",
+                Some(deadline),
+                Some((deadline, Duration::ZERO)),
+            ),
+            Some(Error::Cancelled(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn processor_timeout_interrupts_busy_processor_and_keeps_partial_results() {
+        let mut context = ProcessorContext::new(&[], 5);
+        let timeout = Duration::from_millis(50);
+        let result = chain()
+            .processor_timeout(Some(timeout))
+            .timeout(Some(Duration::from_secs(60)))
+            .execute(&mut context)
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::ProcessorTimeout(name, limit)) if name == "BusyProcessor" && limit == timeout
+        ));
+        assert_eq!(context.get_attribute(&STEP), Some(&1));
+        assert_eq!(context.deadline(), None);
+    }
+
+    #[tokio::test]
+    async fn chain_timeout_wins_when_it_expires_before_processor_timeout() {
+        let mut context = ProcessorContext::new(&[], 5);
+        let result = chain()
+            .processor_timeout(Some(Duration::from_secs(60)))
+            .timeout(Some(Duration::from_millis(50)))
+            .execute(&mut context)
+            .await;
+        assert!(matches!(result, Err(Error::ChainTimeout(name)) if name == "test_chain"));
+        assert_eq!(context.get_attribute(&STEP), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn cancellation_interrupts_busy_processor_and_keeps_partial_results() {
+        let cancellation_token = CancellationToken::new();
+        let mut context =
+            ProcessorContext::new(&[], 5).with_cancellation_token(cancellation_token.clone());
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            cancellation_token.cancel();
+        });
+        let result = chain()
+            .timeout(Some(Duration::from_secs(60)))
+            .execute(&mut context)
+            .await;
+        canceller.join().unwrap();
+        assert!(matches!(result, Err(Error::Cancelled(_))));
+        assert_eq!(context.get_attribute(&STEP), Some(&1));
+    }
+}
@@ -0,0 +1,301 @@
+use crate::backtest::TICKET_PRICE;
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::strategy::ScoredTicket;
+use crate::{
+    FINAL_PROCESSOR_CHAIN_RESULTS, FinalProcessorChainResult, PORTFOLIO, Processor, SCORED_TICKETS,
+};
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::{Ball, RedBall};
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use tracing::info;
+
+/// 组合优化目标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PortfolioObjective {
+    /// 覆盖尽可能多的不同红球
+    MaxRedCoverage,
+    /// 候选号码得分之和尽可能高
+    #[default]
+    MaxExpectedScore,
+    /// 号码之间共有的红球尽可能少
+    MinOverlap,
+}
+
+/// 在预算内从候选号码中选出一组号码，候选号码按照得分从高到低排列时，
+/// 同等条件下优先选择排名靠前的号码
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "PortfolioOptimizerConfig")]
+pub struct PortfolioOptimizer {
+    /// 预算，单位元
+    budget: u64,
+    objective: PortfolioObjective,
+    /// 任意两注号码之间最多共有的红球个数
+    max_overlap: Option<usize>,
+    /// 每注号码都必须包含的胆码，包含多个蓝球胆码时蓝球只需是其中之一
+    bankers: Vec<Ball>,
+}
+
+/// 配置文件中的组合优化参数，经过 [`PortfolioOptimizer::new`] 校验后才能使用
+#[derive(Deserialize, JsonSchema)]
+struct PortfolioOptimizerConfig {
+    budget: u64,
+    #[serde(default)]
+    objective: PortfolioObjective,
+    #[serde(default)]
+    max_overlap: Option<usize>,
+    #[serde(default)]
+    bankers: Vec<Ball>,
+}
+
+impl TryFrom<PortfolioOptimizerConfig> for PortfolioOptimizer {
+    type Error = Error;
+
+    fn try_from(config: PortfolioOptimizerConfig) -> Result<Self, Self::Error> {
+        let optimizer = Self::new(config.budget, config.objective)?.bankers(config.bankers);
+        Ok(match config.max_overlap {
+            Some(max_overlap) => optimizer.max_overlap(max_overlap),
+            None => optimizer,
+        })
+    }
+}
+
+impl PortfolioOptimizer {
+    pub fn new(budget: u64, objective: PortfolioObjective) -> Result<Self, Error> {
+        if budget < TICKET_PRICE {
+            return Err(Error::InvalidProcessorConfig(format!(
+                "预算 {budget} 元不足以购买一注号码"
+            )));
+        }
+        Ok(Self {
+            budget,
+            objective,
+            max_overlap: None,
+            bankers: vec![],
+        })
+    }
+
+    pub fn max_overlap(mut self, max_overlap: usize) -> Self {
+        self.max_overlap = Some(max_overlap);
+        self
+    }
+
+    pub fn bankers(mut self, bankers: Vec<Ball>) -> Self {
+        self.bankers = bankers;
+        self
+    }
+
+    pub fn budget(&self) -> u64 {
+        self.budget
+    }
+
+    pub fn objective(&self) -> PortfolioObjective {
+        self.objective
+    }
+
+    /// 预算能够购买的注数
+    pub fn ticket_count(&self) -> usize {
+        (self.budget / TICKET_PRICE) as usize
+    }
+
+    fn contains_bankers(&self, ticket: &FinalProcessorChainResult) -> bool {
+        let mut blue_bankers = self
+            .bankers
+            .iter()
+            .filter_map(|ball| match ball {
+                Ball::Blue(blue_ball) => Some(blue_ball),
+                Ball::Red(_) => None,
+            })
+            .peekable();
+        let blue_matched = blue_bankers.peek().is_none()
+            || blue_bankers.any(|blue_ball| blue_ball == ticket.blue_ball());
+        blue_matched
+            && self.bankers.iter().all(|ball| match ball {
+                Ball::Red(red_ball) => ticket.red_ball().contains(red_ball),
+                Ball::Blue(_) => true,
+            })
+    }
+
+    /// 贪心地逐注选择对优化目标提升最大的号码
+    pub fn optimize(&self, candidates: &[ScoredTicket]) -> Portfolio {
+        let mut remaining = candidates
+            .iter()
+            .filter(|candidate| self.contains_bankers(candidate.ticket()))
+            .unique_by(|candidate| candidate.ticket())
+            .collect::<Vec<&ScoredTicket>>();
+        let eligible_count = remaining.len();
+        let mut selected = Vec::<ScoredTicket>::new();
+        let mut covered_red_balls = BTreeSet::<RedBall>::new();
+        while selected.len() < self.ticket_count() {
+            remaining.retain(|candidate| {
+                self.max_overlap.is_none_or(|max_overlap| {
+                    selected
+                        .iter()
+                        .all(|ticket| overlap(ticket.ticket(), candidate.ticket()) <= max_overlap)
+                })
+            });
+            // 相同条件下 `max_by` 返回最后一个，因此反向遍历以选择排名靠前的号码
+            let best = remaining
+                .iter()
+                .enumerate()
+                .rev()
+                .max_by(|(_, a), (_, b)| self.compare(a, b, &selected, &covered_red_balls))
+                .map(|(index, _)| index);
+            let Some(best) = best else {
+                break;
+            };
+            let ticket = remaining.remove(best);
+            covered_red_balls.extend(ticket.ticket().red_ball().iter().copied());
+            selected.push(ticket.clone());
+        }
+        let max_pairwise_overlap = selected
+            .iter()
+            .tuple_combinations()
+            .map(|(a, b)| overlap(a.ticket(), b.ticket()))
+            .max()
+            .unwrap_or(0);
+        let cost = selected.len() as u64 * TICKET_PRICE;
+        info!(
+            "预算 {} 元，满足胆码的候选号码 {eligible_count} 注，选出 {} 注，覆盖 {} 个红球",
+            self.budget,
+            selected.len(),
+            covered_red_balls.len()
+        );
+        Portfolio {
+            budget: self.budget,
+            cost,
+            total_score: selected.iter().map(ScoredTicket::score).sum(),
+            covered_red_balls: covered_red_balls.into_iter().collect(),
+            max_pairwise_overlap,
+            tickets: selected,
+        }
+    }
+
+    fn compare(
+        &self,
+        a: &ScoredTicket,
+        b: &ScoredTicket,
+        selected: &[ScoredTicket],
+        covered_red_balls: &BTreeSet<RedBall>,
+    ) -> Ordering {
+        let by_score = a.score().partial_cmp(&b.score()).unwrap_or(Ordering::Equal);
+        match self.objective {
+            PortfolioObjective::MaxExpectedScore => by_score,
+            PortfolioObjective::MaxRedCoverage => {
+                let new_red_balls = |ticket: &ScoredTicket| {
+                    ticket
+                        .ticket()
+                        .red_ball()
+                        .iter()
+                        .filter(|ball| !covered_red_balls.contains(ball))
+                        .count()
+                };
+                new_red_balls(a).cmp(&new_red_balls(b)).then(by_score)
+            }
+            PortfolioObjective::MinOverlap => {
+                let overlaps = |ticket: &ScoredTicket| {
+                    let overlaps = selected
+                        .iter()
+                        .map(|selected| overlap(selected.ticket(), ticket.ticket()))
+                        .collect::<Vec<usize>>();
+                    (
+                        overlaps.iter().max().copied().unwrap_or(0),
+                        overlaps.iter().sum::<usize>(),
+                    )
+                };
+                overlaps(b).cmp(&overlaps(a)).then(by_score)
+            }
+        }
+    }
+}
+
+/// 两注号码共有的红球个数
+fn overlap(a: &FinalProcessorChainResult, b: &FinalProcessorChainResult) -> usize {
+    a.red_ball()
+        .iter()
+        .filter(|ball| b.red_ball().contains(ball))
+        .count()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "投注组合")]
+pub struct Portfolio {
+    #[serde(rename = "预算")]
+    budget: u64,
+    #[serde(rename = "花费")]
+    cost: u64,
+    #[serde(rename = "总得分")]
+    total_score: f64,
+    #[serde(rename = "覆盖红球")]
+    covered_red_balls: Vec<RedBall>,
+    #[serde(rename = "最大重叠红球数")]
+    max_pairwise_overlap: usize,
+    #[serde(rename = "号码")]
+    tickets: Vec<ScoredTicket>,
+}
+
+impl Portfolio {
+    pub fn budget(&self) -> u64 {
+        self.budget
+    }
+
+    pub fn cost(&self) -> u64 {
+        self.cost
+    }
+
+    /// 选出号码的得分之和
+    pub fn total_score(&self) -> f64 {
+        self.total_score
+    }
+
+    /// 选出号码覆盖的所有红球
+    pub fn covered_red_balls(&self) -> &[RedBall] {
+        &self.covered_red_balls
+    }
+
+    /// 任意两注号码之间共有红球个数的最大值
+    pub fn max_pairwise_overlap(&self) -> usize {
+        self.max_pairwise_overlap
+    }
+
+    pub fn tickets(&self) -> &[ScoredTicket] {
+        &self.tickets
+    }
+}
+
+pub struct PortfolioProcessor {
+    optimizer: PortfolioOptimizer,
+}
+
+impl PortfolioProcessor {
+    pub fn new(optimizer: PortfolioOptimizer) -> Self {
+        Self { optimizer }
+    }
+}
+
+#[async_trait::async_trait]
+impl Processor for PortfolioProcessor {
+    fn name(&self) -> &str {
+        "PortfolioProcessor"
+    }
+
+    async fn execute(&mut self, context: &mut ProcessorContext) -> Result<(), Error> {
+        let scored_tickets = context
+            .get_attribute(&SCORED_TICKETS)
+            .ok_or(Error::ContextAttrNotExist(SCORED_TICKETS.to_string()))?;
+        let portfolio = self.optimizer.optimize(scored_tickets);
+        let final_results = portfolio
+            .tickets
+            .iter()
+            .map(ScoredTicket::ticket)
+            .cloned()
+            .collect::<Vec<FinalProcessorChainResult>>();
+        context.set_attribute(&FINAL_PROCESSOR_CHAIN_RESULTS, final_results);
+        context.set_attribute(&PORTFOLIO, portfolio);
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+/// 长时间运行的分析向调用方报告的进度
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(rename = "进度")]
+pub struct ProgressEvent {
+    #[serde(rename = "阶段")]
+    stage: String,
+    #[serde(rename = "已完成")]
+    completed: usize,
+    #[serde(rename = "总数")]
+    total: Option<usize>,
+    #[serde(rename = "信息")]
+    message: String,
+}
+
+impl ProgressEvent {
+    pub fn new(
+        stage: impl Into<String>,
+        completed: usize,
+        total: Option<usize>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            stage: stage.into(),
+            completed,
+            total,
+            message: message.into(),
+        }
+    }
+
+    pub fn stage(&self) -> &str {
+        &self.stage
+    }
+
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    pub fn total(&self) -> Option<usize> {
+        self.total
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// 接收进度的回调，由执行器在执行过程中同步调用，回调中不应做耗时的工作
+#[derive(Clone)]
+pub struct ProgressReporter(Arc<dyn Fn(ProgressEvent) + Send + Sync>);
+
+impl ProgressReporter {
+    pub fn new(report: impl Fn(ProgressEvent) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(report))
+    }
+
+    pub fn report(&self, event: ProgressEvent) {
+        (self.0)(event)
+    }
+}
+
+impl Debug for ProgressReporter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressReporter")
+    }
+}
@@ -0,0 +1,322 @@
+use crate::FinalProcessorChainResult;
+use crate::backtest::{BacktestReport, score_tickets};
+use crate::error::Error;
+use crate::progress::{ProgressEvent, ProgressReporter};
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::seq::index::sample;
+use rand::{Rng, RngCore, SeedableRng};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::{BlueBall, PrBusinessObj, PrizeGrade, RedBall};
+use std::collections::{BTreeMap, HashMap};
+use strum::EnumCount;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+/// 随机基准的最大模拟次数，每次模拟都要为回测的每一期生成同样注数的号码并计奖
+pub const MAX_TRIALS: usize = 100_000;
+/// 每模拟若干次检查一次是否被取消并报告进度
+const CHECK_INTERVAL: usize = 100;
+
+/// 生成一注均匀随机的单式号码
+pub fn random_ticket(rng: &mut impl RngCore) -> Result<FinalProcessorChainResult, Error> {
+    let red_balls = sample(rng, RedBall::COUNT, 6)
+        .into_iter()
+        .sorted()
+        .map(|index| RedBall::try_from(index + 1))
+        .collect::<Result<Vec<RedBall>, _>>()?;
+    let blue_ball = BlueBall::try_from(rng.random_range(1..=BlueBall::COUNT))?;
+    Ok(FinalProcessorChainResult::new(blue_ball, red_balls))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "随机基准报告")]
+pub struct RandomBaselineReport {
+    #[serde(rename = "模拟次数")]
+    trials: usize,
+    #[serde(rename = "随机种子")]
+    seed: u64,
+    #[serde(rename = "策略总奖金")]
+    strategy_return: u64,
+    #[serde(rename = "随机总奖金均值")]
+    mean_return: f64,
+    #[serde(rename = "随机总奖金标准差")]
+    return_standard_deviation: f64,
+    #[serde(rename = "随机总奖金分布")]
+    return_distribution: BTreeMap<u64, usize>,
+    #[serde(rename = "随机各奖级平均中奖注数")]
+    mean_grade_hits: BTreeMap<PrizeGrade, f64>,
+    #[serde(rename = "经验P值")]
+    p_value: f64,
+}
+
+impl RandomBaselineReport {
+    pub fn trials(&self) -> usize {
+        self.trials
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn strategy_return(&self) -> u64 {
+        self.strategy_return
+    }
+
+    pub fn mean_return(&self) -> f64 {
+        self.mean_return
+    }
+
+    pub fn return_standard_deviation(&self) -> f64 {
+        self.return_standard_deviation
+    }
+
+    /// 每种随机总奖金出现的模拟次数
+    pub fn return_distribution(&self) -> &BTreeMap<u64, usize> {
+        &self.return_distribution
+    }
+
+    pub fn mean_grade_hits(&self) -> &BTreeMap<PrizeGrade, f64> {
+        &self.mean_grade_hits
+    }
+
+    /// 随机购买的总奖金不低于策略总奖金的经验概率
+    pub fn p_value(&self) -> f64 {
+        self.p_value
+    }
+}
+
+/// 用同样注数的均匀随机号码重复模拟回测，得出策略结果在随机结果中所处的位置
+pub struct RandomBaseline {
+    trials: usize,
+    seed: u64,
+    progress_reporter: Option<ProgressReporter>,
+    cancellation_token: CancellationToken,
+}
+
+impl RandomBaseline {
+    pub fn new(trials: usize, seed: u64) -> Self {
+        Self {
+            trials,
+            seed,
+            progress_reporter: None,
+            cancellation_token: CancellationToken::new(),
+        }
+    }
+
+    /// 每模拟若干次通过 `progress_reporter` 报告进度
+    pub fn progress_reporter(mut self, progress_reporter: Option<ProgressReporter>) -> Self {
+        self.progress_reporter = progress_reporter;
+        self
+    }
+
+    /// 调用方通过 `cancellation_token` 取消模拟，每模拟若干次检查一次
+    pub fn cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    fn report_progress(&self, completed: usize) {
+        if let Some(progress_reporter) = &self.progress_reporter {
+            progress_reporter.report(ProgressEvent::new(
+                "随机基准",
+                completed,
+                Some(self.trials),
+                format!("随机模拟 {completed} 次"),
+            ));
+        }
+    }
+
+    /// `prize_records` 需要包含回测报告中所有开奖期的中奖记录。模拟是同步的计算密集型工作，
+    /// 在异步代码中应当放到阻塞线程中执行
+    pub fn simulate(
+        &self,
+        prize_records: &[PrBusinessObj],
+        backtest_report: &BacktestReport,
+    ) -> Result<RandomBaselineReport, Error> {
+        if self.trials == 0 || self.trials > MAX_TRIALS {
+            return Err(Error::InvalidProcessorConfig(format!(
+                "随机模拟次数必须在 1 到 {MAX_TRIALS} 之间：{}",
+                self.trials
+            )));
+        }
+        let draws_by_code = prize_records
+            .iter()
+            .map(|record| (record.code.as_str(), record))
+            .collect::<HashMap<&str, &PrBusinessObj>>();
+        let draws = backtest_report
+            .draw_results()
+            .iter()
+            .map(|draw_result| {
+                draws_by_code
+                    .get(draw_result.code())
+                    .map(|draw| (*draw, draw_result.ticket_count()))
+                    .ok_or(Error::OtherFailure(format!(
+                        "没有找到回测期号对应的中奖记录：{}",
+                        draw_result.code()
+                    )))
+            })
+            .collect::<Result<Vec<(&PrBusinessObj, usize)>, Error>>()?;
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut trial_returns = Vec::with_capacity(self.trials);
+        let mut total_grade_hits = BTreeMap::<PrizeGrade, usize>::new();
+        for trial in 0..self.trials {
+            if trial % CHECK_INTERVAL == 0 {
+                if self.cancellation_token.is_cancelled() {
+                    return Err(Error::Cancelled(format!(
+                        "随机基准在第 {trial} 次模拟之前被取消"
+                    )));
+                }
+                self.report_progress(trial);
+            }
+            let mut trial_return = 0;
+            for (draw, ticket_count) in draws.iter() {
+                let tickets = (0..*ticket_count)
+                    .map(|_| random_ticket(&mut rng))
+                    .collect::<Result<Vec<FinalProcessorChainResult>, Error>>()?;
+                let (prize_grades, prize_return) = score_tickets(draw, &tickets);
+                prize_grades
+                    .into_iter()
+                    .for_each(|grade| *total_grade_hits.entry(grade).or_default() += 1);
+                trial_return += prize_return;
+            }
+            trial_returns.push(trial_return);
+        }
+        self.report_progress(self.trials);
+
+        let strategy_return = backtest_report.total_return();
+        let trials = self.trials as f64;
+        let mean_return = trial_returns.iter().sum::<u64>() as f64 / trials;
+        let return_standard_deviation = (trial_returns
+            .iter()
+            .map(|v| (*v as f64 - mean_return).powi(2))
+            .sum::<f64>()
+            / trials)
+            .sqrt();
+        let mut return_distribution = BTreeMap::<u64, usize>::new();
+        trial_returns
+            .iter()
+            .for_each(|v| *return_distribution.entry(*v).or_default() += 1);
+        let mean_grade_hits = total_grade_hits
+            .into_iter()
+            .map(|(grade, hits)| (grade, hits as f64 / trials))
+            .collect::<BTreeMap<PrizeGrade, f64>>();
+        // 加一修正，避免经验 P 值为 0
+        let not_worse_trials = trial_returns
+            .iter()
+            .filter(|v| **v >= strategy_return)
+            .count();
+        let p_value = (not_worse_trials + 1) as f64 / (self.trials + 1) as f64;
+        info!(
+            "随机基准模拟 {} 次，随机总奖金均值 {mean_return:.2}，策略总奖金 {strategy_return}，经验 P 值 {p_value:.4}",
+            self.trials
+        );
+        Ok(RandomBaselineReport {
+            trials: self.trials,
+            seed: self.seed,
+            strategy_return,
+            mean_return,
+            return_standard_deviation,
+            return_distribution,
+            mean_grade_hits,
+            p_value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use serde_json::json;
+    use ssq_tool_domain::PrizeGradeDetail;
+
+    fn draw(code: &str, red_balls: [usize; 6], blue_ball: usize) -> PrBusinessObj {
+        PrBusinessObj {
+            code: code.to_string(),
+            seq: 0,
+            date: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            day: "星期三".to_string(),
+            blue_ball: BlueBall::try_from(blue_ball).unwrap(),
+            red_balls: red_balls.map(|ball| RedBall::try_from(ball).unwrap()),
+            total_tickets: 100_000_000,
+            total_prized_tickets: 5_000_000,
+            prize_grades: vec![PrizeGradeDetail {
+                grade: PrizeGrade::First,
+                winning_tickets: 2,
+                money: 5_000_000,
+            }],
+            pool_money: 1_000_000_000,
+        }
+    }
+
+    fn records() -> Vec<PrBusinessObj> {
+        vec![
+            draw("2025001", [1, 2, 3, 4, 5, 6], 1),
+            draw("2025002", [7, 8, 9, 10, 11, 12], 2),
+        ]
+    }
+
+    /// 两期各买 20 注、都没有中奖的回测报告
+    fn backtest_report() -> BacktestReport {
+        let draw_result = |code: &str| {
+            json!({
+                "中奖期号": code,
+                "历史期数": 100,
+                "预测注数": 20,
+                "中奖奖级": [],
+                "投入金额": 40,
+                "中奖金额": 0,
+            })
+        };
+        serde_json::from_value(json!({
+            "回测期数": 2,
+            "预测总注数": 40,
+            "各奖级中奖注数": {},
+            "总投入": 80,
+            "总奖金": 0,
+            "投资回报率": -1.0,
+            "每期结果": [draw_result("2025001"), draw_result("2025002")],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn simulate_is_reproducible_with_fixed_seed() {
+        let records = records();
+        let report = backtest_report();
+        let first = RandomBaseline::new(500, 42)
+            .simulate(&records, &report)
+            .unwrap();
+        let second = RandomBaseline::new(500, 42)
+            .simulate(&records, &report)
+            .unwrap();
+        assert_eq!(first.trials(), 500);
+        assert_eq!(first.return_distribution(), second.return_distribution());
+        assert_eq!(first.mean_grade_hits(), second.mean_grade_hits());
+        assert_eq!(first.mean_return(), second.mean_return());
+        assert_eq!(first.p_value(), second.p_value());
+        // 每次模拟 40 注，500 次模拟中至少有一些号码中了六等奖
+        assert!(first.mean_grade_hits().contains_key(&PrizeGrade::Sixth));
+    }
+
+    #[test]
+    fn simulate_rejects_too_many_trials_and_stops_when_cancelled() {
+        let records = records();
+        let report = backtest_report();
+        let error = RandomBaseline::new(MAX_TRIALS + 1, 0)
+            .simulate(&records, &report)
+            .unwrap_err();
+        assert!(matches!(error, Error::InvalidProcessorConfig(_)));
+
+        let cancellation_token = CancellationToken::new();
+        cancellation_token.cancel();
+        let error = RandomBaseline::new(MAX_TRIALS, 0)
+            .cancellation_token(cancellation_token)
+            .simulate(&records, &report)
+            .unwrap_err();
+        assert!(error.is_interrupted());
+    }
+}
@@ -0,0 +1,371 @@
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::statistics::{chi_square_p_value, chi_square_statistic, normal_two_sided_p_value};
+use crate::{Processor, RANDOMNESS_TEST_RESULTS};
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::{BlueBall, PrBusinessObj, RedBall};
+use strum::EnumCount;
+use tracing::info;
+
+/// 每期开出的红球个数
+const RED_BALLS_PER_DRAW: usize = 6;
+/// 蓝球大号的起始值，1-8 为小号，9-16 为大号
+const BIG_BLUE_BALL_START: usize = 9;
+/// 红球和值的理论均值，6 × (1 + 33) / 2
+const RED_BALL_SUM_MEAN: usize = 102;
+/// 卡方检验中每个分组的最小期望频数
+const MINIMUM_EXPECTED_FREQUENCY: f64 = 5.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "随机性检验结果")]
+pub struct RandomnessTestResult {
+    #[serde(rename = "检验名称")]
+    name: String,
+    #[serde(rename = "统计量")]
+    statistic: f64,
+    #[serde(rename = "自由度")]
+    degrees_of_freedom: Option<usize>,
+    #[serde(rename = "P值")]
+    p_value: f64,
+    #[serde(rename = "拒绝随机假设")]
+    reject_randomness: bool,
+}
+
+impl RandomnessTestResult {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn statistic(&self) -> f64 {
+        self.statistic
+    }
+
+    /// 基于正态近似的检验没有自由度
+    pub fn degrees_of_freedom(&self) -> Option<usize> {
+        self.degrees_of_freedom
+    }
+
+    pub fn p_value(&self) -> f64 {
+        self.p_value
+    }
+
+    /// 在执行器配置的显著性水平下是否拒绝“历史数据服从均匀随机”的假设
+    pub fn reject_randomness(&self) -> bool {
+        self.reject_randomness
+    }
+}
+
+/// 对历史中奖记录做卡方拟合优度检验、游程检验、序列相关检验与间隔检验
+pub struct RandomnessTestProcessor {
+    significance_level: f64,
+}
+
+impl RandomnessTestProcessor {
+    pub fn new(significance_level: f64) -> Self {
+        Self { significance_level }
+    }
+
+    fn result(
+        &self,
+        name: &str,
+        statistic: f64,
+        degrees_of_freedom: Option<usize>,
+        p_value: f64,
+    ) -> RandomnessTestResult {
+        RandomnessTestResult {
+            name: name.to_owned(),
+            statistic,
+            degrees_of_freedom,
+            p_value,
+            reject_randomness: p_value < self.significance_level,
+        }
+    }
+
+    /// 各号码出现次数的卡方拟合优度检验
+    ///
+    /// 每期从 `ball_count` 个号码中不放回地开出 `balls_per_draw` 个，各号码出现次数之间负相关，
+    /// Pearson 统计量的期望为 `ball_count - balls_per_draw` 而不是自由度 `ball_count - 1`，
+    /// 因此乘以 `(ball_count - 1) / (ball_count - balls_per_draw)` 加以修正，蓝球每期只开一个，无需修正
+    fn frequency_test(
+        &self,
+        name: &str,
+        values: &[usize],
+        ball_count: usize,
+        balls_per_draw: usize,
+        draw_count: usize,
+    ) -> RandomnessTestResult {
+        let mut observed = vec![0f64; ball_count];
+        values.iter().for_each(|value| observed[value - 1] += 1.0);
+        let expected =
+            vec![draw_count as f64 * balls_per_draw as f64 / ball_count as f64; ball_count];
+        let degrees_of_freedom = ball_count - 1;
+        let statistic = chi_square_statistic(&observed, &expected) * degrees_of_freedom as f64
+            / (ball_count - balls_per_draw) as f64;
+        self.result(
+            name,
+            statistic,
+            Some(degrees_of_freedom),
+            chi_square_p_value(statistic, degrees_of_freedom),
+        )
+    }
+
+    /// Wald-Wolfowitz 游程检验，统计量为标准化后的 Z 值
+    fn runs_test(&self, name: &str, sequence: &[bool]) -> RandomnessTestResult {
+        let n = sequence.len() as f64;
+        let n1 = sequence.iter().filter(|v| **v).count() as f64;
+        let n2 = n - n1;
+        if n1 == 0.0 || n2 == 0.0 {
+            return self.result(name, 0.0, None, 1.0);
+        }
+        let runs = 1 + sequence
+            .windows(2)
+            .filter(|pair| pair[0] != pair[1])
+            .count();
+        let mean = 2.0 * n1 * n2 / n + 1.0;
+        let variance = 2.0 * n1 * n2 * (2.0 * n1 * n2 - n) / (n * n * (n - 1.0));
+        let z = (runs as f64 - mean) / variance.sqrt();
+        self.result(name, z, None, normal_two_sided_p_value(z))
+    }
+
+    /// 滞后一期的序列相关检验，统计量为标准化后的 Z 值
+    fn serial_correlation_test(&self, name: &str, series: &[f64]) -> RandomnessTestResult {
+        let n = series.len() as f64;
+        let mean = series.iter().sum::<f64>() / n;
+        let variance = series.iter().map(|v| (v - mean).powi(2)).sum::<f64>();
+        if variance == 0.0 {
+            return self.result(name, 0.0, None, 1.0);
+        }
+        let covariance = series
+            .windows(2)
+            .map(|pair| (pair[0] - mean) * (pair[1] - mean))
+            .sum::<f64>();
+        let z = covariance / variance * n.sqrt();
+        self.result(name, z, None, normal_two_sided_p_value(z))
+    }
+
+    /// 间隔检验，相邻两次出现之间的间隔期数在随机假设下服从几何分布，
+    /// 按照期望频数不少于 5 的规则合并分组后做卡方检验
+    fn gap_test(
+        &self,
+        name: &str,
+        draws: &[Vec<usize>],
+        ball_count: usize,
+        balls_per_draw: usize,
+    ) -> RandomnessTestResult {
+        let mut last_occurrences = vec![None::<usize>; ball_count];
+        let mut gaps = Vec::new();
+        draws.iter().enumerate().for_each(|(index, values)| {
+            values.iter().for_each(|value| {
+                if let Some(last) = last_occurrences[value - 1] {
+                    gaps.push(index - last - 1);
+                }
+                last_occurrences[value - 1] = Some(index);
+            });
+        });
+        let probability = balls_per_draw as f64 / ball_count as f64;
+        let total_gaps = gaps.len() as f64;
+        // 合并相邻的间隔分组，最后一组包含所有更长的间隔
+        let mut bin_upper_bounds = Vec::new();
+        let mut bin_expected = Vec::new();
+        let mut accumulated = 0.0;
+        let mut gap = 0;
+        let mut tail_probability = 1.0;
+        while total_gaps * tail_probability >= 2.0 * MINIMUM_EXPECTED_FREQUENCY {
+            let gap_probability = probability * (1.0 - probability).powi(gap as i32);
+            accumulated += total_gaps * gap_probability;
+            tail_probability -= gap_probability;
+            if accumulated >= MINIMUM_EXPECTED_FREQUENCY {
+                bin_upper_bounds.push(gap);
+                bin_expected.push(accumulated);
+                accumulated = 0.0;
+            }
+            gap += 1;
+        }
+        bin_expected.push(accumulated + total_gaps * tail_probability);
+        let mut observed = vec![0f64; bin_expected.len()];
+        gaps.iter().for_each(|gap| {
+            let bin = bin_upper_bounds
+                .iter()
+                .position(|upper_bound| gap <= upper_bound)
+                .unwrap_or(bin_upper_bounds.len());
+            observed[bin] += 1.0;
+        });
+        let statistic = chi_square_statistic(&observed, &bin_expected);
+        let degrees_of_freedom = bin_expected.len().saturating_sub(1);
+        self.result(
+            name,
+            statistic,
+            Some(degrees_of_freedom),
+            chi_square_p_value(statistic, degrees_of_freedom),
+        )
+    }
+}
+
+impl Default for RandomnessTestProcessor {
+    fn default() -> Self {
+        Self::new(0.05)
+    }
+}
+
+#[async_trait::async_trait]
+impl Processor for RandomnessTestProcessor {
+    fn name(&self) -> &str {
+        "RandomnessTestProcessor"
+    }
+
+    async fn execute(&mut self, context: &mut ProcessorContext) -> Result<(), Error> {
+        // 对中奖记录按照从最早到最近进行排序
+        let sorted_records = context
+            .get_prize_records()
+            .iter()
+            .sorted_by_key(|record| record.date)
+            .collect::<Vec<&PrBusinessObj>>();
+        if sorted_records.len() < 2 {
+            return Err(Error::OtherFailure(
+                "随机性检验至少需要两期中奖记录".to_string(),
+            ));
+        }
+        let draw_count = sorted_records.len();
+        let red_draws = sorted_records
+            .iter()
+            .map(|record| {
+                record
+                    .red_balls
+                    .iter()
+                    .map(|ball| *ball as usize)
+                    .collect::<Vec<usize>>()
+            })
+            .collect::<Vec<Vec<usize>>>();
+        let blue_draws = sorted_records
+            .iter()
+            .map(|record| vec![record.blue_ball as usize])
+            .collect::<Vec<Vec<usize>>>();
+        let red_values = red_draws.concat();
+        let blue_values = blue_draws.concat();
+        let red_sums = red_draws
+            .iter()
+            .map(|values| values.iter().sum::<usize>())
+            .collect::<Vec<usize>>();
+
+        let results = vec![
+            self.frequency_test(
+                "红球频率卡方检验",
+                &red_values,
+                RedBall::COUNT,
+                RED_BALLS_PER_DRAW,
+                draw_count,
+            ),
+            self.frequency_test(
+                "蓝球频率卡方检验",
+                &blue_values,
+                BlueBall::COUNT,
+                1,
+                draw_count,
+            ),
+            self.runs_test(
+                "红球奇偶游程检验",
+                &red_draws
+                    .iter()
+                    .map(|values| {
+                        values.iter().filter(|v| *v % 2 == 1).count() > RED_BALLS_PER_DRAW / 2
+                    })
+                    .collect::<Vec<bool>>(),
+            ),
+            self.runs_test(
+                "蓝球奇偶游程检验",
+                &blue_values
+                    .iter()
+                    .map(|v| v % 2 == 1)
+                    .collect::<Vec<bool>>(),
+            ),
+            self.runs_test(
+                "蓝球大小游程检验",
+                &blue_values
+                    .iter()
+                    .map(|v| *v >= BIG_BLUE_BALL_START)
+                    .collect::<Vec<bool>>(),
+            ),
+            self.runs_test(
+                "红球和值大小游程检验",
+                &red_sums
+                    .iter()
+                    .map(|v| *v > RED_BALL_SUM_MEAN)
+                    .collect::<Vec<bool>>(),
+            ),
+            self.serial_correlation_test(
+                "红球和值序列相关检验",
+                &red_sums.iter().map(|v| *v as f64).collect::<Vec<f64>>(),
+            ),
+            self.serial_correlation_test(
+                "蓝球序列相关检验",
+                &blue_values.iter().map(|v| *v as f64).collect::<Vec<f64>>(),
+            ),
+            self.gap_test(
+                "红球间隔检验",
+                &red_draws,
+                RedBall::COUNT,
+                RED_BALLS_PER_DRAW,
+            ),
+            self.gap_test("蓝球间隔检验", &blue_draws, BlueBall::COUNT, 1),
+        ];
+        results.iter().for_each(|result| {
+            info!(
+                "{}：统计量 {:.4}，自由度 {:?}，P 值 {:.4}，拒绝随机假设：{}",
+                result.name,
+                result.statistic,
+                result.degrees_of_freedom,
+                result.p_value,
+                result.reject_randomness
+            );
+        });
+        context.set_attribute(&RANDOMNESS_TEST_RESULTS, results);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_test_counts_runs_of_sequence() {
+        let processor = RandomnessTestProcessor::default();
+        // 10 个 true 与 10 个 false 交替出现，共 20 个游程，期望 11 个
+        let alternating = (0..20).map(|i| i % 2 == 0).collect::<Vec<bool>>();
+        let result = processor.runs_test("交替", &alternating);
+        assert!((result.statistic() - 9.0 / (90.0 / 19.0f64).sqrt()).abs() < 1e-12);
+        assert!(result.reject_randomness());
+
+        let constant = vec![true; 20];
+        let result = processor.runs_test("不变", &constant);
+        assert_eq!(result.p_value(), 1.0);
+        assert!(!result.reject_randomness());
+    }
+
+    #[test]
+    fn frequency_test_corrects_for_drawing_without_replacement() {
+        let processor = RandomnessTestProcessor::default();
+        // 11 期共 66 个红球，期望每个号码出现 2 次，1 号出现 3 次、2 号出现 1 次，Pearson 统计量为 1
+        let values = (1..=RedBall::COUNT)
+            .flat_map(|ball| {
+                let count = match ball {
+                    1 => 3,
+                    2 => 1,
+                    _ => 2,
+                };
+                std::iter::repeat_n(ball, count)
+            })
+            .collect::<Vec<usize>>();
+        let result =
+            processor.frequency_test("红球", &values, RedBall::COUNT, RED_BALLS_PER_DRAW, 11);
+        assert!((result.statistic() - 32.0 / 27.0).abs() < 1e-12);
+        assert_eq!(result.degrees_of_freedom(), Some(32));
+
+        // 蓝球每期只开一个，统计量即为 Pearson 统计量
+        let values = [1, 1, 2];
+        let result = processor.frequency_test("蓝球", &values, 2, 1, 3);
+        assert!((result.statistic() - (0.25 / 1.5 + 0.25 / 1.5)).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,256 @@
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::{Processor, RED_BALL_SHAPE_DISTRIBUTION, RED_BALL_SHAPES};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::RedBall;
+use std::collections::BTreeMap;
+use tracing::debug;
+
+/// 大号的起始值，1-16 为小号，17-33 为大号
+const BIG_RED_BALL_START: usize = 17;
+/// 三区的起始值，分别为 1-11、12-22、23-33
+const ZONE_STARTS: [usize; 3] = [1, 12, 23];
+/// 按照彩票分析的惯例，1 也算作质数
+const PRIME_RED_BALLS: [usize; 12] = [1, 2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "红球形态")]
+pub struct RedBallShape {
+    #[serde(rename = "和值")]
+    sum: usize,
+    #[serde(rename = "跨度")]
+    span: usize,
+    #[serde(rename = "奇数个数")]
+    odd_count: usize,
+    #[serde(rename = "偶数个数")]
+    even_count: usize,
+    #[serde(rename = "大号个数")]
+    big_count: usize,
+    #[serde(rename = "小号个数")]
+    small_count: usize,
+    #[serde(rename = "三区分布")]
+    zone_counts: [usize; 3],
+    #[serde(rename = "质数个数")]
+    prime_count: usize,
+    #[serde(rename = "合数个数")]
+    composite_count: usize,
+}
+
+impl RedBallShape {
+    pub fn new(red_balls: &[RedBall]) -> Self {
+        let values = red_balls
+            .iter()
+            .map(|ball| *ball as usize)
+            .collect::<Vec<usize>>();
+        let mut shape = Self {
+            sum: values.iter().sum(),
+            span: match (values.iter().min(), values.iter().max()) {
+                (Some(min), Some(max)) => max - min,
+                _ => 0,
+            },
+            ..Default::default()
+        };
+        values.iter().for_each(|value| {
+            if value % 2 == 1 {
+                shape.odd_count += 1;
+            } else {
+                shape.even_count += 1;
+            }
+            if *value >= BIG_RED_BALL_START {
+                shape.big_count += 1;
+            } else {
+                shape.small_count += 1;
+            }
+            let zone = ZONE_STARTS
+                .iter()
+                .rposition(|start| value >= start)
+                .unwrap_or(0);
+            shape.zone_counts[zone] += 1;
+            if PRIME_RED_BALLS.contains(value) {
+                shape.prime_count += 1;
+            } else {
+                shape.composite_count += 1;
+            }
+        });
+        shape
+    }
+
+    pub fn sum(&self) -> usize {
+        self.sum
+    }
+
+    pub fn span(&self) -> usize {
+        self.span
+    }
+
+    pub fn odd_count(&self) -> usize {
+        self.odd_count
+    }
+
+    pub fn even_count(&self) -> usize {
+        self.even_count
+    }
+
+    pub fn big_count(&self) -> usize {
+        self.big_count
+    }
+
+    pub fn small_count(&self) -> usize {
+        self.small_count
+    }
+
+    pub fn zone_counts(&self) -> [usize; 3] {
+        self.zone_counts
+    }
+
+    pub fn prime_count(&self) -> usize {
+        self.prime_count
+    }
+
+    pub fn composite_count(&self) -> usize {
+        self.composite_count
+    }
+
+    /// 奇偶比，例如 `3:3`
+    pub fn odd_even_ratio(&self) -> String {
+        format!("{}:{}", self.odd_count, self.even_count)
+    }
+
+    /// 大小比，例如 `4:2`
+    pub fn big_small_ratio(&self) -> String {
+        format!("{}:{}", self.big_count, self.small_count)
+    }
+
+    /// 三区比，例如 `2:2:2`
+    pub fn zone_ratio(&self) -> String {
+        format!(
+            "{}:{}:{}",
+            self.zone_counts[0], self.zone_counts[1], self.zone_counts[2]
+        )
+    }
+
+    /// 质合比，例如 `2:4`
+    pub fn prime_composite_ratio(&self) -> String {
+        format!("{}:{}", self.prime_count, self.composite_count)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "单期红球形态")]
+pub struct DrawRedBallShape {
+    #[serde(rename = "中奖期号")]
+    code: String,
+    #[serde(rename = "红球形态")]
+    shape: RedBallShape,
+}
+
+impl DrawRedBallShape {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn shape(&self) -> &RedBallShape {
+        &self.shape
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "红球形态历史分布")]
+pub struct RedBallShapeDistribution {
+    #[serde(rename = "和值分布")]
+    sum: BTreeMap<usize, usize>,
+    #[serde(rename = "跨度分布")]
+    span: BTreeMap<usize, usize>,
+    #[serde(rename = "奇偶比分布")]
+    odd_even_ratio: BTreeMap<String, usize>,
+    #[serde(rename = "大小比分布")]
+    big_small_ratio: BTreeMap<String, usize>,
+    #[serde(rename = "三区比分布")]
+    zone_ratio: BTreeMap<String, usize>,
+    #[serde(rename = "质合比分布")]
+    prime_composite_ratio: BTreeMap<String, usize>,
+}
+
+impl RedBallShapeDistribution {
+    fn add(&mut self, shape: &RedBallShape) {
+        *self.sum.entry(shape.sum).or_default() += 1;
+        *self.span.entry(shape.span).or_default() += 1;
+        *self
+            .odd_even_ratio
+            .entry(shape.odd_even_ratio())
+            .or_default() += 1;
+        *self
+            .big_small_ratio
+            .entry(shape.big_small_ratio())
+            .or_default() += 1;
+        *self.zone_ratio.entry(shape.zone_ratio()).or_default() += 1;
+        *self
+            .prime_composite_ratio
+            .entry(shape.prime_composite_ratio())
+            .or_default() += 1;
+    }
+
+    pub fn sum(&self) -> &BTreeMap<usize, usize> {
+        &self.sum
+    }
+
+    pub fn span(&self) -> &BTreeMap<usize, usize> {
+        &self.span
+    }
+
+    pub fn odd_even_ratio(&self) -> &BTreeMap<String, usize> {
+        &self.odd_even_ratio
+    }
+
+    pub fn big_small_ratio(&self) -> &BTreeMap<String, usize> {
+        &self.big_small_ratio
+    }
+
+    pub fn zone_ratio(&self) -> &BTreeMap<String, usize> {
+        &self.zone_ratio
+    }
+
+    pub fn prime_composite_ratio(&self) -> &BTreeMap<String, usize> {
+        &self.prime_composite_ratio
+    }
+}
+
+impl<'s> FromIterator<&'s RedBallShape> for RedBallShapeDistribution {
+    fn from_iter<I: IntoIterator<Item = &'s RedBallShape>>(shapes: I) -> Self {
+        let mut distribution = Self::default();
+        shapes.into_iter().for_each(|shape| distribution.add(shape));
+        distribution
+    }
+}
+
+/// 统计每期红球的和值、跨度、奇偶比、大小比、三区比与质合比，以及它们的历史分布
+pub struct RedBallShapeProcessor;
+
+#[async_trait::async_trait]
+impl Processor for RedBallShapeProcessor {
+    fn name(&self) -> &str {
+        "RedBallShapeProcessor"
+    }
+
+    async fn execute(&mut self, context: &mut ProcessorContext) -> Result<(), Error> {
+        let draw_shapes = context
+            .get_prize_records()
+            .iter()
+            .map(|record| DrawRedBallShape {
+                code: record.code.clone(),
+                shape: RedBallShape::new(&record.red_balls),
+            })
+            .collect::<Vec<DrawRedBallShape>>();
+        let distribution = draw_shapes
+            .iter()
+            .map(DrawRedBallShape::shape)
+            .collect::<RedBallShapeDistribution>();
+        debug!("红球和值分布：{:?}", distribution.sum());
+        debug!("红球奇偶比分布：{:?}", distribution.odd_even_ratio());
+        debug!("红球三区比分布：{:?}", distribution.zone_ratio());
+        context.set_attribute(&RED_BALL_SHAPES, draw_shapes);
+        context.set_attribute(&RED_BALL_SHAPE_DISTRIBUTION, distribution);
+        Ok(())
+    }
+}
@@ -0,0 +1,324 @@
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::{Processor, RED_BALL_STRUCTURE_DISTRIBUTION, RED_BALL_STRUCTURES};
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::{PrBusinessObj, RedBall};
+use std::collections::{BTreeMap, HashSet};
+use tracing::debug;
+
+/// 取得红球中所有连号的长度，例如 `[1, 2, 3, 10, 11, 20]` 的连号长度为 `[3, 2]`
+pub fn consecutive_runs(red_balls: &[RedBall]) -> Vec<usize> {
+    let values = red_balls
+        .iter()
+        .map(|ball| *ball as usize)
+        .sorted()
+        .dedup()
+        .collect::<Vec<usize>>();
+    let mut runs = Vec::new();
+    let mut current_run = 1;
+    values.windows(2).for_each(|pair| {
+        if pair[1] == pair[0] + 1 {
+            current_run += 1;
+        } else {
+            if current_run > 1 {
+                runs.push(current_run);
+            }
+            current_run = 1;
+        }
+    });
+    if current_run > 1 {
+        runs.push(current_run);
+    }
+    runs
+}
+
+/// 计算红球的 AC 值，即所有两两差值去重后的个数减去 `红球个数 - 1`
+pub fn ac_value(red_balls: &[RedBall]) -> usize {
+    let distinct_differences = red_balls
+        .iter()
+        .tuple_combinations()
+        .map(|(a, b)| (*a as usize).abs_diff(*b as usize))
+        .collect::<HashSet<usize>>()
+        .len();
+    distinct_differences.saturating_sub(red_balls.len().saturating_sub(1))
+}
+
+/// 按照尾数对红球分组
+pub fn tail_groups(red_balls: &[RedBall]) -> BTreeMap<usize, Vec<RedBall>> {
+    let mut groups = BTreeMap::<usize, Vec<RedBall>>::new();
+    red_balls.iter().sorted().for_each(|ball| {
+        groups.entry(*ball as usize % 10).or_default().push(*ball);
+    });
+    groups
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "红球结构")]
+pub struct RedBallStructure {
+    #[serde(rename = "连号长度")]
+    consecutive_runs: Vec<usize>,
+    #[serde(rename = "最长连号")]
+    max_consecutive: usize,
+    #[serde(rename = "AC值")]
+    ac_value: usize,
+    #[serde(rename = "尾数组数")]
+    tail_group_count: usize,
+    #[serde(rename = "最大同尾个数")]
+    max_same_tail_count: usize,
+    #[serde(rename = "同尾组数")]
+    same_tail_group_count: usize,
+    #[serde(rename = "重号个数")]
+    repeated_count: usize,
+    #[serde(rename = "邻号个数")]
+    adjacent_count: usize,
+    #[serde(rename = "斜连号个数")]
+    diagonal_count: usize,
+}
+
+impl RedBallStructure {
+    /// `previous` 为上一期的红球，`before_previous` 为上上期的红球，
+    /// 重号与邻号参考上一期，斜连号需要连续三期形成斜线
+    pub fn new(
+        red_balls: &[RedBall],
+        previous: Option<&[RedBall]>,
+        before_previous: Option<&[RedBall]>,
+    ) -> Self {
+        let consecutive_runs = consecutive_runs(red_balls);
+        let tail_groups = tail_groups(red_balls);
+        let values = red_balls
+            .iter()
+            .map(|ball| *ball as usize)
+            .collect::<Vec<usize>>();
+        let previous = previous
+            .unwrap_or_default()
+            .iter()
+            .map(|ball| *ball as usize)
+            .collect::<HashSet<usize>>();
+        let before_previous = before_previous
+            .unwrap_or_default()
+            .iter()
+            .map(|ball| *ball as usize)
+            .collect::<HashSet<usize>>();
+        let repeated_count = values
+            .iter()
+            .filter(|value| previous.contains(value))
+            .count();
+        let adjacent_count = values
+            .iter()
+            .filter(|value| previous.contains(&(*value + 1)) || previous.contains(&(*value - 1)))
+            .count();
+        let diagonal_count = values
+            .iter()
+            .filter(|value| {
+                let ascending = **value >= 3
+                    && previous.contains(&(*value - 1))
+                    && before_previous.contains(&(*value - 2));
+                let descending =
+                    previous.contains(&(*value + 1)) && before_previous.contains(&(*value + 2));
+                ascending || descending
+            })
+            .count();
+        Self {
+            max_consecutive: consecutive_runs.iter().max().copied().unwrap_or(1),
+            consecutive_runs,
+            ac_value: ac_value(red_balls),
+            tail_group_count: tail_groups.len(),
+            max_same_tail_count: tail_groups.values().map(Vec::len).max().unwrap_or(0),
+            same_tail_group_count: tail_groups.values().filter(|group| group.len() > 1).count(),
+            repeated_count,
+            adjacent_count,
+            diagonal_count,
+        }
+    }
+
+    pub fn consecutive_runs(&self) -> &[usize] {
+        &self.consecutive_runs
+    }
+
+    /// 最长连号的长度，没有连号时为 1
+    pub fn max_consecutive(&self) -> usize {
+        self.max_consecutive
+    }
+
+    pub fn ac_value(&self) -> usize {
+        self.ac_value
+    }
+
+    pub fn tail_group_count(&self) -> usize {
+        self.tail_group_count
+    }
+
+    pub fn max_same_tail_count(&self) -> usize {
+        self.max_same_tail_count
+    }
+
+    pub fn same_tail_group_count(&self) -> usize {
+        self.same_tail_group_count
+    }
+
+    pub fn repeated_count(&self) -> usize {
+        self.repeated_count
+    }
+
+    pub fn adjacent_count(&self) -> usize {
+        self.adjacent_count
+    }
+
+    pub fn diagonal_count(&self) -> usize {
+        self.diagonal_count
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "单期红球结构")]
+pub struct DrawRedBallStructure {
+    #[serde(rename = "中奖期号")]
+    code: String,
+    #[serde(rename = "红球结构")]
+    structure: RedBallStructure,
+}
+
+impl DrawRedBallStructure {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn structure(&self) -> &RedBallStructure {
+        &self.structure
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "红球结构历史分布")]
+pub struct RedBallStructureDistribution {
+    #[serde(rename = "最长连号分布")]
+    max_consecutive: BTreeMap<usize, usize>,
+    #[serde(rename = "AC值分布")]
+    ac_value: BTreeMap<usize, usize>,
+    #[serde(rename = "尾数组数分布")]
+    tail_group_count: BTreeMap<usize, usize>,
+    #[serde(rename = "最大同尾个数分布")]
+    max_same_tail_count: BTreeMap<usize, usize>,
+    #[serde(rename = "重号个数分布")]
+    repeated_count: BTreeMap<usize, usize>,
+    #[serde(rename = "邻号个数分布")]
+    adjacent_count: BTreeMap<usize, usize>,
+    #[serde(rename = "斜连号个数分布")]
+    diagonal_count: BTreeMap<usize, usize>,
+}
+
+impl RedBallStructureDistribution {
+    fn add(&mut self, structure: &RedBallStructure) {
+        *self
+            .max_consecutive
+            .entry(structure.max_consecutive)
+            .or_default() += 1;
+        *self.ac_value.entry(structure.ac_value).or_default() += 1;
+        *self
+            .tail_group_count
+            .entry(structure.tail_group_count)
+            .or_default() += 1;
+        *self
+            .max_same_tail_count
+            .entry(structure.max_same_tail_count)
+            .or_default() += 1;
+        *self
+            .repeated_count
+            .entry(structure.repeated_count)
+            .or_default() += 1;
+        *self
+            .adjacent_count
+            .entry(structure.adjacent_count)
+            .or_default() += 1;
+        *self
+            .diagonal_count
+            .entry(structure.diagonal_count)
+            .or_default() += 1;
+    }
+
+    pub fn max_consecutive(&self) -> &BTreeMap<usize, usize> {
+        &self.max_consecutive
+    }
+
+    pub fn ac_value(&self) -> &BTreeMap<usize, usize> {
+        &self.ac_value
+    }
+
+    pub fn tail_group_count(&self) -> &BTreeMap<usize, usize> {
+        &self.tail_group_count
+    }
+
+    pub fn max_same_tail_count(&self) -> &BTreeMap<usize, usize> {
+        &self.max_same_tail_count
+    }
+
+    pub fn repeated_count(&self) -> &BTreeMap<usize, usize> {
+        &self.repeated_count
+    }
+
+    pub fn adjacent_count(&self) -> &BTreeMap<usize, usize> {
+        &self.adjacent_count
+    }
+
+    pub fn diagonal_count(&self) -> &BTreeMap<usize, usize> {
+        &self.diagonal_count
+    }
+}
+
+impl<'s> FromIterator<&'s RedBallStructure> for RedBallStructureDistribution {
+    fn from_iter<I: IntoIterator<Item = &'s RedBallStructure>>(structures: I) -> Self {
+        let mut distribution = Self::default();
+        structures
+            .into_iter()
+            .for_each(|structure| distribution.add(structure));
+        distribution
+    }
+}
+
+/// 统计每期红球的连号、AC 值、尾数、重号、邻号与斜连号，以及它们的历史分布
+pub struct RedBallStructureProcessor;
+
+#[async_trait::async_trait]
+impl Processor for RedBallStructureProcessor {
+    fn name(&self) -> &str {
+        "RedBallStructureProcessor"
+    }
+
+    async fn execute(&mut self, context: &mut ProcessorContext) -> Result<(), Error> {
+        // 对中奖记录按照从最早到最近进行排序，以便找到每期的上一期
+        let sorted_records = context
+            .get_prize_records()
+            .iter()
+            .sorted_by_key(|record| record.date)
+            .collect::<Vec<&PrBusinessObj>>();
+        let draw_structures = sorted_records
+            .iter()
+            .enumerate()
+            .map(|(index, record)| {
+                let previous = index
+                    .checked_sub(1)
+                    .map(|index| sorted_records[index].red_balls.as_slice());
+                let before_previous = index
+                    .checked_sub(2)
+                    .map(|index| sorted_records[index].red_balls.as_slice());
+                DrawRedBallStructure {
+                    code: record.code.clone(),
+                    structure: RedBallStructure::new(&record.red_balls, previous, before_previous),
+                }
+            })
+            .rev()
+            .collect::<Vec<DrawRedBallStructure>>();
+        let distribution = draw_structures
+            .iter()
+            .map(DrawRedBallStructure::structure)
+            .collect::<RedBallStructureDistribution>();
+        debug!("红球 AC 值分布：{:?}", distribution.ac_value());
+        debug!("红球最长连号分布：{:?}", distribution.max_consecutive());
+        debug!("红球重号个数分布：{:?}", distribution.repeated_count());
+        context.set_attribute(&RED_BALL_STRUCTURES, draw_structures);
+        context.set_attribute(&RED_BALL_STRUCTURE_DISTRIBUTION, distribution);
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+//! 分析执行器共用的概率分布函数
+
+use std::f64::consts::PI;
+
+const MAX_ITERATIONS: usize = 500;
+const EPSILON: f64 = 1e-14;
+
+/// Lanczos 近似计算 ln Γ(x)
+pub(crate) fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // 反射公式
+        (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x)
+    } else {
+        let x = x - 1.0;
+        let t = x + 7.5;
+        let series = COEFFICIENTS
+            .iter()
+            .enumerate()
+            .skip(1)
+            .fold(COEFFICIENTS[0], |sum, (i, c)| sum + c / (x + i as f64));
+        0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+    }
+}
+
+/// 正则化上不完全伽马函数 Q(a, x)
+pub(crate) fn regularized_gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    if x < a + 1.0 {
+        // 级数展开求 P(a, x)
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        let mut n = a;
+        for _ in 0..MAX_ITERATIONS {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        1.0 - sum * (-x + a * x.ln() - ln_gamma(a)).exp()
+    } else {
+        // 连分式求 Q(a, x)
+        let tiny = f64::MIN_POSITIVE / EPSILON;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..MAX_ITERATIONS {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+        (-x + a * x.ln() - ln_gamma(a)).exp() * h
+    }
+}
+
+/// 卡方分布的右尾概率
+pub(crate) fn chi_square_p_value(statistic: f64, degrees_of_freedom: usize) -> f64 {
+    if degrees_of_freedom == 0 {
+        return 1.0;
+    }
+    regularized_gamma_q(degrees_of_freedom as f64 / 2.0, statistic / 2.0).clamp(0.0, 1.0)
+}
+
+/// 互补误差函数
+pub(crate) fn erfc(x: f64) -> f64 {
+    if x < 0.0 {
+        2.0 - erfc(-x)
+    } else {
+        regularized_gamma_q(0.5, x * x)
+    }
+}
+
+/// 标准正态分布的双侧概率
+pub(crate) fn normal_two_sided_p_value(z: f64) -> f64 {
+    erfc(z.abs() / 2f64.sqrt()).clamp(0.0, 1.0)
+}
+
+/// 卡方拟合优度统计量
+pub(crate) fn chi_square_statistic(observed: &[f64], expected: &[f64]) -> f64 {
+    observed
+        .iter()
+        .zip(expected)
+        .filter(|(_, expected)| **expected > 0.0)
+        .map(|(observed, expected)| (observed - expected).powi(2) / expected)
+        .sum()
+}
+
+/// 正则化不完全贝塔函数的连分式部分
+fn incomplete_beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    let tiny = f64::MIN_POSITIVE / EPSILON;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < tiny {
+        d = tiny;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..MAX_ITERATIONS {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+        for an in [
+            m * (b - m) * x / ((a + m2 - 1.0) * (a + m2)),
+            -(a + m) * (a + b + m) * x / ((a + m2) * (a + m2 + 1.0)),
+        ] {
+            d = 1.0 + an * d;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = 1.0 + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            h *= d * c;
+        }
+        if (d * c - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+    h
+}
+
+/// 正则化不完全贝塔函数 I_x(a, b)，即贝塔分布的累积分布函数
+pub(crate) fn regularized_incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * incomplete_beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * incomplete_beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+/// 二分法求贝塔分布的分位数
+pub(crate) fn beta_quantile(a: f64, b: f64, probability: f64) -> f64 {
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..100 {
+        let middle = (low + high) / 2.0;
+        if regularized_incomplete_beta(a, b, middle) < probability {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() < tolerance,
+            "实际值 {actual}，期望值 {expected}"
+        );
+    }
+
+    #[test]
+    fn ln_gamma_matches_known_values() {
+        assert_close(ln_gamma(10.0), 362_880f64.ln(), 1e-10);
+        assert_close(ln_gamma(0.5), PI.sqrt().ln(), 1e-10);
+        assert_close(ln_gamma(0.3), 1.095_797_994_818_075_6, 1e-10);
+    }
+
+    #[test]
+    fn chi_square_p_value_matches_known_values() {
+        // 红球频率卡方检验，32 个自由度
+        assert_close(chi_square_p_value(52.625, 32), 0.012_252_066_607_581, 1e-9);
+        // 蓝球频率卡方检验，15 个自由度
+        assert_close(chi_square_p_value(14.738, 15), 0.470_449_061_869_207, 1e-9);
+        assert_close(chi_square_p_value(3.841, 1), 0.050_013_683_763_957, 1e-9);
+        assert_eq!(chi_square_p_value(1.0, 0), 1.0);
+    }
+
+    #[test]
+    fn normal_two_sided_p_value_matches_known_values() {
+        assert_close(normal_two_sided_p_value(2.194), 0.028_235_409_552_418, 1e-9);
+        assert_close(normal_two_sided_p_value(-1.96), 0.049_995_790_296_441, 1e-9);
+        assert_close(normal_two_sided_p_value(0.0), 1.0, 1e-12);
+    }
+
+    #[test]
+    fn chi_square_statistic_skips_empty_groups() {
+        let statistic = chi_square_statistic(&[12.0, 8.0, 3.0], &[10.0, 10.0, 0.0]);
+        assert_close(statistic, 0.8, 1e-12);
+    }
+
+    #[test]
+    fn incomplete_beta_and_quantile_match_known_values() {
+        assert_close(regularized_incomplete_beta(2.0, 3.0, 0.4), 0.5248, 1e-10);
+        assert_close(
+            regularized_incomplete_beta(0.5, 0.5, 0.3),
+            0.369_010_119_565_545,
+            1e-10,
+        );
+        assert_eq!(regularized_incomplete_beta(2.0, 3.0, 0.0), 0.0);
+        assert_eq!(regularized_incomplete_beta(2.0, 3.0, 1.0), 1.0);
+        assert_close(beta_quantile(2.0, 3.0, 0.5248), 0.4, 1e-10);
+    }
+}
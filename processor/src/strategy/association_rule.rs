@@ -0,0 +1,94 @@
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::strategy::{BallScores, ScoredTicket, Strategy, rank_by_score};
+use crate::{BALL_ASSOCIATION_RULES, FinalProcessorChainResult};
+use itertools::Itertools;
+use ssq_tool_domain::{Ball, BlueBall, RedBall};
+use std::collections::BTreeMap;
+
+/// 按照关联规则选择号码，蓝球按照以其为前项的规则强度排序，
+/// 红球优先选择与蓝球关联度高的红球，不足 6 个时用整体关联度高的红球补足
+pub struct AssociationRuleStrategy;
+
+impl AssociationRuleStrategy {
+    /// 规则强度为超出独立情况的部分乘以支持度，使只出现一两次的组合不会因为提升度高而排在前面
+    fn rule_strength(lift: f64, support: f64) -> f64 {
+        (lift - 1.0).max(0.0) * support
+    }
+}
+
+impl Strategy for AssociationRuleStrategy {
+    fn name(&self) -> &str {
+        "AssociationRuleStrategy"
+    }
+
+    fn ball_scores(&mut self, context: &ProcessorContext) -> Result<BallScores, Error> {
+        let rules =
+            context
+                .get_attribute(&BALL_ASSOCIATION_RULES)
+                .ok_or(Error::ContextAttrNotExist(
+                    BALL_ASSOCIATION_RULES.to_string(),
+                ))?;
+        let mut red = BTreeMap::<RedBall, f64>::new();
+        let mut blue = BTreeMap::<BlueBall, f64>::new();
+        rules.iter().for_each(|rule| {
+            let strength = Self::rule_strength(rule.lift(), rule.support());
+            rule.antecedent().iter().for_each(|ball| {
+                if let Ball::Blue(ball) = ball {
+                    *blue.entry(*ball).or_default() += strength;
+                }
+            });
+            rule.consequent_red_balls().iter().for_each(|ball| {
+                *red.entry(*ball).or_default() += strength;
+            });
+        });
+        Ok(BallScores::new(red, blue))
+    }
+
+    fn generate(
+        &mut self,
+        context: &ProcessorContext,
+        size: usize,
+    ) -> Result<Vec<ScoredTicket>, Error> {
+        let ball_scores = self.ball_scores(context)?;
+        let rules =
+            context
+                .get_attribute(&BALL_ASSOCIATION_RULES)
+                .ok_or(Error::ContextAttrNotExist(
+                    BALL_ASSOCIATION_RULES.to_string(),
+                ))?;
+        let overall_red_balls = ball_scores.ranked_red_balls();
+        let tickets = ball_scores
+            .ranked_blue_balls()
+            .into_iter()
+            .take(size)
+            .map(|blue_ball| {
+                let mut related_red_scores = BTreeMap::<RedBall, f64>::new();
+                rules
+                    .iter()
+                    .filter(|rule| rule.antecedent().contains(&Ball::Blue(blue_ball)))
+                    .for_each(|rule| {
+                        let strength = Self::rule_strength(rule.lift(), rule.support());
+                        rule.consequent_red_balls().iter().for_each(|ball| {
+                            *related_red_scores.entry(*ball).or_default() += strength;
+                        });
+                    });
+                let red_balls = rank_by_score(&related_red_scores)
+                    .into_iter()
+                    .chain(overall_red_balls.iter().copied())
+                    .unique()
+                    .take(6)
+                    .sorted()
+                    .collect::<Vec<RedBall>>();
+                let score =
+                    ball_scores.blue()[&blue_ball] + related_red_scores.values().sum::<f64>();
+                ScoredTicket::new(
+                    FinalProcessorChainResult::new(blue_ball, red_balls),
+                    score,
+                    self.name(),
+                )
+            })
+            .collect();
+        Ok(tickets)
+    }
+}
@@ -0,0 +1,133 @@
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::strategy::{BallScores, ScoredTicket, Strategy};
+use crate::{
+    BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP, BLUE_BALL_FOLLOWING_OCCURRENCES,
+    FinalProcessorChainResult,
+};
+use itertools::Itertools;
+use ssq_tool_domain::{BlueBall, RedBall};
+use std::collections::{BTreeMap, HashMap};
+use tracing::info;
+
+/// 最近一期蓝球之后出现次数最多的蓝球，加上这些蓝球的高支持度红球频繁项集
+pub struct FollowingBlueFpStrategy;
+
+impl FollowingBlueFpStrategy {
+    /// 最近一期中奖蓝球之后各蓝球的出现次数
+    fn following_blue_balls<'c>(
+        context: &'c ProcessorContext,
+    ) -> Result<Option<&'c HashMap<BlueBall, usize>>, Error> {
+        let blue_ball_following_occurrences = context
+            .get_attribute(&BLUE_BALL_FOLLOWING_OCCURRENCES)
+            .ok_or(Error::ContextAttrNotExist(
+                BLUE_BALL_FOLLOWING_OCCURRENCES.to_string(),
+            ))?;
+        // 最近一次中奖蓝球
+        let last_occur_blue_ball = context
+            .get_prize_records()
+            .iter()
+            .max_by_key(|record| record.date)
+            .map(|record| record.blue_ball)
+            .ok_or(Error::OtherFailure("没有中奖记录".to_string()))?;
+        let following_blue_ball_occurrences =
+            blue_ball_following_occurrences.get(&last_occur_blue_ball);
+        info!(
+            "最后一次中奖的篮球 {last_occur_blue_ball} 后续可能出现的蓝球情况：{following_blue_ball_occurrences:?}"
+        );
+        Ok(following_blue_ball_occurrences)
+    }
+
+    /// 蓝球关联的红球频繁项集中，支持度高于平均支持度的前 6 个项集所包含的红球
+    fn top_related_red_balls(
+        context: &ProcessorContext,
+        blue_ball: &BlueBall,
+    ) -> Result<Vec<(Vec<RedBall>, usize)>, Error> {
+        let red_ball_occurrence_fp = context
+            .get_attribute(&BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP)
+            .ok_or(Error::ContextAttrNotExist(
+                BLUE_BALL_AND_RED_BALL_RELATIONSHIP_FP.to_string(),
+            ))?;
+        red_ball_occurrence_fp
+            .get(blue_ball)
+            .map(|red_ball_fp_result| {
+                let red_ball_occurrence_fp_pattern = red_ball_fp_result.frequent_patterns();
+                let red_ball_occurrence_fp_ave_support = red_ball_occurrence_fp_pattern
+                    .iter()
+                    .map(|pattern| pattern.1)
+                    .sum::<usize>()
+                    .checked_div(red_ball_occurrence_fp_pattern.len())
+                    .unwrap_or(0);
+                red_ball_occurrence_fp_pattern
+                    .into_iter()
+                    .sorted_by_key(|pattern| pattern.1)
+                    .rev()
+                    .filter(|pattern| pattern.1 > red_ball_occurrence_fp_ave_support)
+                    .take(6)
+                    .collect::<Vec<(Vec<RedBall>, usize)>>()
+            })
+            .ok_or(Error::OtherFailure(format!(
+                "没有找到蓝球出现情况：{blue_ball}"
+            )))
+    }
+}
+
+impl Strategy for FollowingBlueFpStrategy {
+    fn name(&self) -> &str {
+        "FollowingBlueFpStrategy"
+    }
+
+    fn ball_scores(&mut self, context: &ProcessorContext) -> Result<BallScores, Error> {
+        let blue = Self::following_blue_balls(context)?
+            .map(|occurrences| {
+                occurrences
+                    .iter()
+                    .map(|(ball, count)| (*ball, *count as f64))
+                    .collect::<BTreeMap<BlueBall, f64>>()
+            })
+            .unwrap_or_default();
+        let mut red = BTreeMap::<RedBall, f64>::new();
+        blue.iter().try_for_each(|(blue_ball, blue_score)| {
+            Self::top_related_red_balls(context, blue_ball)?
+                .iter()
+                .for_each(|(red_balls, support)| {
+                    red_balls.iter().for_each(|red_ball| {
+                        *red.entry(*red_ball).or_default() += blue_score * *support as f64;
+                    })
+                });
+            Ok::<(), Error>(())
+        })?;
+        Ok(BallScores::new(red, blue))
+    }
+
+    fn generate(
+        &mut self,
+        context: &ProcessorContext,
+        size: usize,
+    ) -> Result<Vec<ScoredTicket>, Error> {
+        let Some(possible_blue_balls) = Self::following_blue_balls(context)? else {
+            return Ok(Vec::new());
+        };
+        // 按照出现次数从大到小排列
+        possible_blue_balls
+            .iter()
+            .sorted_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)))
+            .take(size)
+            .map(|(blue_ball, count)| {
+                let top_related_red_balls = Self::top_related_red_balls(context, blue_ball)?
+                    .iter()
+                    .flat_map(|pattern| &pattern.0)
+                    .copied()
+                    .unique()
+                    .sorted()
+                    .take(6)
+                    .collect::<Vec<RedBall>>();
+                Ok(ScoredTicket::new(
+                    FinalProcessorChainResult::new(*blue_ball, top_related_red_balls),
+                    *count as f64,
+                    self.name(),
+                ))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,54 @@
+use crate::BALL_TEMPERATURE;
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::strategy::{BallScores, Strategy};
+use ssq_tool_domain::Ball;
+use std::collections::BTreeMap;
+
+/// 按照冷热窗口中实际出现次数与期望出现次数的比值为双色球加权
+pub struct FrequencyWeightedStrategy {
+    window_size: Option<usize>,
+}
+
+impl FrequencyWeightedStrategy {
+    /// `window_size` 为空时使用 `BALL_TEMPERATURE` 中的第一个窗口
+    pub fn new(window_size: Option<usize>) -> Self {
+        Self { window_size }
+    }
+}
+
+impl Strategy for FrequencyWeightedStrategy {
+    fn name(&self) -> &str {
+        "FrequencyWeightedStrategy"
+    }
+
+    fn ball_scores(&mut self, context: &ProcessorContext) -> Result<BallScores, Error> {
+        let temperature_windows = context
+            .get_attribute(&BALL_TEMPERATURE)
+            .ok_or(Error::ContextAttrNotExist(BALL_TEMPERATURE.to_string()))?;
+        let temperature_window = match self.window_size {
+            Some(window_size) => temperature_windows
+                .iter()
+                .find(|window| window.window_size() == window_size),
+            None => temperature_windows.first(),
+        }
+        .ok_or(Error::OtherFailure(format!(
+            "没有找到冷热窗口：{:?}",
+            self.window_size
+        )))?;
+        let mut red = BTreeMap::new();
+        let mut blue = BTreeMap::new();
+        temperature_window
+            .frequencies()
+            .iter()
+            .for_each(|frequency| match frequency.ball() {
+                Ball::Red(ball) => {
+                    red.insert(ball, frequency.frequency_ratio());
+                }
+                Ball::Blue(ball) => {
+                    blue.insert(ball, frequency.frequency_ratio());
+                }
+            });
+        Ok(BallScores::new(red, blue))
+    }
+}
@@ -0,0 +1,42 @@
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::strategy::{BallScores, Strategy};
+use crate::{BALL_OCCURRENCE, BLUE_BALL_MARKOV_MODEL};
+use ssq_tool_domain::{Ball, RedBall};
+use std::collections::BTreeMap;
+use strum::IntoEnumIterator;
+
+/// 按照马尔可夫链给出的下期蓝球概率选择蓝球，红球按照历史出现次数选择
+pub struct MarkovBlueStrategy;
+
+impl Strategy for MarkovBlueStrategy {
+    fn name(&self) -> &str {
+        "MarkovBlueStrategy"
+    }
+
+    fn ball_scores(&mut self, context: &ProcessorContext) -> Result<BallScores, Error> {
+        let markov_model =
+            context
+                .get_attribute(&BLUE_BALL_MARKOV_MODEL)
+                .ok_or(Error::ContextAttrNotExist(
+                    BLUE_BALL_MARKOV_MODEL.to_string(),
+                ))?;
+        let ball_occurrence = context
+            .get_attribute(&BALL_OCCURRENCE)
+            .ok_or(Error::ContextAttrNotExist(BALL_OCCURRENCE.to_string()))?;
+        let red = RedBall::iter()
+            .map(|ball| {
+                let occurrence_count = ball_occurrence
+                    .get(&Ball::Red(ball))
+                    .map_or(0, |occurrence| {
+                        occurrence.occurrence_count_by_official_data()
+                    });
+                (ball, occurrence_count as f64)
+            })
+            .collect::<BTreeMap<RedBall, f64>>();
+        Ok(BallScores::new(
+            red,
+            markov_model.next_draw_probabilities().clone(),
+        ))
+    }
+}
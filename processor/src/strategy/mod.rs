@@ -0,0 +1,218 @@
+mod association_rule;
+mod following_blue_fp;
+mod frequency_weighted;
+mod markov_blue;
+mod omission;
+mod uniform_random;
+
+pub use association_rule::AssociationRuleStrategy;
+pub use following_blue_fp::FollowingBlueFpStrategy;
+pub use frequency_weighted::FrequencyWeightedStrategy;
+pub use markov_blue::MarkovBlueStrategy;
+pub use omission::OmissionStrategy;
+pub use uniform_random::UniformRandomStrategy;
+
+use crate::FinalProcessorChainResult;
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::{BlueBall, RedBall};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use strum::IntoEnumIterator;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "双色球得分")]
+pub struct BallScores {
+    #[serde(rename = "红球得分")]
+    red: BTreeMap<RedBall, f64>,
+    #[serde(rename = "蓝球得分")]
+    blue: BTreeMap<BlueBall, f64>,
+}
+
+impl BallScores {
+    /// 没有给出得分的双色球按 0 分处理
+    pub fn new(red: BTreeMap<RedBall, f64>, blue: BTreeMap<BlueBall, f64>) -> Self {
+        let red = RedBall::iter()
+            .map(|ball| (ball, red.get(&ball).copied().unwrap_or(0.0)))
+            .collect();
+        let blue = BlueBall::iter()
+            .map(|ball| (ball, blue.get(&ball).copied().unwrap_or(0.0)))
+            .collect();
+        Self { red, blue }
+    }
+
+    pub fn red(&self) -> &BTreeMap<RedBall, f64> {
+        &self.red
+    }
+
+    pub fn blue(&self) -> &BTreeMap<BlueBall, f64> {
+        &self.blue
+    }
+
+    /// 按照得分从高到低排列的红球，得分相同时号码小的在前
+    pub fn ranked_red_balls(&self) -> Vec<RedBall> {
+        rank_by_score(&self.red)
+    }
+
+    /// 按照得分从高到低排列的蓝球，得分相同时号码小的在前
+    pub fn ranked_blue_balls(&self) -> Vec<BlueBall> {
+        rank_by_score(&self.blue)
+    }
+}
+
+pub(crate) fn rank_by_score<T: Copy + Ord>(scores: &BTreeMap<T, f64>) -> Vec<T> {
+    scores
+        .iter()
+        .sorted_by(|a, b| {
+            b.1.partial_cmp(a.1)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.0.cmp(b.0))
+        })
+        .map(|(ball, _)| *ball)
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "候选号码")]
+pub struct ScoredTicket {
+    #[serde(rename = "号码")]
+    ticket: FinalProcessorChainResult,
+    #[serde(rename = "得分")]
+    score: f64,
+    #[serde(rename = "来源策略")]
+    strategies: Vec<String>,
+}
+
+impl ScoredTicket {
+    pub fn new(ticket: FinalProcessorChainResult, score: f64, strategy: impl Into<String>) -> Self {
+        Self {
+            ticket,
+            score,
+            strategies: vec![strategy.into()],
+        }
+    }
+
+    pub fn with_strategies(
+        ticket: FinalProcessorChainResult,
+        score: f64,
+        strategies: Vec<String>,
+    ) -> Self {
+        Self {
+            ticket,
+            score,
+            strategies,
+        }
+    }
+
+    pub fn ticket(&self) -> &FinalProcessorChainResult {
+        &self.ticket
+    }
+
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
+    /// 产出该号码的所有策略
+    pub fn strategies(&self) -> &[String] {
+        &self.strategies
+    }
+
+    pub fn into_ticket(self) -> FinalProcessorChainResult {
+        self.ticket
+    }
+}
+
+/// 把分析结果转换为带得分的候选号码的预测策略
+pub trait Strategy {
+    /// Return the name of the strategy
+    fn name(&self) -> &str;
+
+    /// 计算每个双色球的得分，分数越高越值得选择
+    fn ball_scores(&mut self, context: &ProcessorContext) -> Result<BallScores, Error>;
+
+    /// 生成至多 `size` 注候选号码，默认按照双色球得分组合
+    fn generate(
+        &mut self,
+        context: &ProcessorContext,
+        size: usize,
+    ) -> Result<Vec<ScoredTicket>, Error> {
+        let ball_scores = self.ball_scores(context)?;
+        Ok(tickets_from_ball_scores(&ball_scores, size, self.name()))
+    }
+}
+
+/// 第 i 注使用排名第 i 的蓝球，以及从排名第 i 开始的 6 个红球，
+/// 号码得分为 7 个双色球得分的平均值
+pub(crate) fn tickets_from_ball_scores(
+    ball_scores: &BallScores,
+    size: usize,
+    strategy: &str,
+) -> Vec<ScoredTicket> {
+    let ranked_red_balls = ball_scores.ranked_red_balls();
+    let ranked_blue_balls = ball_scores.ranked_blue_balls();
+    (0..size.min(ranked_blue_balls.len()))
+        .map(|index| {
+            let blue_ball = ranked_blue_balls[index];
+            let red_balls = ranked_red_balls
+                .iter()
+                .cycle()
+                .skip(index)
+                .take(6)
+                .copied()
+                .sorted()
+                .collect::<Vec<RedBall>>();
+            let score = (red_balls
+                .iter()
+                .map(|ball| ball_scores.red[ball])
+                .sum::<f64>()
+                + ball_scores.blue[&blue_ball])
+                / 7.0;
+            ScoredTicket::new(
+                FinalProcessorChainResult::new(blue_ball, red_balls),
+                score,
+                strategy,
+            )
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type")]
+pub enum StrategyConfig {
+    /// 最近一期蓝球的后续蓝球，加上这些蓝球的高支持度红球频繁项集
+    #[serde(rename = "following_blue_fp")]
+    FollowingBlueFp,
+    /// 按照冷热窗口中的出现频率比加权，`window_size` 为空时使用第一个窗口
+    #[serde(rename = "frequency_weighted")]
+    FrequencyWeighted { window_size: Option<usize> },
+    /// 按照当前遗漏期数与平均出现间隔的比值加权
+    #[serde(rename = "omission")]
+    Omission,
+    /// 按照马尔可夫链给出的下期蓝球概率选择蓝球
+    #[serde(rename = "markov_blue")]
+    MarkovBlue,
+    /// 按照关联规则的提升度选择与蓝球关联的红球
+    #[serde(rename = "association_rule")]
+    AssociationRule,
+    /// 均匀随机选择号码
+    #[serde(rename = "uniform_random")]
+    UniformRandom { seed: u64 },
+}
+
+impl StrategyConfig {
+    pub fn build(&self) -> Box<dyn Strategy + Send> {
+        match self {
+            StrategyConfig::FollowingBlueFp => Box::new(FollowingBlueFpStrategy),
+            StrategyConfig::FrequencyWeighted { window_size } => {
+                Box::new(FrequencyWeightedStrategy::new(*window_size))
+            }
+            StrategyConfig::Omission => Box::new(OmissionStrategy),
+            StrategyConfig::MarkovBlue => Box::new(MarkovBlueStrategy),
+            StrategyConfig::AssociationRule => Box::new(AssociationRuleStrategy),
+            StrategyConfig::UniformRandom { seed } => Box::new(UniformRandomStrategy::new(*seed)),
+        }
+    }
+}
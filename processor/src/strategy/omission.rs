@@ -0,0 +1,40 @@
+use crate::BALL_OCCURRENCE;
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::strategy::{BallScores, Strategy};
+use ssq_tool_domain::{Ball, BlueBall, RedBall};
+use std::collections::BTreeMap;
+use strum::IntoEnumIterator;
+
+/// 按照当前遗漏期数与平均出现间隔的比值为双色球加权，遗漏越久得分越高
+pub struct OmissionStrategy;
+
+impl Strategy for OmissionStrategy {
+    fn name(&self) -> &str {
+        "OmissionStrategy"
+    }
+
+    fn ball_scores(&mut self, context: &ProcessorContext) -> Result<BallScores, Error> {
+        let ball_occurrence = context
+            .get_attribute(&BALL_OCCURRENCE)
+            .ok_or(Error::ContextAttrNotExist(BALL_OCCURRENCE.to_string()))?;
+        let total_prized_record_num = context.get_prize_records().len() as f64;
+        // 从未出现过的双色球视为遗漏了全部期数
+        let omission_score = |ball: Ball| {
+            ball_occurrence
+                .get(&ball)
+                .map(|occurrence| {
+                    occurrence.latest_occur_seq() as f64
+                        / (occurrence.average_occur_interval() as f64 + 1.0)
+                })
+                .unwrap_or(total_prized_record_num)
+        };
+        let red = RedBall::iter()
+            .map(|ball| (ball, omission_score(Ball::Red(ball))))
+            .collect::<BTreeMap<RedBall, f64>>();
+        let blue = BlueBall::iter()
+            .map(|ball| (ball, omission_score(Ball::Blue(ball))))
+            .collect::<BTreeMap<BlueBall, f64>>();
+        Ok(BallScores::new(red, blue))
+    }
+}
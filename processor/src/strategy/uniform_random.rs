@@ -0,0 +1,53 @@
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::random_baseline::random_ticket;
+use crate::strategy::{BallScores, ScoredTicket, Strategy};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use ssq_tool_domain::{BlueBall, RedBall};
+use strum::IntoEnumIterator;
+
+/// 均匀随机选择号码，作为其他策略的对照
+pub struct UniformRandomStrategy {
+    rng: StdRng,
+}
+
+impl UniformRandomStrategy {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Strategy for UniformRandomStrategy {
+    fn name(&self) -> &str {
+        "UniformRandomStrategy"
+    }
+
+    fn ball_scores(&mut self, _context: &ProcessorContext) -> Result<BallScores, Error> {
+        let red = RedBall::iter()
+            .map(|ball| (ball, self.rng.random::<f64>()))
+            .collect();
+        let blue = BlueBall::iter()
+            .map(|ball| (ball, self.rng.random::<f64>()))
+            .collect();
+        Ok(BallScores::new(red, blue))
+    }
+
+    fn generate(
+        &mut self,
+        _context: &ProcessorContext,
+        size: usize,
+    ) -> Result<Vec<ScoredTicket>, Error> {
+        (0..size)
+            .map(|_| {
+                Ok(ScoredTicket::new(
+                    random_ticket(&mut self.rng)?,
+                    1.0,
+                    self.name(),
+                ))
+            })
+            .collect()
+    }
+}
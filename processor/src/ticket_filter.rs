@@ -0,0 +1,497 @@
+use crate::context::ProcessorContext;
+use crate::error::Error;
+use crate::progress::{ProgressEvent, ProgressReporter};
+use crate::red_ball_shape::RedBallShape;
+use crate::red_ball_structure::{ac_value, consecutive_runs};
+use crate::strategy::ScoredTicket;
+use crate::{
+    FINAL_PROCESSOR_CHAIN_RESULTS, FinalProcessorChainResult, Processor, SCORED_TICKETS,
+    TICKET_FILTER_HISTORY, TICKET_FILTER_REPORT,
+};
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::{BlueBall, PrBusinessObj, RedBall};
+use strum::{EnumCount, IntoEnumIterator};
+use tracing::info;
+
+/// 红球组合的总数 C(33, 6)
+const RED_BALL_COMBINATION_COUNT: usize = 1_107_568;
+/// 全量缩水时每过滤这么多组红球报告一次进度
+const FULL_SPACE_PROGRESS_STEP: usize = 100_000;
+
+/// 一注号码中红球的特征，每注只计算一次，供所有条件共用
+struct RedBallFeatures {
+    red_balls: Vec<RedBall>,
+    shape: RedBallShape,
+    max_consecutive: usize,
+    ac_value: usize,
+}
+
+impl RedBallFeatures {
+    fn new(red_balls: &[RedBall]) -> Self {
+        Self {
+            red_balls: red_balls.to_vec(),
+            shape: RedBallShape::new(red_balls),
+            max_consecutive: consecutive_runs(red_balls).into_iter().max().unwrap_or(1),
+            ac_value: ac_value(red_balls),
+        }
+    }
+}
+
+/// 可组合的缩水条件，范围条件的上下限都包含在内
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TicketPredicate {
+    /// 红球和值范围
+    SumRange { min: usize, max: usize },
+    /// 红球跨度范围
+    SpanRange { min: usize, max: usize },
+    /// 红球奇数个数范围
+    OddCount { min: usize, max: usize },
+    /// 红球大号个数范围
+    BigCount { min: usize, max: usize },
+    /// 红球质数个数范围
+    PrimeCount { min: usize, max: usize },
+    /// 奇偶比属于给定的比例之一，例如 `3:3`
+    OddEvenRatio { ratios: Vec<String> },
+    /// 三区比属于给定的比例之一，例如 `2:2:2`
+    ZoneRatio { ratios: Vec<String> },
+    /// 最长连号不超过给定长度
+    MaxConsecutive { max: usize },
+    /// AC 值范围
+    AcValue { min: usize, max: usize },
+    /// 至少覆盖给定个数的红球三区
+    ZoneCoverage { min_zones: usize },
+    /// 红球包含所有给定的号码（胆码）
+    RedContainsAll { balls: Vec<RedBall> },
+    /// 红球不包含任何给定的号码（杀号）
+    RedContainsNone { balls: Vec<RedBall> },
+    /// 红球至少包含给定号码中的若干个
+    RedContainsAtLeast { balls: Vec<RedBall>, count: usize },
+    /// 蓝球属于给定的号码之一
+    BlueIn { balls: Vec<BlueBall> },
+    /// 所有条件都满足
+    And { predicates: Vec<TicketPredicate> },
+    /// 任意一个条件满足
+    Or { predicates: Vec<TicketPredicate> },
+    /// 条件不满足
+    Not { predicate: Box<TicketPredicate> },
+}
+
+impl TicketPredicate {
+    fn evaluate(&self, features: &RedBallFeatures, blue_ball: BlueBall) -> bool {
+        let shape = &features.shape;
+        match self {
+            TicketPredicate::SumRange { min, max } => (min..=max).contains(&&shape.sum()),
+            TicketPredicate::SpanRange { min, max } => (min..=max).contains(&&shape.span()),
+            TicketPredicate::OddCount { min, max } => (min..=max).contains(&&shape.odd_count()),
+            TicketPredicate::BigCount { min, max } => (min..=max).contains(&&shape.big_count()),
+            TicketPredicate::PrimeCount { min, max } => (min..=max).contains(&&shape.prime_count()),
+            TicketPredicate::OddEvenRatio { ratios } => ratios.contains(&shape.odd_even_ratio()),
+            TicketPredicate::ZoneRatio { ratios } => ratios.contains(&shape.zone_ratio()),
+            TicketPredicate::MaxConsecutive { max } => features.max_consecutive <= *max,
+            TicketPredicate::AcValue { min, max } => (min..=max).contains(&&features.ac_value),
+            TicketPredicate::ZoneCoverage { min_zones } => {
+                shape
+                    .zone_counts()
+                    .iter()
+                    .filter(|count| **count > 0)
+                    .count()
+                    >= *min_zones
+            }
+            TicketPredicate::RedContainsAll { balls } => {
+                balls.iter().all(|ball| features.red_balls.contains(ball))
+            }
+            TicketPredicate::RedContainsNone { balls } => {
+                balls.iter().all(|ball| !features.red_balls.contains(ball))
+            }
+            TicketPredicate::RedContainsAtLeast { balls, count } => {
+                balls
+                    .iter()
+                    .filter(|ball| features.red_balls.contains(ball))
+                    .count()
+                    >= *count
+            }
+            TicketPredicate::BlueIn { balls } => balls.contains(&blue_ball),
+            TicketPredicate::And { predicates } => predicates
+                .iter()
+                .all(|predicate| predicate.evaluate(features, blue_ball)),
+            TicketPredicate::Or { predicates } => predicates
+                .iter()
+                .any(|predicate| predicate.evaluate(features, blue_ball)),
+            TicketPredicate::Not { predicate } => !predicate.evaluate(features, blue_ball),
+        }
+    }
+
+    /// 条件是否与蓝球有关，与蓝球无关的条件在全量号码空间中只需对每组红球计算一次
+    fn references_blue_ball(&self) -> bool {
+        match self {
+            TicketPredicate::BlueIn { .. } => true,
+            TicketPredicate::And { predicates } | TicketPredicate::Or { predicates } => {
+                predicates.iter().any(TicketPredicate::references_blue_ball)
+            }
+            TicketPredicate::Not { predicate } => predicate.references_blue_ball(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TicketFilter {
+    name: String,
+    predicate: TicketPredicate,
+}
+
+impl TicketFilter {
+    pub fn new(name: impl Into<String>, predicate: TicketPredicate) -> Self {
+        Self {
+            name: name.into(),
+            predicate,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn predicate(&self) -> &TicketPredicate {
+        &self.predicate
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "缩水条件统计")]
+pub struct FilterRemoval {
+    #[serde(rename = "条件名称")]
+    name: String,
+    #[serde(rename = "按顺序过滤掉的注数")]
+    removed: u64,
+    #[serde(rename = "单独不满足的注数")]
+    rejected: u64,
+}
+
+impl FilterRemoval {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 按照条件顺序，第一个不满足的条件为该条件的注数
+    pub fn removed(&self) -> u64 {
+        self.removed
+    }
+
+    /// 不考虑其他条件时该条件不满足的注数
+    pub fn rejected(&self) -> u64 {
+        self.rejected
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "缩水报告")]
+pub struct FilterReport {
+    #[serde(rename = "总注数")]
+    total_count: u64,
+    #[serde(rename = "保留注数")]
+    passed_count: u64,
+    #[serde(rename = "各条件统计")]
+    removals: Vec<FilterRemoval>,
+    #[serde(rename = "保留号码")]
+    passed: Vec<FinalProcessorChainResult>,
+}
+
+impl FilterReport {
+    pub fn total_count(&self) -> u64 {
+        self.total_count
+    }
+
+    pub fn passed_count(&self) -> u64 {
+        self.passed_count
+    }
+
+    pub fn removals(&self) -> &[FilterRemoval] {
+        &self.removals
+    }
+
+    /// 保留下来的号码，全量号码空间下最多只收集给定的注数
+    pub fn passed(&self) -> &[FinalProcessorChainResult] {
+        &self.passed
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "历史缩水结果")]
+pub struct HistoricalFilterResult {
+    #[serde(rename = "中奖期号")]
+    code: String,
+    #[serde(rename = "是否通过")]
+    passed: bool,
+    #[serde(rename = "不满足的条件")]
+    failed_filters: Vec<String>,
+}
+
+impl HistoricalFilterResult {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn passed(&self) -> bool {
+        self.passed
+    }
+
+    pub fn failed_filters(&self) -> &[String] {
+        &self.failed_filters
+    }
+}
+
+/// 缩水执行器过滤的号码范围
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FilterScope {
+    /// 只过滤策略生成的候选号码
+    #[default]
+    Candidates,
+    /// 在过滤候选号码之外过滤全部 C(33, 6) × 16 注号码，缩水报告统计全量号码空间，
+    /// 最多收集 `max_collected` 注保留下来的号码
+    FullSpace { max_collected: usize },
+}
+
+/// 由多个缩水条件组成的过滤引擎，号码需要满足所有条件才会保留
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TicketFilterEngine {
+    filters: Vec<TicketFilter>,
+}
+
+impl TicketFilterEngine {
+    pub fn new(filters: Vec<TicketFilter>) -> Self {
+        Self { filters }
+    }
+
+    pub fn add_filter(mut self, filter: TicketFilter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn filters(&self) -> &[TicketFilter] {
+        &self.filters
+    }
+
+    /// 返回每个条件是否满足
+    fn evaluate(&self, features: &RedBallFeatures, blue_ball: BlueBall) -> Vec<bool> {
+        self.filters
+            .iter()
+            .map(|filter| filter.predicate.evaluate(features, blue_ball))
+            .collect()
+    }
+
+    fn record(removals: &mut [FilterRemoval], results: &[bool], weight: u64) -> bool {
+        let mut first_failure = true;
+        results.iter().enumerate().for_each(|(index, passed)| {
+            if !passed {
+                removals[index].rejected += weight;
+                if first_failure {
+                    removals[index].removed += weight;
+                    first_failure = false;
+                }
+            }
+        });
+        first_failure
+    }
+
+    fn empty_removals(&self) -> Vec<FilterRemoval> {
+        self.filters
+            .iter()
+            .map(|filter| FilterRemoval {
+                name: filter.name.clone(),
+                removed: 0,
+                rejected: 0,
+            })
+            .collect()
+    }
+
+    /// 过滤候选号码
+    pub fn filter_candidates(&self, candidates: &[FinalProcessorChainResult]) -> FilterReport {
+        let mut removals = self.empty_removals();
+        let passed = candidates
+            .iter()
+            .filter(|candidate| {
+                let features = RedBallFeatures::new(candidate.red_ball());
+                let results = self.evaluate(&features, *candidate.blue_ball());
+                Self::record(&mut removals, &results, 1)
+            })
+            .cloned()
+            .collect::<Vec<FinalProcessorChainResult>>();
+        FilterReport {
+            total_count: candidates.len() as u64,
+            passed_count: passed.len() as u64,
+            removals,
+            passed,
+        }
+    }
+
+    /// 过滤全部 C(33, 6) × 16 注号码，最多收集 `max_collected` 注保留下来的号码，
+    /// 每过滤完一批红球组合通过 `progress_reporter` 报告进度，每组红球过滤前通过 `check_cancelled` 检查是否需要停止
+    pub fn filter_full_space(
+        &self,
+        max_collected: usize,
+        progress_reporter: Option<&ProgressReporter>,
+        check_cancelled: impl Fn() -> Result<(), Error>,
+    ) -> Result<FilterReport, Error> {
+        let mut removals = self.empty_removals();
+        let blue_independent = self
+            .filters
+            .iter()
+            .all(|filter| !filter.predicate.references_blue_ball());
+        let mut total_count = 0u64;
+        let mut passed_count = 0u64;
+        let mut passed = Vec::new();
+        let mut filtered_combinations = 0usize;
+        RedBall::iter().combinations(6).try_for_each(|red_balls| {
+            check_cancelled()?;
+            if let Some(progress_reporter) = progress_reporter
+                && filtered_combinations.is_multiple_of(FULL_SPACE_PROGRESS_STEP)
+            {
+                progress_reporter.report(ProgressEvent::new(
+                    "全量缩水",
+                    filtered_combinations,
+                    Some(RED_BALL_COMBINATION_COUNT),
+                    format!("已过滤 {filtered_combinations} 组红球"),
+                ));
+            }
+            filtered_combinations += 1;
+            let features = RedBallFeatures::new(&red_balls);
+            if blue_independent {
+                let results = self.evaluate(&features, BlueBall::V1);
+                let blue_ball_count = BlueBall::COUNT as u64;
+                total_count += blue_ball_count;
+                if Self::record(&mut removals, &results, blue_ball_count) {
+                    passed_count += blue_ball_count;
+                    BlueBall::iter()
+                        .take(max_collected.saturating_sub(passed.len()))
+                        .for_each(|blue_ball| {
+                            passed
+                                .push(FinalProcessorChainResult::new(blue_ball, red_balls.clone()))
+                        });
+                }
+            } else {
+                BlueBall::iter().for_each(|blue_ball| {
+                    let results = self.evaluate(&features, blue_ball);
+                    total_count += 1;
+                    if Self::record(&mut removals, &results, 1) {
+                        passed_count += 1;
+                        if passed.len() < max_collected {
+                            passed
+                                .push(FinalProcessorChainResult::new(blue_ball, red_balls.clone()));
+                        }
+                    }
+                });
+            }
+            Ok::<(), Error>(())
+        })?;
+        info!("全量号码空间 {total_count} 注，缩水后保留 {passed_count} 注");
+        Ok(FilterReport {
+            total_count,
+            passed_count,
+            removals,
+            passed,
+        })
+    }
+
+    /// 用历史开奖号码检验缩水条件，得出哪些开奖期能够通过
+    pub fn historical_results(
+        &self,
+        prize_records: &[PrBusinessObj],
+    ) -> Vec<HistoricalFilterResult> {
+        prize_records
+            .iter()
+            .map(|record| {
+                let features = RedBallFeatures::new(&record.red_balls);
+                let failed_filters = self
+                    .filters
+                    .iter()
+                    .filter(|filter| !filter.predicate.evaluate(&features, record.blue_ball))
+                    .map(|filter| filter.name.clone())
+                    .collect::<Vec<String>>();
+                HistoricalFilterResult {
+                    code: record.code.clone(),
+                    passed: failed_filters.is_empty(),
+                    failed_filters,
+                }
+            })
+            .collect()
+    }
+}
+
+/// 用缩水条件过滤策略生成的候选号码，并用历史开奖号码检验这些条件。
+/// `scope` 为全量号码空间时缩水报告统计全部号码，最终结果仍然来自过滤后的候选号码
+pub struct TicketFilterProcessor {
+    engine: TicketFilterEngine,
+    scope: FilterScope,
+}
+
+impl TicketFilterProcessor {
+    pub fn new(engine: TicketFilterEngine, scope: FilterScope) -> Self {
+        Self { engine, scope }
+    }
+}
+
+#[async_trait::async_trait]
+impl Processor for TicketFilterProcessor {
+    fn name(&self) -> &str {
+        "TicketFilterProcessor"
+    }
+
+    async fn execute(&mut self, context: &mut ProcessorContext) -> Result<(), Error> {
+        let scored_tickets = context
+            .get_attribute(&SCORED_TICKETS)
+            .ok_or(Error::ContextAttrNotExist(SCORED_TICKETS.to_string()))?;
+        let final_result_size = context
+            .get_attribute(&FINAL_PROCESSOR_CHAIN_RESULTS)
+            .map_or(context.expect_result_size(), Vec::len);
+        let candidates = scored_tickets
+            .iter()
+            .map(|scored_ticket| scored_ticket.ticket().clone())
+            .collect::<Vec<FinalProcessorChainResult>>();
+        let candidate_report = self.engine.filter_candidates(&candidates);
+        let passed_scored_tickets = scored_tickets
+            .iter()
+            .filter(|scored_ticket| candidate_report.passed.contains(scored_ticket.ticket()))
+            .cloned()
+            .collect::<Vec<ScoredTicket>>();
+        let report = match self.scope {
+            FilterScope::Candidates => candidate_report,
+            FilterScope::FullSpace { max_collected } => {
+                info!(
+                    "候选号码 {} 注，缩水后保留 {} 注",
+                    candidate_report.total_count, candidate_report.passed_count
+                );
+                self.engine
+                    .filter_full_space(max_collected, context.progress_reporter(), || {
+                        context.check_cancelled(self.name())
+                    })?
+            }
+        };
+        let historical_results = self.engine.historical_results(context.get_prize_records());
+        info!(
+            "候选号码 {} 注，缩水后保留 {} 注，历史开奖号码通过 {} 期，共 {} 期",
+            report.total_count,
+            report.passed_count,
+            historical_results
+                .iter()
+                .filter(|result| result.passed)
+                .count(),
+            historical_results.len()
+        );
+        let final_results = passed_scored_tickets
+            .iter()
+            .take(final_result_size)
+            .map(ScoredTicket::ticket)
+            .cloned()
+            .collect::<Vec<FinalProcessorChainResult>>();
+        context.set_attribute(&SCORED_TICKETS, passed_scored_tickets);
+        context.set_attribute(&FINAL_PROCESSOR_CHAIN_RESULTS, final_results);
+        context.set_attribute(&TICKET_FILTER_REPORT, report);
+        context.set_attribute(&TICKET_FILTER_HISTORY, historical_results);
+        Ok(())
+    }
+}
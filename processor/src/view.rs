@@ -0,0 +1,140 @@
+//! 分析结果的 JSON 友好表示：以双色球为键的映射转换为按照号码排序的列表，
+//! `FPResult` 转换为按照支持度排序的频繁项集列表
+
+use crate::context::OccurrenceDetail;
+use ::fp_growth::algorithm::FPResult;
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::Serialize;
+use ssq_tool_domain::{Ball, BlueBall, RedBall};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename = "双色球出现情况")]
+pub struct BallOccurrenceView {
+    #[serde(rename = "双色球")]
+    pub ball: Ball,
+    #[serde(flatten)]
+    pub detail: OccurrenceDetail,
+}
+
+impl BallOccurrenceView {
+    pub fn from_map(ball_occurrence: &HashMap<Ball, OccurrenceDetail>) -> Vec<Self> {
+        ball_occurrence
+            .iter()
+            .sorted_by_key(|(ball, _)| **ball)
+            .map(|(ball, detail)| Self {
+                ball: *ball,
+                detail: detail.clone(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename = "频繁项集")]
+pub struct FrequentPatternView {
+    #[serde(rename = "红球")]
+    pub red_balls: Vec<RedBall>,
+    #[serde(rename = "支持度")]
+    pub support: usize,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename = "频繁模式挖掘结果")]
+pub struct FpResultView {
+    #[serde(rename = "频繁项集")]
+    pub frequent_patterns: Vec<FrequentPatternView>,
+    #[serde(rename = "排除集")]
+    pub elimination_sets: Vec<Vec<RedBall>>,
+}
+
+impl From<&FPResult<RedBall>> for FpResultView {
+    fn from(fp_result: &FPResult<RedBall>) -> Self {
+        Self {
+            frequent_patterns: fp_result
+                .frequent_patterns()
+                .into_iter()
+                .map(|(red_balls, support)| FrequentPatternView {
+                    red_balls: red_balls.into_iter().sorted().collect(),
+                    support,
+                })
+                .sorted_by(|a, b| {
+                    b.support
+                        .cmp(&a.support)
+                        .then_with(|| a.red_balls.cmp(&b.red_balls))
+                })
+                .collect(),
+            elimination_sets: fp_result
+                .elimination_sets()
+                .into_iter()
+                .map(|red_balls| red_balls.into_iter().sorted().collect::<Vec<RedBall>>())
+                .sorted()
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename = "双色球关联关系")]
+pub struct BallRelationshipView<T> {
+    #[serde(rename = "双色球")]
+    pub ball: T,
+    #[serde(flatten)]
+    pub fp_result: FpResultView,
+}
+
+impl<T: Copy + Ord> BallRelationshipView<T> {
+    pub fn from_map(relationships: &HashMap<T, FPResult<RedBall>>) -> Vec<Self> {
+        relationships
+            .iter()
+            .sorted_by_key(|(ball, _)| **ball)
+            .map(|(ball, fp_result)| Self {
+                ball: *ball,
+                fp_result: fp_result.into(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename = "后续蓝球")]
+pub struct FollowingBlueBallView {
+    #[serde(rename = "蓝球")]
+    pub blue_ball: BlueBall,
+    #[serde(rename = "次数")]
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename = "蓝球后续出现情况")]
+pub struct BlueBallFollowingView {
+    #[serde(rename = "蓝球")]
+    pub blue_ball: BlueBall,
+    /// 按照次数从多到少排列
+    #[serde(rename = "后续蓝球")]
+    pub following: Vec<FollowingBlueBallView>,
+}
+
+impl BlueBallFollowingView {
+    pub fn from_map(
+        following_occurrences: &HashMap<BlueBall, HashMap<BlueBall, usize>>,
+    ) -> Vec<Self> {
+        following_occurrences
+            .iter()
+            .sorted_by_key(|(blue_ball, _)| **blue_ball)
+            .map(|(blue_ball, following)| Self {
+                blue_ball: *blue_ball,
+                following: following
+                    .iter()
+                    .sorted_by_key(|(blue_ball, count)| (Reverse(**count), **blue_ball))
+                    .map(|(blue_ball, count)| FollowingBlueBallView {
+                        blue_ball: *blue_ball,
+                        count: *count,
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
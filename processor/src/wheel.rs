@@ -0,0 +1,286 @@
+use crate::FinalProcessorChainResult;
+use crate::error::Error;
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssq_tool_domain::{BlueBall, RedBall};
+use tracing::{debug, info};
+
+/// 每注红球个数
+const TICKET_SIZE: usize = 6;
+/// 旋转矩阵支持的最大选号个数，超过后组合数增长过快
+const MAX_POOL_SIZE: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "旋转矩阵")]
+pub struct Wheel {
+    #[serde(rename = "选号")]
+    pool: Vec<RedBall>,
+    #[serde(rename = "保证命中个数")]
+    guarantee: usize,
+    #[serde(rename = "开出个数")]
+    drawn: usize,
+    #[serde(rename = "红球组合")]
+    red_ball_combinations: Vec<Vec<RedBall>>,
+    #[serde(rename = "复式注数")]
+    full_combination_count: usize,
+}
+
+impl Wheel {
+    pub fn pool(&self) -> &[RedBall] {
+        &self.pool
+    }
+
+    pub fn guarantee(&self) -> usize {
+        self.guarantee
+    }
+
+    pub fn drawn(&self) -> usize {
+        self.drawn
+    }
+
+    /// 旋转矩阵中的每组红球
+    pub fn red_ball_combinations(&self) -> &[Vec<RedBall>] {
+        &self.red_ball_combinations
+    }
+
+    /// 同样选号的复式投注需要的注数
+    pub fn full_combination_count(&self) -> usize {
+        self.full_combination_count
+    }
+
+    /// 把每组红球与每个蓝球组合成单式号码
+    pub fn tickets(&self, blue_balls: &[BlueBall]) -> Vec<FinalProcessorChainResult> {
+        self.red_ball_combinations
+            .iter()
+            .cartesian_product(blue_balls)
+            .map(|(red_balls, blue_ball)| {
+                FinalProcessorChainResult::new(*blue_ball, red_balls.clone())
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename = "旋转矩阵校验结果")]
+pub struct WheelVerification {
+    #[serde(rename = "开奖情况总数")]
+    total: usize,
+    #[serde(rename = "满足保证的情况数")]
+    covered: usize,
+    #[serde(rename = "未满足保证的示例")]
+    uncovered_example: Option<Vec<RedBall>>,
+}
+
+impl WheelVerification {
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    pub fn covered(&self) -> usize {
+        self.covered
+    }
+
+    /// 一个不满足保证的开出号码组合
+    pub fn uncovered_example(&self) -> Option<&[RedBall]> {
+        self.uncovered_example.as_deref()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.covered == self.total
+    }
+}
+
+/// 旋转矩阵生成器：从 v 个选号中生成若干组 6 个红球，保证只要选号中开出 m 个红球，
+/// 至少有一组红球命中其中 t 个
+pub struct WheelGenerator {
+    pool: Vec<RedBall>,
+    guarantee: usize,
+    drawn: usize,
+}
+
+impl WheelGenerator {
+    /// `guarantee` 为保证命中个数 t，`drawn` 为选号中开出的红球个数 m
+    pub fn new(pool: Vec<RedBall>, guarantee: usize, drawn: usize) -> Result<Self, Error> {
+        let pool = pool.into_iter().sorted().dedup().collect::<Vec<RedBall>>();
+        if pool.len() < TICKET_SIZE || pool.len() > MAX_POOL_SIZE {
+            return Err(Error::InvalidProcessorConfig(format!(
+                "旋转矩阵的选号个数必须在 {TICKET_SIZE} 到 {MAX_POOL_SIZE} 之间：{}",
+                pool.len()
+            )));
+        }
+        if guarantee == 0 || guarantee > drawn || drawn > TICKET_SIZE {
+            return Err(Error::InvalidProcessorConfig(format!(
+                "旋转矩阵的保证条件必须满足 1 ≤ t ≤ m ≤ {TICKET_SIZE}：t = {guarantee}，m = {drawn}"
+            )));
+        }
+        Ok(Self {
+            pool,
+            guarantee,
+            drawn,
+        })
+    }
+
+    fn mask_to_red_balls(&self, mask: u32) -> Vec<RedBall> {
+        self.pool
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| mask & (1 << index) != 0)
+            .map(|(_, ball)| *ball)
+            .collect()
+    }
+
+    fn subsets(&self, size: usize) -> Vec<u32> {
+        (0..self.pool.len())
+            .combinations(size)
+            .map(|indexes| indexes.iter().fold(0u32, |mask, index| mask | (1 << index)))
+            .collect()
+    }
+
+    fn covers(&self, ticket: u32, drawn: u32) -> bool {
+        (ticket & drawn).count_ones() as usize >= self.guarantee
+    }
+
+    /// 所有能够满足给定开出组合的红球组合
+    fn covering_tickets(&self, drawn: u32) -> Vec<u32> {
+        let drawn_indexes = (0..self.pool.len())
+            .filter(|index| drawn & (1 << index) != 0)
+            .collect::<Vec<usize>>();
+        let other_indexes = (0..self.pool.len())
+            .filter(|index| drawn & (1 << index) == 0)
+            .collect::<Vec<usize>>();
+        (self.guarantee..=self.drawn.min(TICKET_SIZE))
+            .filter(|hits| TICKET_SIZE - hits <= other_indexes.len())
+            .flat_map(|hits| {
+                drawn_indexes
+                    .iter()
+                    .combinations(hits)
+                    .cartesian_product(
+                        other_indexes
+                            .iter()
+                            .combinations(TICKET_SIZE - hits)
+                            .collect::<Vec<_>>(),
+                    )
+                    .map(|(hit_indexes, other_indexes)| {
+                        hit_indexes
+                            .into_iter()
+                            .chain(other_indexes)
+                            .fold(0u32, |mask, index| mask | (1 << index))
+                    })
+                    .collect::<Vec<u32>>()
+            })
+            .collect()
+    }
+
+    /// 贪心生成旋转矩阵：每次取第一个尚未满足的开出组合，在能满足它的红球组合中
+    /// 选择满足最多未满足组合的一组，最后去掉多余的红球组合。
+    /// 每选出一组红球前通过 `check_cancelled` 检查是否需要停止
+    pub fn generate(
+        &self,
+        check_cancelled: impl Fn() -> Result<(), Error>,
+    ) -> Result<Wheel, Error> {
+        let mut uncovered = self.subsets(self.drawn);
+        let mut tickets = Vec::<u32>::new();
+        while let Some(first_uncovered) = uncovered.first().copied() {
+            check_cancelled()?;
+            let best_ticket = self
+                .covering_tickets(first_uncovered)
+                .into_iter()
+                .max_by_key(|ticket| {
+                    uncovered
+                        .iter()
+                        .filter(|drawn| self.covers(*ticket, **drawn))
+                        .count()
+                })
+                .unwrap_or(first_uncovered);
+            uncovered.retain(|drawn| !self.covers(best_ticket, *drawn));
+            tickets.push(best_ticket);
+            debug!(
+                "旋转矩阵已选出 {} 组，剩余 {} 种开出组合未满足",
+                tickets.len(),
+                uncovered.len()
+            );
+        }
+        let tickets = self.remove_redundant(tickets, &check_cancelled)?;
+        let full_combination_count = self.subsets(TICKET_SIZE).len();
+        info!(
+            "从 {} 个选号生成中 {} 保 {} 旋转矩阵，共 {} 注，复式需要 {full_combination_count} 注",
+            self.pool.len(),
+            self.drawn,
+            self.guarantee,
+            tickets.len()
+        );
+        Ok(Wheel {
+            pool: self.pool.clone(),
+            guarantee: self.guarantee,
+            drawn: self.drawn,
+            red_ball_combinations: tickets
+                .into_iter()
+                .map(|ticket| self.mask_to_red_balls(ticket))
+                .collect(),
+            full_combination_count,
+        })
+    }
+
+    /// 去掉所满足的开出组合都能被其他红球组合满足的红球组合
+    fn remove_redundant(
+        &self,
+        mut tickets: Vec<u32>,
+        check_cancelled: impl Fn() -> Result<(), Error>,
+    ) -> Result<Vec<u32>, Error> {
+        let drawn_subsets = self.subsets(self.drawn);
+        let mut cover_counts = drawn_subsets
+            .iter()
+            .map(|drawn| {
+                tickets
+                    .iter()
+                    .filter(|ticket| self.covers(**ticket, *drawn))
+                    .count()
+            })
+            .collect::<Vec<usize>>();
+        let mut index = tickets.len();
+        while index > 0 {
+            check_cancelled()?;
+            index -= 1;
+            let ticket = tickets[index];
+            let redundant = drawn_subsets
+                .iter()
+                .zip(&cover_counts)
+                .all(|(drawn, count)| !self.covers(ticket, *drawn) || *count > 1);
+            if redundant {
+                drawn_subsets
+                    .iter()
+                    .zip(cover_counts.iter_mut())
+                    .filter(|(drawn, _)| self.covers(ticket, **drawn))
+                    .for_each(|(_, count)| *count -= 1);
+                tickets.remove(index);
+            }
+        }
+        Ok(tickets)
+    }
+
+    /// 校验一组红球组合是否满足保证条件
+    pub fn verify(&self, red_ball_combinations: &[Vec<RedBall>]) -> WheelVerification {
+        let tickets = red_ball_combinations
+            .iter()
+            .map(|red_balls| {
+                red_balls
+                    .iter()
+                    .filter_map(|ball| self.pool.iter().position(|pool_ball| pool_ball == ball))
+                    .fold(0u32, |mask, index| mask | (1 << index))
+            })
+            .collect::<Vec<u32>>();
+        let drawn_subsets = self.subsets(self.drawn);
+        let uncovered = drawn_subsets
+            .iter()
+            .filter(|drawn| !tickets.iter().any(|ticket| self.covers(*ticket, **drawn)))
+            .collect::<Vec<&u32>>();
+        WheelVerification {
+            total: drawn_subsets.len(),
+            covered: drawn_subsets.len() - uncovered.len(),
+            uncovered_example: uncovered
+                .first()
+                .map(|drawn| self.mask_to_red_balls(**drawn)),
+        }
+    }
+}